@@ -0,0 +1,43 @@
+-- Mirror of the MarketplaceData jobs, amounts are kept as exact integers
+CREATE TABLE jobs (
+    job_id BIGINT PRIMARY KEY,
+    title TEXT NOT NULL,
+    state SMALLINT NOT NULL,
+    creator TEXT NOT NULL,
+    worker TEXT NOT NULL,
+    arbitrator TEXT NOT NULL,
+    tags TEXT[] NOT NULL,
+    content_hash TEXT NOT NULL,
+    token TEXT NOT NULL,
+    amount NUMERIC(78, 0) NOT NULL,
+    delivery_method TEXT NOT NULL,
+    max_time BIGINT NOT NULL,
+    collateral_owed NUMERIC(78, 0) NOT NULL,
+    escrow_id NUMERIC(78, 0) NOT NULL,
+    result_hash TEXT NOT NULL,
+    rating SMALLINT NOT NULL,
+    disputed BOOLEAN NOT NULL,
+    multiple_applicants BOOLEAN NOT NULL,
+    whitelist_workers BOOLEAN NOT NULL,
+    -- JobPost.timestamp, unix seconds
+    job_timestamp BIGINT NOT NULL,
+    indexed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX jobs_creator_idx ON jobs (creator);
+CREATE INDEX jobs_state_idx ON jobs (state);
+
+-- Complete event history, `event_index` is the index in getEvents
+CREATE TABLE job_events (
+    job_id BIGINT NOT NULL REFERENCES jobs (job_id) ON DELETE CASCADE,
+    event_index BIGINT NOT NULL,
+    event_type SMALLINT NOT NULL,
+    -- JobEventType name, e.g. "Created"
+    event_name TEXT NOT NULL,
+    address BYTEA NOT NULL,
+    data BYTEA NOT NULL,
+    event_timestamp BIGINT NOT NULL,
+    PRIMARY KEY (job_id, event_index)
+);
+
+CREATE INDEX job_events_type_idx ON job_events (event_type, event_timestamp);
@@ -0,0 +1,5 @@
+-- Last block whose JobEvent logs are mirrored, a single row
+CREATE TABLE indexer_state (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    last_block BIGINT NOT NULL
+);
@@ -0,0 +1,456 @@
+use alloy::primitives::{Address, B256};
+use alloy::providers::Provider;
+use alloy::rpc::types::Log;
+use eyre::Result;
+use futures::stream::StreamExt;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+use crate::{
+    error::AppError, telegram_api::TelegramClient, MarketPlaceData, MarketplaceProxy, Notification,
+    MARKETPLACE_DATA_ADDRESS,
+};
+
+const ARBISCAN_URL: &str = "https://arbiscan.io";
+// Attempts at sending an alert before giving up
+const ALERT_ATTEMPTS: u32 = 3;
+// Delay before starting the admin worker again after it failed
+const WORKER_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+// Operator alerts, set under `admin` in the config file
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AdminSettings {
+    pub enabled: bool,
+    // Private chat of the operators, defaults to TG_ADMIN_CHAT_ID
+    pub chat_id: Option<String>,
+    // Hold back announcements while the marketplace is paused
+    pub suspend_while_paused: bool,
+    // Seconds between two reads of `paused()`, in case an event is missed
+    pub poll_secs: u64,
+}
+
+impl Default for AdminSettings {
+    fn default() -> Self {
+        AdminSettings {
+            enabled: false,
+            chat_id: None,
+            suspend_while_paused: true,
+            poll_secs: 300,
+        }
+    }
+}
+
+impl AdminSettings {
+    // None when neither `chat_id` nor TG_ADMIN_CHAT_ID is set
+    pub fn chat_id(&self) -> Option<String> {
+        self.chat_id
+            .clone()
+            .or_else(|| env::var("TG_ADMIN_CHAT_ID").ok())
+    }
+}
+
+// Security relevant events of MarketplaceData and the Marketplace proxy
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminEvent {
+    Paused { account: Address },
+    Unpaused { account: Address },
+    OwnershipTransferred { previous: Address, new: Address },
+    MarketplaceAddressChanged { marketplace: Address },
+    Upgraded { implementation: Address },
+    AdminChanged { previous: Address, new: Address },
+    // `paused()` changed without an event being seen
+    PauseDrift { paused: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdminAlert {
+    pub event: AdminEvent,
+    // Contract that emitted the event
+    pub contract: Address,
+    pub tx_hash: Option<B256>,
+}
+
+impl AdminAlert {
+    pub fn new(event: AdminEvent, contract: Address, log: Option<&Log>) -> Self {
+        AdminAlert {
+            event,
+            contract,
+            tx_hash: log.and_then(|log| log.transaction_hash),
+        }
+    }
+}
+
+fn address_link(address: &Address) -> String {
+    format!(
+        "<a href=\"{}/address/{}\">{}</a>",
+        ARBISCAN_URL, address, address
+    )
+}
+
+// HTML text of an alert, sent as is to the operator chat
+pub fn render_alert(alert: &AdminAlert) -> String {
+    let (title, details) = match &alert.event {
+        AdminEvent::Paused { account } => (
+            "Marketplace paused".to_string(),
+            format!(
+                "By {}\nAnnouncements are held back until it is unpaused",
+                address_link(account)
+            ),
+        ),
+        AdminEvent::Unpaused { account } => (
+            "Marketplace unpaused".to_string(),
+            format!("By {}\nAnnouncements resume", address_link(account)),
+        ),
+        AdminEvent::OwnershipTransferred { previous, new } => (
+            "Ownership transferred".to_string(),
+            format!("From {}\nTo {}", address_link(previous), address_link(new)),
+        ),
+        AdminEvent::MarketplaceAddressChanged { marketplace } => (
+            "Marketplace address changed".to_string(),
+            format!("New marketplace {}", address_link(marketplace)),
+        ),
+        AdminEvent::Upgraded { implementation } => (
+            "Marketplace upgraded".to_string(),
+            format!("New implementation {}", address_link(implementation)),
+        ),
+        AdminEvent::AdminChanged { previous, new } => (
+            "Proxy admin changed".to_string(),
+            format!("From {}\nTo {}", address_link(previous), address_link(new)),
+        ),
+        AdminEvent::PauseDrift { paused } => (
+            format!(
+                "Marketplace {}",
+                if *paused { "paused" } else { "unpaused" }
+            ),
+            "Read from paused(), the event was missed".to_string(),
+        ),
+    };
+    let mut text = format!(
+        "🚨 <b>{}</b>\n{}\nContract: {}",
+        title,
+        details,
+        address_link(&alert.contract)
+    );
+    if let Some(tx_hash) = &alert.tx_hash {
+        text.push_str(&format!(
+            "\n<a href=\"{}/tx/{}\">Transaction</a>",
+            ARBISCAN_URL, tx_hash
+        ));
+    }
+    text
+}
+
+// Pause state of MarketplaceData, shared by the workers posting announcements
+#[derive(Debug)]
+pub struct PauseState {
+    paused: watch::Sender<bool>,
+}
+
+impl Default for PauseState {
+    fn default() -> Self {
+        PauseState {
+            paused: watch::Sender::new(false),
+        }
+    }
+}
+
+impl PauseState {
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    // Returns whether the state changed
+    pub fn set(&self, paused: bool) -> bool {
+        self.paused.send_if_modified(|state| {
+            let changed = *state != paused;
+            *state = paused;
+            changed
+        })
+    }
+
+    // Edits of existing posts still go through, new announcements wait for the unpause
+    pub fn allows(&self, notification: &Notification) -> bool {
+        !self.is_paused() || matches!(notification, Notification::JobUpdated(_))
+    }
+}
+
+// Forward the notifications of `rx` to `tx`, holding announcements back while paused
+// Held notifications are released in order on the unpause, with the edits of their jobs
+#[tracing::instrument(name = "pause_gate", skip(rx, pause, tx))]
+pub async fn pause_gate(
+    mut rx: mpsc::Receiver<Notification>,
+    pause: Arc<PauseState>,
+    tx: mpsc::Sender<Notification>,
+) {
+    let mut changes = pause.paused.subscribe();
+    let mut held: VecDeque<Notification> = VecDeque::new();
+    loop {
+        // The unpause is seen before the notifications sent after it
+        let notification = tokio::select! {
+            biased;
+            Ok(()) = changes.changed() => None,
+            notification = rx.recv() => match notification {
+                Some(notification) => Some(notification),
+                None => return,
+            },
+        };
+        if !pause.is_paused() && !held.is_empty() {
+            tracing::info!(
+                "Marketplace unpaused, releasing {} notifications",
+                held.len()
+            );
+            for notification in held.drain(..) {
+                if tx.send(notification).await.is_err() {
+                    return;
+                }
+            }
+        }
+        let Some(notification) = notification else {
+            continue;
+        };
+        // An edit can't overtake the announcement of its job
+        let job_held = held
+            .iter()
+            .any(|held| held.job_id() == notification.job_id());
+        if job_held || !pause.allows(&notification) {
+            tracing::info!(
+                "Marketplace paused, holding back notification of job {}",
+                notification.job_id()
+            );
+            held.push_back(notification);
+            continue;
+        }
+        if tx.send(notification).await.is_err() {
+            return;
+        }
+    }
+}
+
+// Notification queue, gated by `pause` when set
+pub fn gated_channel(
+    pause: Option<Arc<PauseState>>,
+    buffer: usize,
+) -> (mpsc::Sender<Notification>, mpsc::Receiver<Notification>) {
+    let (tx, rx) = mpsc::channel::<Notification>(buffer);
+    match pause {
+        Some(pause) => {
+            let (gated_tx, gated_rx) = mpsc::channel::<Notification>(buffer);
+            tokio::spawn(pause_gate(rx, pause, gated_tx));
+            (tx, gated_rx)
+        }
+        None => (tx, rx),
+    }
+}
+
+// Send an alert to the operator chat, retrying as it shouldn't get lost
+#[tracing::instrument(name = "send_alert", skip(api))]
+pub async fn send_alert(
+    api: &TelegramClient,
+    chat_id: &str,
+    alert: &AdminAlert,
+) -> Result<(), AppError> {
+    tracing::warn!("Admin event: {:?}", alert);
+    let text = render_alert(alert);
+    let mut attempt = 1;
+    loop {
+        match api.send_message(chat_id, &text).await {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt < ALERT_ATTEMPTS => {
+                tracing::error!("Failed to send admin alert, attempt {}: {}", attempt, e);
+                tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Follow the admin events and the pause state, alerting the operators on each change
+// The pause gate relies on it, so it is started again whenever it stops
+#[tracing::instrument(name = "admin_worker", skip(provider, settings, pause, api))]
+pub async fn admin_worker<P: Provider + Clone>(
+    provider: P,
+    settings: AdminSettings,
+    chat_id: String,
+    pause: Arc<PauseState>,
+    api: TelegramClient,
+) {
+    loop {
+        match watch_admin_events(provider.clone(), &settings, &chat_id, &pause, &api).await {
+            Ok(()) => tracing::error!("Admin event streams ended, restarting"),
+            Err(e) => tracing::error!("Admin worker failed, restarting: {}", e),
+        }
+        tokio::time::sleep(WORKER_RETRY_DELAY).await;
+    }
+}
+
+async fn watch_admin_events<P: Provider + Clone>(
+    provider: P,
+    settings: &AdminSettings,
+    chat_id: &str,
+    pause: &PauseState,
+    api: &TelegramClient,
+) -> Result<()> {
+    let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider.clone());
+    let mut paused = marketplace_data
+        .Paused_filter()
+        .subscribe()
+        .await?
+        .into_stream();
+    let mut unpaused = marketplace_data
+        .Unpaused_filter()
+        .subscribe()
+        .await?
+        .into_stream();
+    let mut ownership_transferred = marketplace_data
+        .OwnershipTransferred_filter()
+        .subscribe()
+        .await?
+        .into_stream();
+    let mut marketplace_changed = marketplace_data
+        .MarketplaceAddressChanged_filter()
+        .subscribe()
+        .await?
+        .into_stream();
+
+    // Read after subscribing so a pause in between isn't missed
+    pause.set(marketplace_data.paused().call().await?._0);
+    if pause.is_paused() {
+        tracing::warn!("Marketplace is paused, announcements are held back");
+    }
+    let mut poll = tokio::time::interval(Duration::from_secs(settings.poll_secs.max(30)));
+
+    // The proxy streams follow the marketplace address, they are opened again when it changes
+    loop {
+        let marketplace_address = marketplace_data.marketplace().call().await?._0;
+        let marketplace = MarketplaceProxy::new(marketplace_address, provider.clone());
+        let mut upgraded = marketplace
+            .Upgraded_filter()
+            .subscribe()
+            .await?
+            .into_stream();
+        let mut admin_changed = marketplace
+            .AdminChanged_filter()
+            .subscribe()
+            .await?
+            .into_stream();
+        tracing::info!("Watching the marketplace proxy {}", marketplace_address);
+
+        loop {
+            let alert = tokio::select! {
+                log = paused.next() => match log {
+                    Some(Ok((event, log))) => {
+                        pause.set(true);
+                        AdminAlert::new(
+                            AdminEvent::Paused { account: event.account },
+                            MARKETPLACE_DATA_ADDRESS,
+                            Some(&log),
+                        )
+                    }
+                    Some(Err(e)) => {
+                        tracing::error!("Error in Paused stream: {:?}", e);
+                        continue;
+                    }
+                    None => return Err(eyre::eyre!("Paused stream ended")),
+                },
+                log = unpaused.next() => match log {
+                    Some(Ok((event, log))) => {
+                        pause.set(false);
+                        AdminAlert::new(
+                            AdminEvent::Unpaused { account: event.account },
+                            MARKETPLACE_DATA_ADDRESS,
+                            Some(&log),
+                        )
+                    }
+                    Some(Err(e)) => {
+                        tracing::error!("Error in Unpaused stream: {:?}", e);
+                        continue;
+                    }
+                    None => return Err(eyre::eyre!("Unpaused stream ended")),
+                },
+                log = ownership_transferred.next() => match log {
+                    Some(Ok((event, log))) => AdminAlert::new(
+                        AdminEvent::OwnershipTransferred {
+                            previous: event.previousOwner,
+                            new: event.newOwner,
+                        },
+                        MARKETPLACE_DATA_ADDRESS,
+                        Some(&log),
+                    ),
+                    Some(Err(e)) => {
+                        tracing::error!("Error in OwnershipTransferred stream: {:?}", e);
+                        continue;
+                    }
+                    None => return Err(eyre::eyre!("OwnershipTransferred stream ended")),
+                },
+                log = marketplace_changed.next() => match log {
+                    Some(Ok((event, log))) => {
+                        let alert = AdminAlert::new(
+                            AdminEvent::MarketplaceAddressChanged {
+                                marketplace: event.marketplaceAddress,
+                            },
+                            MARKETPLACE_DATA_ADDRESS,
+                            Some(&log),
+                        );
+                        if let Err(e) = send_alert(api, chat_id, &alert).await {
+                            tracing::error!("Failed to send admin alert: {}", e);
+                        }
+                        break;
+                    }
+                    Some(Err(e)) => {
+                        tracing::error!("Error in MarketplaceAddressChanged stream: {:?}", e);
+                        continue;
+                    }
+                    None => return Err(eyre::eyre!("MarketplaceAddressChanged stream ended")),
+                },
+                log = upgraded.next() => match log {
+                    Some(Ok((event, log))) => AdminAlert::new(
+                        AdminEvent::Upgraded { implementation: event.implementation },
+                        marketplace_address,
+                        Some(&log),
+                    ),
+                    Some(Err(e)) => {
+                        tracing::error!("Error in Upgraded stream: {:?}", e);
+                        continue;
+                    }
+                    None => return Err(eyre::eyre!("Upgraded stream ended")),
+                },
+                log = admin_changed.next() => match log {
+                    Some(Ok((event, log))) => AdminAlert::new(
+                        AdminEvent::AdminChanged {
+                            previous: event.previousAdmin,
+                            new: event.newAdmin,
+                        },
+                        marketplace_address,
+                        Some(&log),
+                    ),
+                    Some(Err(e)) => {
+                        tracing::error!("Error in AdminChanged stream: {:?}", e);
+                        continue;
+                    }
+                    None => return Err(eyre::eyre!("AdminChanged stream ended")),
+                },
+                _ = poll.tick() => match marketplace_data.paused().call().await {
+                    Ok(state) if pause.set(state._0) => AdminAlert::new(
+                        AdminEvent::PauseDrift { paused: state._0 },
+                        MARKETPLACE_DATA_ADDRESS,
+                        None,
+                    ),
+                    Ok(_) => continue,
+                    Err(e) => {
+                        tracing::error!("Failed to read the pause state: {}", e);
+                        continue;
+                    }
+                },
+            };
+            if let Err(e) = send_alert(api, chat_id, &alert).await {
+                tracing::error!("Failed to send admin alert: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use alloy::primitives::{utils::format_units, U256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Subscript digits used by the small-amount notation
+const SUBSCRIPT_DIGITS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+
+// How amounts are displayed, set under `number_format` in the config file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AmountFormat {
+    // "en" (1,234.5), "de" (1.234,5), "fr" (1 234,5) or "ch" (1'234.5)
+    pub locale: String,
+    // Round to this many significant digits, integer digits are always kept
+    // None displays the exact amount stored onchain
+    pub significant_digits: Option<u32>,
+    // Amounts below 1 with at least this many leading fractional zeros
+    // are written 0.0₅1234 (five zeros then 1234)
+    pub small_amount_zeros: usize,
+}
+
+impl Default for AmountFormat {
+    fn default() -> Self {
+        AmountFormat {
+            locale: "en".to_string(),
+            significant_digits: None,
+            small_amount_zeros: 4,
+        }
+    }
+}
+
+impl AmountFormat {
+    fn separators(&self) -> (&'static str, &'static str) {
+        match self.locale.as_str() {
+            "de" | "es" | "it" | "nl" | "pt" => (".", ","),
+            "fr" => ("\u{202f}", ","),
+            "ch" => ("'", "."),
+            _ => (",", "."),
+        }
+    }
+
+    fn group_thousands(&self, digits: &str) -> String {
+        let (thousands, _) = self.separators();
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push_str(thousands);
+            }
+            grouped.push(c);
+        }
+        grouped
+    }
+
+    // Format an exact decimal string such as "1234.5000"
+    pub fn format_decimal_str(&self, value: &str) -> String {
+        let (_, decimal) = self.separators();
+        let (int_part, frac_part) = value.split_once('.').unwrap_or((value, ""));
+        let (sign, digits) = match int_part.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", int_part),
+        };
+        let grouped = self.group_thousands(digits);
+
+        let frac_part = frac_part.trim_end_matches('0');
+        if frac_part.is_empty() {
+            return format!("{}{}", sign, grouped);
+        }
+
+        let leading_zeros = frac_part.len() - frac_part.trim_start_matches('0').len();
+        if grouped == "0" && self.small_amount_zeros > 0 && leading_zeros >= self.small_amount_zeros
+        {
+            let subscript: String = leading_zeros
+                .to_string()
+                .chars()
+                .map(|c| SUBSCRIPT_DIGITS[c.to_digit(10).unwrap_or_default() as usize])
+                .collect();
+            return format!(
+                "{}0{}0{}{}",
+                sign,
+                decimal,
+                subscript,
+                &frac_part[leading_zeros..]
+            );
+        }
+        format!("{}{}{}{}", sign, grouped, decimal, frac_part)
+    }
+
+    // Dollar value rounded to cents: $1,234.50
+    pub fn format_usd(&self, value: f64) -> String {
+        let (_, decimal) = self.separators();
+        let cents = format!("{:.2}", value.abs());
+        let (int_part, frac_part) = cents.split_once('.').unwrap_or((&cents, "00"));
+        let sign = if value < 0.0 { "-" } else { "" };
+        format!(
+            "{}${}{}{}",
+            sign,
+            self.group_thousands(int_part),
+            decimal,
+            frac_part
+        )
+    }
+}
+
+// Token amount as stored by the contract, raw units plus token decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "TokenAmountRepr", into = "TokenAmountRepr")]
+pub struct TokenAmount {
+    pub raw: U256,
+    pub decimals: u8,
+}
+
+// Serialized form, `value` is the exact decimal amount for templates
+#[derive(Serialize, Deserialize)]
+struct TokenAmountRepr {
+    raw: String,
+    decimals: u8,
+    #[serde(default)]
+    value: String,
+}
+
+impl From<TokenAmount> for TokenAmountRepr {
+    fn from(amount: TokenAmount) -> Self {
+        TokenAmountRepr {
+            raw: amount.raw.to_string(),
+            decimals: amount.decimals,
+            value: amount.to_decimal_string(),
+        }
+    }
+}
+
+impl TryFrom<TokenAmountRepr> for TokenAmount {
+    type Error = String;
+
+    fn try_from(repr: TokenAmountRepr) -> Result<Self, Self::Error> {
+        let raw = repr
+            .raw
+            .parse::<U256>()
+            .map_err(|e| format!("Invalid raw amount {}: {}", repr.raw, e))?;
+        Ok(TokenAmount::new(raw, repr.decimals))
+    }
+}
+
+impl TokenAmount {
+    pub fn new(raw: U256, decimals: u8) -> Self {
+        TokenAmount { raw, decimals }
+    }
+
+    // Exact decimal representation without trailing zeros: 1500.25
+    pub fn to_decimal_string(&self) -> String {
+        let formatted =
+            format_units(self.raw, self.decimals).unwrap_or_else(|_| self.raw.to_string());
+        match formatted.split_once('.') {
+            Some((int_part, frac_part)) => {
+                let frac_part = frac_part.trim_end_matches('0');
+                if frac_part.is_empty() {
+                    int_part.to_string()
+                } else {
+                    format!("{}.{}", int_part, frac_part)
+                }
+            }
+            None => formatted,
+        }
+    }
+
+    // Lossy conversion, only meant for valuations and comparisons
+    pub fn to_f64(&self) -> f64 {
+        self.to_decimal_string().parse().unwrap_or_default()
+    }
+
+    // Round half up to `digits` significant digits without touching integer digits
+    pub fn round_significant(&self, digits: u32) -> TokenAmount {
+        let raw_digits = self.raw.to_string().len() as u32;
+        if digits == 0 || raw_digits <= digits {
+            return *self;
+        }
+        let drop = (raw_digits - digits).min(u32::from(self.decimals));
+        if drop == 0 {
+            return *self;
+        }
+        let unit = U256::from(10).pow(U256::from(drop));
+        let half = unit / U256::from(2);
+        let rounded = self.raw.saturating_add(half) / unit * unit;
+        TokenAmount::new(rounded, self.decimals)
+    }
+
+    pub fn format(&self, format: &AmountFormat) -> String {
+        let amount = match format.significant_digits {
+            Some(digits) => self.round_significant(digits),
+            None => *self,
+        };
+        format.format_decimal_str(&amount.to_decimal_string())
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&AmountFormat::default()))
+    }
+}
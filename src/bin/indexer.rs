@@ -0,0 +1,36 @@
+// Mirror every job and its event history into Postgres, then follow the live JobEvent stream
+// Usage: cargo run --bin indexer [--backfill-only]
+use alloy::providers::{ProviderBuilder, WsConnect};
+use eacc_rs::config::Settings;
+use eacc_rs::indexer::{Indexer, JobStore};
+use eacc_rs::telemetry::{get_subscriber, init_subscriber};
+use std::env;
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    dotenvy::dotenv().ok();
+    let rpc_api = env::var("RPC_API").expect("RPC_API not set");
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL not found in .env file");
+    let log_level = env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
+    let subscriber = get_subscriber("eacc_indexer".into(), log_level, std::io::stdout);
+    init_subscriber(subscriber);
+
+    let settings = Settings::load()?;
+    let ws = WsConnect::new(format!(
+        "wss://arbitrum-mainnet.infura.io/ws/v3/{}",
+        rpc_api
+    ));
+    let provider = ProviderBuilder::new().on_ws(ws).await?;
+
+    // Migrations are applied on connect
+    let store = JobStore::connect(&database_url).await?;
+    let indexer = Indexer::new(provider, store, settings.indexer);
+
+    if env::args().any(|arg| arg == "--backfill-only") {
+        let stored = indexer.backfill().await?;
+        tracing::info!("Backfill done, {} new events stored", stored);
+        return Ok(());
+    }
+    // Backfills on the first run, later runs only read the blocks since the last one indexed
+    indexer.follow().await
+}
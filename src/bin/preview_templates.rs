@@ -0,0 +1,165 @@
+// Dry run of the message templates: renders a notification for every sink without sending it
+// Usage: cargo run --bin preview_templates [notification.json]
+// The job card is written to ./card_preview.png, without the token logo
+use alloy::primitives::{address, U256};
+use eacc_rs::amount::TokenAmount;
+use eacc_rs::cards::CardRenderer;
+use eacc_rs::config::Settings;
+use eacc_rs::content::JobContent;
+use eacc_rs::deliverables::DeliveryNotification;
+use eacc_rs::digest::{Activity, Digest, DigestPeriod, JobActivity};
+use eacc_rs::escrow::{Funding, FundingStatus};
+use eacc_rs::events::{JobEventType, JobStatusNotification};
+use eacc_rs::job_state::{JobState, JobStatus};
+use eacc_rs::models::{ArbitratorProfile, JobDetails, UserProfile, UserRating};
+use eacc_rs::tags::TagCatalogue;
+use eacc_rs::telegram_format::{
+    render_delivery_message, render_job_message, render_status_message, CAPTION_MAX_LEN,
+    MESSAGE_MAX_LEN,
+};
+use eacc_rs::templates::{
+    arbitrator_context, digest_context, job_context, status_context, Templates,
+    TELEGRAM_ARBITRATOR, TELEGRAM_DIGEST, X_ARBITRATOR, X_DIGEST, X_JOB, X_STATUS,
+};
+use eacc_rs::tokens::TokenRegistry;
+use eacc_rs::JobNotification;
+use std::env;
+use std::fs;
+
+fn sample_job() -> JobNotification {
+    let tags = vec!["DA".to_string(), "design".to_string()];
+    JobNotification {
+        job_id: "517".to_string(),
+        title: "Design a logo for <EACC> & friends".to_string(),
+        description: JobContent::parse(
+            "We need a **vector logo** for our project.\n\n- SVG and PNG exports\n- Brand guidelines: [brief](https://example.com/brief.pdf)",
+        ),
+        amount: TokenAmount::new(U256::from(1500u64) * U256::from(10u64).pow(U256::from(18)), 18),
+        symbol: "ARB".to_string(),
+        token: TokenRegistry::bundled()
+            .get(address!("912CE59144191C1204E64559FE8253a0e49E6548"))
+            .unwrap_or_default(),
+        job: JobDetails {
+            creator: "0x1111111111111111111111111111111111111111".to_string(),
+            arbitrator: "0x2222222222222222222222222222222222222222".to_string(),
+            tags: tags.clone(),
+            delivery_method: "ipfs".to_string(),
+            max_time: 259200,
+            ..Default::default()
+        },
+        tags: TagCatalogue::bundled().labels("517", &tags),
+        funding: Some(Funding {
+            status: FundingStatus::Held,
+            issue: None,
+        }),
+        creator_profile: Some(UserProfile {
+            name: "Alice".to_string(),
+            ..Default::default()
+        }),
+        creator_rating: Some(UserRating::new(4.8, 12)),
+        arbitrator_profile: Some(ArbitratorProfile {
+            name: "Bob".to_string(),
+            fee: 150,
+            fee_percent: 1.5,
+            ..Default::default()
+        }),
+        worker_profile: None,
+        usd_value: Some(1234.5),
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenvy::dotenv().ok();
+    let settings = Settings::load()?;
+    let templates = Templates::from_settings(&settings)?;
+
+    let job = match env::args().nth(1) {
+        Some(path) => serde_json::from_str::<JobNotification>(&fs::read_to_string(path)?)?,
+        None => sample_job(),
+    };
+    let delivery = DeliveryNotification {
+        job_id: job.job_id.clone(),
+        title: job.title.clone(),
+        result_hash: "0x0000000000000000000000000000000000000000000000000000000000000000"
+            .to_string(),
+        preview: Some(job.description.summary.clone()),
+        job: job.clone(),
+    };
+    let taken = JobStatusNotification {
+        event: JobEventType::Taken,
+        status: "Taken".to_string(),
+        job: JobNotification {
+            job: JobDetails {
+                state: JobState::Taken,
+                status: JobStatus::Taken,
+                ..job.job.clone()
+            },
+            worker_profile: Some(UserProfile {
+                name: "Carol".to_string(),
+                ..Default::default()
+            }),
+            ..job.clone()
+        },
+    };
+
+    println!("===== Telegram: new job =====");
+    println!("{}", render_job_message(&templates, &job, MESSAGE_MAX_LEN)?);
+    println!("\n===== Telegram: new job card caption =====");
+    println!("{}", render_job_message(&templates, &job, CAPTION_MAX_LEN)?);
+    println!("\n===== Telegram: job delivered =====");
+    println!(
+        "{}",
+        render_delivery_message(&templates, &delivery, MESSAGE_MAX_LEN)?
+    );
+    println!("\n===== Telegram: job taken (edited announcement) =====");
+    println!(
+        "{}",
+        render_status_message(&templates, &taken, MESSAGE_MAX_LEN)?
+    );
+    // A day with only the sample job published
+    let digest = Digest::compute(
+        DigestPeriod::Daily,
+        0,
+        1,
+        &Activity {
+            jobs: vec![JobActivity {
+                job_id: job.job_id.clone(),
+                tags: job.job.tags.clone(),
+                token: job.token.clone(),
+                amount: job.amount,
+                usd_value: job.usd_value,
+                events: vec![(JobEventType::Created, 0)],
+            }],
+            arbitrators: Vec::new(),
+        },
+        5,
+    );
+    println!("\n===== Telegram: daily digest =====");
+    println!(
+        "{}",
+        templates.render(TELEGRAM_DIGEST, &digest_context(&digest)?)?
+    );
+    println!("\n===== Telegram: new arbitrator =====");
+    let arbitrator = job.arbitrator_profile.clone().unwrap_or_default();
+    println!(
+        "{}",
+        templates.render(TELEGRAM_ARBITRATOR, &arbitrator_context(&arbitrator)?)?
+    );
+    println!("\n===== X: new job =====");
+    println!("{}", templates.render(X_JOB, &job_context(&job)?)?);
+    println!("\n===== X: job taken =====");
+    println!("{}", templates.render(X_STATUS, &status_context(&taken)?)?);
+    println!("\n===== X: daily digest =====");
+    println!("{}", templates.render(X_DIGEST, &digest_context(&digest)?)?);
+    println!("\n===== X: new arbitrator =====");
+    println!(
+        "{}",
+        templates.render(X_ARBITRATOR, &arbitrator_context(&arbitrator)?)?
+    );
+
+    let cards = CardRenderer::new(reqwest::Client::new(), settings.number_format.clone());
+    fs::write("card_preview.png", cards.render_png(&job, None)?)?;
+    println!("\n===== Card written to card_preview.png =====");
+
+    Ok(())
+}
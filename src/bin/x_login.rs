@@ -0,0 +1,44 @@
+// Authorize the posting account with OAuth 2.0 PKCE and save its token for `x.auth: "oauth2"`
+// Usage: cargo run --bin x_login, open the printed URL, then paste the URL X redirected to
+use eacc_rs::config::Settings;
+use eacc_rs::x_client::oauth2_app;
+use eacc_rs::x_oauth::{authorize_url, Pkce};
+use rand::distributions::{Alphanumeric, DistString};
+use std::io::BufRead;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenvy::dotenv().ok();
+    let settings = Settings::load()?;
+    let app = oauth2_app(&settings, reqwest::Client::new());
+    let redirect_uri = settings.x.oauth2_redirect_uri();
+
+    let pkce = Pkce::new();
+    let state = Alphanumeric.sample_string(&mut rand::thread_rng(), 16);
+    println!("Open this URL logged in as the posting account:");
+    println!(
+        "{}",
+        authorize_url(&app.client_id, redirect_uri, &state, &pkce)
+    );
+    println!("\nThen paste the URL you were redirected to:");
+
+    let mut redirected = String::new();
+    std::io::stdin().lock().read_line(&mut redirected)?;
+    let redirected = url::Url::parse(redirected.trim())?;
+    let param = |name: &str| {
+        redirected
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if param("state").as_deref() != Some(state.as_str()) {
+        return Err("State mismatch, start the login again".into());
+    }
+    let code = param("code").ok_or("No code in the redirect URL")?;
+
+    let token = app.exchange_code(&code, redirect_uri, &pkce).await?;
+    let path = settings.x.oauth2_token_path();
+    token.save(&path)?;
+    println!("Token saved to {}", path.display());
+    Ok(())
+}
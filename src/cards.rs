@@ -0,0 +1,428 @@
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use image::{imageops, ImageFormat, Rgb, RgbImage, Rgba, RgbaImage};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::{amount::AmountFormat, error::AppError, tokens::TokenInfo, JobNotification};
+
+// 16:9, shown uncropped by X and Telegram
+pub const CARD_WIDTH: u32 = 1200;
+pub const CARD_HEIGHT: u32 = 675;
+
+const MARGIN: i32 = 72;
+const LOGO_SIZE: u32 = 96;
+const LOGO_TIMEOUT: Duration = Duration::from_secs(5);
+
+const REGULAR_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+const BACKGROUND_TOP: Rgb<u8> = Rgb([11, 16, 32]);
+const BACKGROUND_BOTTOM: Rgb<u8> = Rgb([28, 31, 62]);
+const ACCENT: Rgb<u8> = Rgb([124, 92, 255]);
+const REWARD: Rgb<u8> = Rgb([255, 209, 102]);
+const TEXT: Rgb<u8> = Rgb([255, 255, 255]);
+const MUTED: Rgb<u8> = Rgb([160, 166, 196]);
+const TAG_BACKGROUND: Rgb<u8> = Rgb([44, 48, 88]);
+
+// Card images attached to the job announcements, set under `cards` in the config
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct CardSettings {
+    // Disabled, X posts get ./media/tweet_img.png and Telegram posts are plain text
+    pub enabled: bool,
+}
+
+impl Default for CardSettings {
+    fn default() -> Self {
+        CardSettings { enabled: true }
+    }
+}
+
+// Renders job cards to PNG, token logos are downloaded once
+pub struct CardRenderer {
+    client: Client,
+    format: AmountFormat,
+    regular: FontRef<'static>,
+    bold: FontRef<'static>,
+    // None when the logo is missing or can't be decoded, failed downloads are tried again
+    logos: RwLock<HashMap<String, Option<Arc<RgbaImage>>>>,
+}
+
+impl CardRenderer {
+    pub fn new(client: Client, format: AmountFormat) -> Self {
+        CardRenderer {
+            client,
+            format,
+            regular: FontRef::try_from_slice(REGULAR_FONT).expect("Bundled font is invalid"),
+            bold: FontRef::try_from_slice(BOLD_FONT).expect("Bundled font is invalid"),
+            logos: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // Card of a job with its token logo
+    #[tracing::instrument(name = "render_card", skip_all, fields(job_id = %job.job_id))]
+    pub async fn render(&self, job: &JobNotification) -> Result<Vec<u8>, AppError> {
+        let logo = self.logo(&job.token).await;
+        self.render_png(job, logo.as_deref())
+    }
+
+    // Card of a job, the token symbol stands in for a missing logo
+    pub fn render_png(
+        &self,
+        job: &JobNotification,
+        logo: Option<&RgbaImage>,
+    ) -> Result<Vec<u8>, AppError> {
+        let mut card = RgbImage::from_fn(CARD_WIDTH, CARD_HEIGHT, |_, y| {
+            mix(
+                BACKGROUND_TOP,
+                BACKGROUND_BOTTOM,
+                y as f32 / CARD_HEIGHT as f32,
+            )
+        });
+        fill_rect(&mut card, 0, 0, 12, CARD_HEIGHT as i32, ACCENT);
+
+        // Branding and job id
+        self.draw_text(&mut card, true, 36.0, MARGIN, 48, ACCENT, "EACC");
+        let job_label = format!("Job #{}", job.job_id);
+        let job_label_width = text_width(&self.regular, 28.0, &job_label);
+        self.draw_text(
+            &mut card,
+            false,
+            28.0,
+            CARD_WIDTH as i32 - MARGIN - job_label_width,
+            54,
+            MUTED,
+            &job_label,
+        );
+
+        // Title, up to 3 lines
+        let max_width = CARD_WIDTH as i32 - 2 * MARGIN;
+        for (i, line) in wrap_text(&self.bold, 60.0, &job.title, max_width, 3)
+            .iter()
+            .enumerate()
+        {
+            self.draw_text(
+                &mut card,
+                true,
+                60.0,
+                MARGIN,
+                140 + 74 * i as i32,
+                TEXT,
+                line,
+            );
+        }
+
+        // Reward with the token logo
+        let reward_top = 390;
+        match logo {
+            Some(logo) => draw_logo(&mut card, logo, MARGIN, reward_top),
+            None => self.draw_symbol_badge(&mut card, &job.token.symbol, MARGIN, reward_top),
+        }
+        let reward_x = MARGIN + LOGO_SIZE as i32 + 28;
+        let reward = format!("{} ${}", job.amount.format(&self.format), job.symbol);
+        self.draw_text(&mut card, true, 50.0, reward_x, reward_top, REWARD, &reward);
+        if let Some(usd_value) = job.usd_value {
+            let usd = format!("≈ {}", self.format.format_usd(usd_value));
+            self.draw_text(
+                &mut card,
+                false,
+                30.0,
+                reward_x,
+                reward_top + 62,
+                MUTED,
+                &usd,
+            );
+        }
+
+        // Tags, as many as fit on one line
+        let mut x = MARGIN;
+        for tag in &job.tags {
+            let width = text_width(&self.regular, 26.0, &tag.name) + 40;
+            if x + width > CARD_WIDTH as i32 - MARGIN {
+                break;
+            }
+            fill_rounded_rect(&mut card, x, 540, width, 48, 24, TAG_BACKGROUND);
+            self.draw_text(&mut card, false, 26.0, x + 20, 549, TEXT, &tag.name);
+            x += width + 12;
+        }
+
+        let footer = "effectiveacceleration.ai";
+        let footer_width = text_width(&self.regular, 22.0, footer);
+        self.draw_text(
+            &mut card,
+            false,
+            22.0,
+            CARD_WIDTH as i32 - MARGIN - footer_width,
+            CARD_HEIGHT as i32 - 52,
+            MUTED,
+            footer,
+        );
+
+        let mut png = Cursor::new(Vec::new());
+        card.write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| AppError::Image(format!("Can't encode card: {}", e)))?;
+        Ok(png.into_inner())
+    }
+
+    async fn logo(&self, token: &TokenInfo) -> Option<Arc<RgbaImage>> {
+        let url = token.logo_url.as_ref()?;
+        if let Some(logo) = self
+            .logos
+            .read()
+            .expect("Logo cache lock poisoned")
+            .get(url)
+        {
+            return logo.clone();
+        }
+        let logo = match self.fetch_logo(url).await {
+            Ok(logo) => Some(Arc::new(logo)),
+            Err(e) if is_permanent(&e) => {
+                tracing::warn!("Can't use logo of {}: {}", token.symbol, e);
+                None
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Can't download logo of {}, retrying later: {}",
+                    token.symbol,
+                    e
+                );
+                return None;
+            }
+        };
+        self.logos
+            .write()
+            .expect("Logo cache lock poisoned")
+            .insert(url.clone(), logo.clone());
+        logo
+    }
+
+    async fn fetch_logo(&self, url: &str) -> Result<RgbaImage, AppError> {
+        let response = self
+            .client
+            .get(url)
+            .timeout(LOGO_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+        let bytes = response.bytes().await?;
+        let logo = image::load_from_memory(&bytes)
+            .map_err(|e| AppError::Image(format!("Can't decode {}: {}", url, e)))?;
+        Ok(imageops::resize(
+            &logo.to_rgba8(),
+            LOGO_SIZE,
+            LOGO_SIZE,
+            imageops::FilterType::Triangle,
+        ))
+    }
+
+    fn draw_symbol_badge(&self, card: &mut RgbImage, symbol: &str, x: i32, y: i32) {
+        let radius = LOGO_SIZE as i32 / 2;
+        fill_rounded_rect(
+            card,
+            x,
+            y,
+            LOGO_SIZE as i32,
+            LOGO_SIZE as i32,
+            radius,
+            ACCENT,
+        );
+        let initials: String = symbol.chars().take(3).collect();
+        let size = if initials.chars().count() > 2 {
+            28.0
+        } else {
+            36.0
+        };
+        let width = text_width(&self.bold, size, &initials);
+        let height = self.bold.as_scaled(PxScale::from(size)).ascent() as i32;
+        self.draw_text(
+            card,
+            true,
+            size,
+            x + radius - width / 2,
+            y + radius - height / 2,
+            TEXT,
+            &initials,
+        );
+    }
+
+    // Draw a single line with its top at `y`, returns its width
+    #[allow(clippy::too_many_arguments)]
+    fn draw_text(
+        &self,
+        card: &mut RgbImage,
+        bold: bool,
+        size: f32,
+        x: i32,
+        y: i32,
+        color: Rgb<u8>,
+        text: &str,
+    ) -> i32 {
+        let font = if bold { &self.bold } else { &self.regular };
+        let scaled = font.as_scaled(PxScale::from(size));
+        let baseline = y as f32 + scaled.ascent();
+        let mut caret = x as f32;
+        let mut previous = None;
+        for c in text.chars() {
+            let glyph_id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, glyph_id);
+            }
+            let glyph = glyph_id.with_scale_and_position(size, ab_glyph::point(caret, baseline));
+            caret += scaled.h_advance(glyph_id);
+            previous = Some(glyph_id);
+
+            let Some(outlined) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                blend(card, px, py, color, coverage);
+            });
+        }
+        caret as i32 - x
+    }
+}
+
+// Logos that won't be there on the next try: missing, forbidden or not an image
+// Timeouts, server errors and rate limits are worth another download
+fn is_permanent(error: &AppError) -> bool {
+    match error {
+        AppError::Image(_) => true,
+        AppError::Http(e) => e.status().is_some_and(|status| {
+            status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        }),
+        _ => false,
+    }
+}
+
+// Width of a single line in pixels
+fn text_width(font: &FontRef<'static>, size: f32, text: &str) -> i32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let glyph_id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, glyph_id);
+        }
+        width += scaled.h_advance(glyph_id);
+        previous = Some(glyph_id);
+    }
+    width.ceil() as i32
+}
+
+// Split `text` into lines fitting `max_width`, the last kept line ends with … when cut
+fn wrap_text(
+    font: &FontRef<'static>,
+    size: f32,
+    text: &str,
+    max_width: i32,
+    max_lines: usize,
+) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if text_width(font, size, &candidate) <= max_width {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        // Words wider than a line are cut anywhere
+        for c in word.chars() {
+            line.push(c);
+            if text_width(font, size, &line) > max_width {
+                line.pop();
+                lines.push(std::mem::replace(&mut line, c.to_string()));
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = lines.last_mut().expect("max_lines is not 0");
+        while !last.is_empty() && text_width(font, size, &format!("{}…", last)) > max_width {
+            last.pop();
+        }
+        *last = format!("{}…", last.trim_end());
+    }
+    lines
+}
+
+fn mix(from: Rgb<u8>, to: Rgb<u8>, amount: f32) -> Rgb<u8> {
+    let channel = |i: usize| (from[i] as f32 + (to[i] as f32 - from[i] as f32) * amount) as u8;
+    Rgb([channel(0), channel(1), channel(2)])
+}
+
+fn blend(card: &mut RgbImage, x: i32, y: i32, color: Rgb<u8>, alpha: f32) {
+    if x < 0 || y < 0 || x >= card.width() as i32 || y >= card.height() as i32 {
+        return;
+    }
+    let pixel = card.get_pixel_mut(x as u32, y as u32);
+    *pixel = mix(*pixel, color, alpha.clamp(0.0, 1.0));
+}
+
+fn fill_rect(card: &mut RgbImage, x: i32, y: i32, width: i32, height: i32, color: Rgb<u8>) {
+    for py in y..y + height {
+        for px in x..x + width {
+            blend(card, px, py, color, 1.0);
+        }
+    }
+}
+
+// A radius of half the height gives a pill, of half the size a circle
+fn fill_rounded_rect(
+    card: &mut RgbImage,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    radius: i32,
+    color: Rgb<u8>,
+) {
+    for py in 0..height {
+        for px in 0..width {
+            blend(
+                card,
+                x + px,
+                y + py,
+                color,
+                rounded_coverage(px, py, width, height, radius),
+            );
+        }
+    }
+}
+
+// How much of the pixel is inside the rounded rectangle, antialiased over one pixel
+fn rounded_coverage(px: i32, py: i32, width: i32, height: i32, radius: i32) -> f32 {
+    let radius = radius as f32;
+    let cx = (px as f32 + 0.5).clamp(radius, width as f32 - radius);
+    let cy = (py as f32 + 0.5).clamp(radius, height as f32 - radius);
+    let distance = ((px as f32 + 0.5 - cx).powi(2) + (py as f32 + 0.5 - cy).powi(2)).sqrt();
+    (radius - distance + 0.5).clamp(0.0, 1.0)
+}
+
+// Logo cut to a circle, its own transparency kept
+fn draw_logo(card: &mut RgbImage, logo: &RgbaImage, x: i32, y: i32) {
+    let size = LOGO_SIZE as i32;
+    for (px, py, Rgba([r, g, b, a])) in logo.enumerate_pixels() {
+        let mask = rounded_coverage(px as i32, py as i32, size, size, size / 2);
+        let alpha = *a as f32 / 255.0 * mask;
+        blend(card, x + px as i32, y + py as i32, Rgb([*r, *g, *b]), alpha);
+    }
+}
@@ -0,0 +1,214 @@
+use serde::Deserialize;
+use std::{collections::HashMap, env, fs, path::PathBuf};
+
+use crate::{
+    admin::AdminSettings, amount::AmountFormat, cards::CardSettings, digest::DigestSettings,
+    directory::DirectorySettings, error::AppError, filters::FilterRule, indexer::IndexerSettings,
+    pricing::PricingSettings, reputation::ReputationSettings, tags::TagSettings,
+    telegram_api::DEFAULT_API_URL, x_client::DEFAULT_API_URL as X_DEFAULT_API_URL,
+    x_scheduler::XScheduleSettings, Notification,
+};
+
+// Default location of the config file, overridden with CONFIG_PATH
+const DEFAULT_CONFIG_PATH: &str = "./config.json";
+const DEFAULT_POSTS_PATH: &str = "./posts.json";
+const DEFAULT_X_TOKEN_PATH: &str = "./x_token.json";
+const DEFAULT_X_REDIRECT_URI: &str = "http://127.0.0.1:3000/callback";
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TelegramSettings {
+    // Template files, the bundled templates are used when not set
+    pub job_template: Option<PathBuf>,
+    pub delivery_template: Option<PathBuf>,
+    pub digest_template: Option<PathBuf>,
+    pub arbitrator_template: Option<PathBuf>,
+    // Jobs sent to the chat, every job by default
+    pub filter: FilterRule,
+    // Bot API base URL, defaults to https://api.telegram.org
+    pub api_url: Option<String>,
+    pub bot: TelegramBotSettings,
+    // Chats fed by the channel worker, TG_CHAT_ID is used when empty
+    pub destinations: Vec<TelegramDestination>,
+    // Delete the announcements of closed jobs instead of editing them
+    pub delete_closed: bool,
+}
+
+impl TelegramSettings {
+    pub fn api_url(&self) -> &str {
+        self.api_url.as_deref().unwrap_or(DEFAULT_API_URL)
+    }
+}
+
+// A chat, optionally organised in forum topics
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TelegramDestination {
+    pub chat_id: String,
+    // Topic used when no tag is mapped, None posts to the main thread
+    pub message_thread_id: Option<i64>,
+    // MECE tag (e.g. "DA") -> topic id, the first tag of the job found here wins
+    pub topics: HashMap<String, i64>,
+    pub filter: FilterRule,
+}
+
+impl TelegramDestination {
+    pub fn thread_for(&self, notification: &Notification) -> Option<i64> {
+        let Notification::JobPublished(job) = notification else {
+            return self.message_thread_id;
+        };
+        job.job
+            .tags
+            .iter()
+            .find_map(|tag| {
+                self.topics
+                    .iter()
+                    .find(|(topic_tag, _)| topic_tag.eq_ignore_ascii_case(tag))
+                    .map(|(_, thread_id)| *thread_id)
+            })
+            .or(self.message_thread_id)
+    }
+}
+
+// Interactive bot sending personalised alerts by DM
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TelegramBotSettings {
+    pub enabled: bool,
+    // JSON file keeping the subscriptions of every chat
+    pub subscriptions_path: PathBuf,
+    pub poll_timeout_secs: u64,
+}
+
+impl Default for TelegramBotSettings {
+    fn default() -> Self {
+        TelegramBotSettings {
+            enabled: false,
+            subscriptions_path: PathBuf::from("./subscriptions.json"),
+            poll_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct XSettings {
+    pub job_template: Option<PathBuf>,
+    pub status_template: Option<PathBuf>,
+    // "N new jobs" post of collapsed bursts
+    pub summary_template: Option<PathBuf>,
+    pub digest_template: Option<PathBuf>,
+    pub arbitrator_template: Option<PathBuf>,
+    pub filter: FilterRule,
+    // How job state changes are announced under the original post
+    pub status_post: XStatusPost,
+    // Delete the posts of closed jobs
+    pub delete_closed: bool,
+    pub auth: XAuthMethod,
+    pub api_url: Option<String>,
+    // OAuth 2.0 token written by the x_login binary, defaults to ./x_token.json
+    pub oauth2_token_path: Option<PathBuf>,
+    // Must match a callback URL of the X app
+    pub oauth2_redirect_uri: Option<String>,
+    pub thread: XThreadSettings,
+    pub schedule: XScheduleSettings,
+}
+
+// Post the job description as a thread of replies under the announcement
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct XThreadSettings {
+    pub enabled: bool,
+    // Longer descriptions are cut at the end of the last reply
+    pub max_posts: usize,
+}
+
+impl Default for XThreadSettings {
+    fn default() -> Self {
+        XThreadSettings {
+            enabled: false,
+            max_posts: 5,
+        }
+    }
+}
+
+impl XSettings {
+    pub fn api_url(&self) -> &str {
+        self.api_url.as_deref().unwrap_or(X_DEFAULT_API_URL)
+    }
+
+    pub fn oauth2_token_path(&self) -> PathBuf {
+        self.oauth2_token_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_X_TOKEN_PATH))
+    }
+
+    pub fn oauth2_redirect_uri(&self) -> &str {
+        self.oauth2_redirect_uri
+            .as_deref()
+            .unwrap_or(DEFAULT_X_REDIRECT_URI)
+    }
+}
+
+// OAuth 1.0a keys of the .env file, or an OAuth 2.0 user token from x_login
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum XAuthMethod {
+    #[default]
+    OAuth1,
+    OAuth2,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum XStatusPost {
+    #[default]
+    Reply,
+    Quote,
+    None,
+}
+
+// Settings not secret enough to live in the .env file
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub telegram: TelegramSettings,
+    pub x: XSettings,
+    pub number_format: AmountFormat,
+    pub pricing: PricingSettings,
+    pub cards: CardSettings,
+    pub digest: DigestSettings,
+    pub directory: DirectorySettings,
+    pub reputation: ReputationSettings,
+    pub tags: TagSettings,
+    pub admin: AdminSettings,
+    pub indexer: IndexerSettings,
+    // Where the message ids of the announcements are kept, defaults to ./posts.json
+    pub posts_path: Option<PathBuf>,
+}
+
+impl Settings {
+    pub fn posts_path(&self) -> PathBuf {
+        self.posts_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_POSTS_PATH))
+    }
+
+    // Load the JSON config file, every setting falls back to its default when missing
+    #[tracing::instrument(name = "load_settings")]
+    pub fn load() -> Result<Self, AppError> {
+        let path = env::var("CONFIG_PATH").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+        match fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .map_err(|e| AppError::Config(format!("Invalid config file {}: {}", path, e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("No config file at {}, using defaults", path);
+                Ok(Settings::default())
+            }
+            Err(e) => Err(AppError::Config(format!(
+                "Can't read config file {}: {}",
+                path, e
+            ))),
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+// Maximum number of characters of the plain-text summary
+const SUMMARY_MAX_CHARS: usize = 200;
+
+// File extensions treated as attachments when linked from a description
+const ATTACHMENT_EXTENSIONS: [&str; 12] = [
+    ".pdf", ".zip", ".doc", ".docx", ".xls", ".xlsx", ".csv", ".txt", ".md", ".json", ".mp3",
+    ".mp4",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Link {
+    pub text: String,
+    pub url: String,
+}
+
+// Job description parsed from the markdown stored on IPFS
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct JobContent {
+    pub markdown: String,
+    pub links: Vec<Link>,
+    pub images: Vec<Link>,
+    pub attachments: Vec<Link>,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flavor {
+    TelegramHtml,
+    PlainText,
+    Discord,
+}
+
+// Escape text for Telegram `parse_mode: html`
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Escape the characters Discord interprets as markdown
+fn escape_discord(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Only absolute links with a known scheme are rendered as links
+fn is_safe_url(url: &str) -> bool {
+    let lower = url.trim().to_ascii_lowercase();
+    ["https://", "http://", "tg://", "mailto:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+}
+
+fn is_attachment(url: &str) -> bool {
+    let path = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    ATTACHMENT_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+// Bare http(s) URLs written as plain text
+fn bare_urls(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', ')', '!', '?']))
+}
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+    )
+}
+
+// Collapse the blank lines left behind by block elements
+fn tidy(rendered: &str) -> String {
+    let mut out = String::with_capacity(rendered.len());
+    let mut newlines = 0;
+    for c in rendered.trim().chars() {
+        if c == '\n' {
+            newlines += 1;
+            if newlines > 2 {
+                continue;
+            }
+        } else {
+            newlines = 0;
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn render(markdown: &str, flavor: Flavor) -> String {
+    let mut out = String::new();
+    // Ordered lists keep their next number, bullet lists None
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // Link destination and output offset where its text starts, None if not rendered as link
+    let mut links: Vec<(Option<String>, usize)> = Vec::new();
+    let mut in_code_block = false;
+
+    let escape = |text: &str, in_code_block: bool| match flavor {
+        Flavor::TelegramHtml => escape_html(text),
+        Flavor::Discord if !in_code_block => escape_discord(text),
+        _ => text.to_string(),
+    };
+
+    for event in parser(markdown) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Heading { .. } | Tag::Strong => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "<b>",
+                    Flavor::Discord => "**",
+                    Flavor::PlainText => "",
+                }),
+                Tag::Emphasis => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "<i>",
+                    Flavor::Discord => "*",
+                    Flavor::PlainText => "",
+                }),
+                Tag::Strikethrough => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "<s>",
+                    Flavor::Discord => "~~",
+                    Flavor::PlainText => "",
+                }),
+                Tag::BlockQuote(_) => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "<blockquote>",
+                    Flavor::Discord => "> ",
+                    Flavor::PlainText => "",
+                }),
+                Tag::CodeBlock(kind) => {
+                    in_code_block = true;
+                    match flavor {
+                        Flavor::TelegramHtml => out.push_str("<pre>"),
+                        Flavor::Discord => {
+                            let lang = match kind {
+                                CodeBlockKind::Fenced(lang) => lang.to_string(),
+                                CodeBlockKind::Indented => String::new(),
+                            };
+                            out.push_str(&format!("```{}\n", lang));
+                        }
+                        Flavor::PlainText => {}
+                    }
+                }
+                Tag::List(start) => {
+                    if !out.is_empty() && !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                    lists.push(start);
+                }
+                Tag::Item => {
+                    let depth = lists.len().saturating_sub(1);
+                    out.push_str(&"  ".repeat(depth));
+                    match lists.last_mut() {
+                        Some(Some(number)) => {
+                            out.push_str(&format!("{}. ", number));
+                            *number += 1;
+                        }
+                        _ => out.push_str("• "),
+                    }
+                }
+                Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                    let url = dest_url.to_string();
+                    if !is_safe_url(&url) {
+                        links.push((None, out.len()));
+                        continue;
+                    }
+                    match flavor {
+                        Flavor::TelegramHtml => {
+                            out.push_str(&format!("<a href=\"{}\">", escape_html(&url)))
+                        }
+                        Flavor::Discord => out.push('['),
+                        Flavor::PlainText => {}
+                    }
+                    links.push((Some(url), out.len()));
+                }
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph => out.push_str("\n\n"),
+                TagEnd::Heading(_) => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "</b>\n\n",
+                    Flavor::Discord => "**\n\n",
+                    Flavor::PlainText => "\n\n",
+                }),
+                TagEnd::Strong => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "</b>",
+                    Flavor::Discord => "**",
+                    Flavor::PlainText => "",
+                }),
+                TagEnd::Emphasis => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "</i>",
+                    Flavor::Discord => "*",
+                    Flavor::PlainText => "",
+                }),
+                TagEnd::Strikethrough => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "</s>",
+                    Flavor::Discord => "~~",
+                    Flavor::PlainText => "",
+                }),
+                TagEnd::BlockQuote(_) => out.push_str(match flavor {
+                    Flavor::TelegramHtml => "</blockquote>\n\n",
+                    _ => "\n\n",
+                }),
+                TagEnd::CodeBlock => {
+                    in_code_block = false;
+                    out.push_str(match flavor {
+                        Flavor::TelegramHtml => "</pre>\n\n",
+                        Flavor::Discord => "```\n\n",
+                        Flavor::PlainText => "\n",
+                    });
+                }
+                TagEnd::List(_) => {
+                    lists.pop();
+                    if lists.is_empty() {
+                        out.push('\n');
+                    }
+                }
+                TagEnd::Item if !out.ends_with('\n') => out.push('\n'),
+                TagEnd::Link | TagEnd::Image => {
+                    let Some((Some(url), start)) = links.pop() else {
+                        continue;
+                    };
+                    let text_is_empty = out[start..].trim().is_empty();
+                    let text_is_url = out[start..].trim() == url;
+                    match flavor {
+                        Flavor::TelegramHtml => {
+                            if text_is_empty {
+                                out.push_str(&escape_html(&url));
+                            }
+                            out.push_str("</a>");
+                        }
+                        Flavor::Discord => {
+                            if text_is_empty {
+                                out.push_str(&escape_discord(&url));
+                            }
+                            // Angle brackets stop Discord from embedding every link
+                            out.push_str(&format!("](<{}>)", url));
+                        }
+                        Flavor::PlainText => {
+                            if text_is_empty {
+                                out.push_str(&url);
+                            } else if !text_is_url {
+                                out.push_str(&format!(" ({})", url));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) => out.push_str(&escape(&text, in_code_block)),
+            Event::Code(code) | Event::InlineMath(code) | Event::DisplayMath(code) => {
+                match flavor {
+                    Flavor::TelegramHtml => {
+                        out.push_str(&format!("<code>{}</code>", escape_html(&code)))
+                    }
+                    Flavor::Discord if code.contains('`') => {
+                        out.push_str(&format!("`` {} ``", code))
+                    }
+                    Flavor::Discord => out.push_str(&format!("`{}`", code)),
+                    Flavor::PlainText => out.push_str(&code),
+                }
+            }
+            // Raw HTML in descriptions is shown as text, never interpreted
+            Event::Html(html) | Event::InlineHtml(html) => {
+                out.push_str(&escape(&html, in_code_block))
+            }
+            Event::FootnoteReference(name) => out.push_str(&format!("[{}]", name)),
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::Rule => out.push_str("———\n\n"),
+            Event::TaskListMarker(checked) => out.push_str(if checked { "☑ " } else { "☐ " }),
+        }
+    }
+    tidy(&out)
+}
+
+// Build a one paragraph summary from the plain text rendering
+fn summarize(plain_text: &str) -> String {
+    let collapsed = plain_text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= SUMMARY_MAX_CHARS {
+        return collapsed;
+    }
+    let cut: String = collapsed.chars().take(SUMMARY_MAX_CHARS).collect();
+    // Prefer cutting on a word boundary
+    let cut = match cut.rfind(' ') {
+        Some(index) if index > SUMMARY_MAX_CHARS / 2 => &cut[..index],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end())
+}
+
+impl JobContent {
+    // Parse the raw description fetched from IPFS
+    pub fn parse(raw: &str) -> Self {
+        let markdown = raw.trim_matches('\0').trim().to_string();
+
+        let mut links: Vec<Link> = Vec::new();
+        let mut images: Vec<Link> = Vec::new();
+        let mut attachments: Vec<Link> = Vec::new();
+        // Destination and collected text of the link or image being parsed
+        let mut current: Option<(bool, String, String)> = None;
+
+        for event in parser(&markdown) {
+            match event {
+                Event::Start(Tag::Link { dest_url, .. }) => {
+                    current = Some((false, dest_url.to_string(), String::new()))
+                }
+                Event::Start(Tag::Image { dest_url, .. }) => {
+                    current = Some((true, dest_url.to_string(), String::new()))
+                }
+                Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                    if let Some((is_image, url, text)) = current.take() {
+                        if !is_safe_url(&url) {
+                            continue;
+                        }
+                        let link = Link { text, url };
+                        if is_image {
+                            images.push(link);
+                        } else if is_attachment(&link.url) {
+                            attachments.push(link);
+                        } else {
+                            links.push(link);
+                        }
+                    }
+                }
+                Event::Text(text) | Event::Code(text) => match current.as_mut() {
+                    Some((_, _, link_text)) => link_text.push_str(&text),
+                    None => {
+                        for url in bare_urls(&text) {
+                            let link = Link {
+                                text: url.to_string(),
+                                url: url.to_string(),
+                            };
+                            if is_attachment(url) {
+                                attachments.push(link);
+                            } else {
+                                links.push(link);
+                            }
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+        links.dedup_by(|a, b| a.url == b.url);
+
+        let summary = summarize(&render(&markdown, Flavor::PlainText));
+        JobContent {
+            markdown,
+            links,
+            images,
+            attachments,
+            summary,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.markdown.is_empty()
+    }
+
+    // Telegram HTML, safe for `parse_mode: html`
+    pub fn to_telegram_html(&self) -> String {
+        render(&self.markdown, Flavor::TelegramHtml)
+    }
+
+    // Plain text for X, links are written out in full
+    pub fn to_x_text(&self) -> String {
+        render(&self.markdown, Flavor::PlainText)
+    }
+
+    // Discord flavoured markdown with link embeds disabled
+    pub fn to_discord_markdown(&self) -> String {
+        render(&self.markdown, Flavor::Discord)
+    }
+}
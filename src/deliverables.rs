@@ -0,0 +1,54 @@
+use alloy::primitives::B256;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{utils::get_from_ipfs, JobNotification};
+
+// Maximum number of characters of the deliverable shown in a notification
+const PREVIEW_MAX_CHARS: usize = 280;
+
+// Job delivered notification struct
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeliveryNotification {
+    pub job_id: String,
+    pub title: String,
+    pub result_hash: String,
+    // None when the deliverable is encrypted or empty
+    pub preview: Option<String>,
+    // The delivered job, for the filters of the sinks
+    #[serde(default)]
+    pub job: JobNotification,
+}
+
+// Pick the result hash from the Delivered event data, falling back to the JobPost one
+pub fn result_hash_from_event(event_data: &[u8], job_result_hash: B256) -> B256 {
+    if event_data.len() == 32 {
+        B256::from_slice(event_data)
+    } else {
+        job_result_hash
+    }
+}
+
+// Build a short preview of the deliverable content, cut on a char boundary
+pub fn preview(content: &str) -> Option<String> {
+    let content = content.trim();
+    if content.is_empty() {
+        return None;
+    }
+    if content.chars().count() <= PREVIEW_MAX_CHARS {
+        return Some(content.to_string());
+    }
+    let cut: String = content.chars().take(PREVIEW_MAX_CHARS).collect();
+    Some(format!("{}…", cut.trim_end()))
+}
+
+// Fetch the deliverable from IPFS and build its preview
+// Only public results are previewed, encrypted ones aren't valid UTF-8 and yield none
+#[tracing::instrument(name = "fetch_deliverable")]
+pub async fn fetch_deliverable(result_hash: B256) -> Result<Option<String>> {
+    if result_hash.is_zero() {
+        return Ok(None);
+    }
+    let content = get_from_ipfs(&result_hash.to_string(), "").await?;
+    Ok(preview(&content))
+}
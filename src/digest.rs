@@ -0,0 +1,486 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::U256;
+use alloy::providers::{MulticallBuilder, Provider};
+use eyre::Result;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::{
+    amount::TokenAmount,
+    error::AppError,
+    events::JobEventType,
+    pricing::PriceOracle,
+    tokens::{TokenInfo, TokenRegistry},
+    utils::JsonStore,
+    x_client::now_secs,
+    MarketPlaceData, Notification, MARKETPLACE_DATA_ADDRESS,
+};
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+const DEFAULT_STATE_PATH: &str = "./digests.json";
+// getJob calls packed in one multicall
+const MULTICALL_SIZE: usize = 20;
+// First block watched for job events, no marketplace log is older
+const FIRST_BLOCK: u64 = 278858754;
+// Blocks per eth_getLogs call, providers reject wide ranges
+pub(crate) const LOG_BLOCK_RANGE: u64 = 50_000;
+// Wait before trying again when the activity can't be read
+const RETRY_DELAY_SECS: u64 = 10 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestPeriod {
+    Daily,
+    Weekly,
+}
+
+impl DigestPeriod {
+    pub fn secs(self) -> u64 {
+        match self {
+            DigestPeriod::Daily => DAY_SECS,
+            DigestPeriod::Weekly => 7 * DAY_SECS,
+        }
+    }
+}
+
+// Scheduled activity summaries, set under `digest` in the config file
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    pub periods: Vec<DigestPeriod>,
+    // Digests are posted at this hour, UTC
+    pub hour_utc: u8,
+    // Day of the weekly digest, 0 is Monday
+    pub weekday: u8,
+    pub telegram: bool,
+    pub x: bool,
+    // Most used tags listed
+    pub top_tags: usize,
+    // When each digest was last posted, defaults to ./digests.json
+    pub state_path: Option<PathBuf>,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        DigestSettings {
+            enabled: false,
+            periods: vec![DigestPeriod::Daily],
+            hour_utc: 9,
+            weekday: 0,
+            telegram: true,
+            x: true,
+            top_tags: 5,
+            state_path: None,
+        }
+    }
+}
+
+impl DigestSettings {
+    pub fn state_path(&self) -> PathBuf {
+        self.state_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_STATE_PATH))
+    }
+
+    // Start of the last digest slot at or before `now`
+    pub fn last_slot(&self, period: DigestPeriod, now: u64) -> u64 {
+        let mut slot = now - now % DAY_SECS + u64::from(self.hour_utc.min(23)) * 3600;
+        if slot > now {
+            slot -= DAY_SECS;
+        }
+        match period {
+            DigestPeriod::Daily => slot,
+            DigestPeriod::Weekly => {
+                // 1970-01-01 was a Thursday
+                let weekday = (slot / DAY_SECS + 3) % 7;
+                slot - (weekday + 7 - u64::from(self.weekday.min(6))) % 7 * DAY_SECS
+            }
+        }
+    }
+}
+
+// Events of a job in the digest window, with the job reward
+#[derive(Debug, Clone, Default)]
+pub struct JobActivity {
+    pub job_id: String,
+    pub tags: Vec<String>,
+    pub token: TokenInfo,
+    pub amount: TokenAmount,
+    pub usd_value: Option<f64>,
+    // Event type and unix time
+    pub events: Vec<(JobEventType, u64)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewArbitrator {
+    pub address: String,
+    pub name: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Activity {
+    pub jobs: Vec<JobActivity>,
+    pub arbitrators: Vec<NewArbitrator>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TokenTotal {
+    pub symbol: String,
+    pub amount: TokenAmount,
+    // Sum of the rewards with a known USD value
+    pub usd_value: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+// Marketplace activity between `from` and `to` (unix seconds)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Digest {
+    pub period: DigestPeriod,
+    pub from: u64,
+    pub to: u64,
+    pub jobs_published: usize,
+    // Rewards of the published jobs per token, highest USD value first
+    pub rewards: Vec<TokenTotal>,
+    pub total_usd: Option<f64>,
+    pub top_tags: Vec<TagCount>,
+    pub jobs_completed: usize,
+    pub disputes_resolved: usize,
+    // Names of the arbitrators registered in the window
+    pub new_arbitrators: Vec<String>,
+}
+
+impl Digest {
+    pub fn compute(
+        period: DigestPeriod,
+        from: u64,
+        to: u64,
+        activity: &Activity,
+        top_tags: usize,
+    ) -> Self {
+        let in_window = |timestamp: u64| timestamp >= from && timestamp < to;
+        let mut jobs_published = 0;
+        let mut jobs_completed = 0;
+        let mut disputes_resolved = 0;
+        // Keyed by token address, a symbol isn't unique
+        let mut rewards: BTreeMap<String, TokenTotal> = BTreeMap::new();
+        let mut tags: HashMap<&str, usize> = HashMap::new();
+
+        for job in &activity.jobs {
+            for (event, _) in job.events.iter().filter(|(_, time)| in_window(*time)) {
+                match event {
+                    JobEventType::Created => {
+                        jobs_published += 1;
+                        let total =
+                            rewards
+                                .entry(job.token.address.to_string())
+                                .or_insert_with(|| TokenTotal {
+                                    symbol: job.token.symbol.clone(),
+                                    amount: TokenAmount::new(U256::ZERO, job.amount.decimals),
+                                    usd_value: None,
+                                });
+                        total.amount.raw = total.amount.raw.saturating_add(job.amount.raw);
+                        if let Some(usd_value) = job.usd_value {
+                            total.usd_value = Some(total.usd_value.unwrap_or_default() + usd_value);
+                        }
+                        for tag in &job.tags {
+                            *tags.entry(tag.as_str()).or_default() += 1;
+                        }
+                    }
+                    JobEventType::Completed => jobs_completed += 1,
+                    JobEventType::Arbitrated | JobEventType::ArbitrationRefused => {
+                        disputes_resolved += 1
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut rewards: Vec<TokenTotal> = rewards.into_values().collect();
+        rewards.sort_by(|a, b| {
+            b.usd_value
+                .unwrap_or(-1.0)
+                .total_cmp(&a.usd_value.unwrap_or(-1.0))
+                .then_with(|| a.symbol.cmp(&b.symbol))
+        });
+        let priced: Vec<f64> = rewards.iter().filter_map(|total| total.usd_value).collect();
+        let total_usd = (!priced.is_empty()).then(|| priced.iter().sum());
+
+        let mut tags: Vec<TagCount> = tags
+            .into_iter()
+            .map(|(tag, count)| TagCount {
+                tag: tag.to_string(),
+                count,
+            })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        tags.truncate(top_tags);
+
+        Digest {
+            period,
+            from,
+            to,
+            jobs_published,
+            rewards,
+            total_usd,
+            top_tags: tags,
+            jobs_completed,
+            disputes_resolved,
+            new_arbitrators: activity
+                .arbitrators
+                .iter()
+                .filter(|arbitrator| in_window(arbitrator.timestamp))
+                .map(|arbitrator| arbitrator.name.clone())
+                .collect(),
+        }
+    }
+}
+
+// Inclusive block ranges of at most `size` blocks covering `from..=to`
+pub fn block_ranges(from: u64, to: u64, size: u64) -> Vec<(u64, u64)> {
+    let mut ranges = Vec::new();
+    let mut start = from;
+    while start <= to {
+        let end = to.min(start + size - 1);
+        ranges.push((start, end));
+        start = end + 1;
+    }
+    ranges
+}
+
+// Reads the marketplace activity since a unix time
+pub trait ActivitySource: Send + Sync {
+    fn activity(&self, since: u64) -> BoxFuture<'_, Result<Activity>>;
+}
+
+// Reads the activity from the `JobEvent` and `ArbitratorRegistered` logs of MarketplaceData
+// Only the blocks of the window are read, and only the jobs with events in it
+pub struct ChainActivity<P> {
+    provider: P,
+    tokens: Arc<TokenRegistry>,
+    prices: Arc<PriceOracle>,
+}
+
+impl<P: Provider + Clone> ChainActivity<P> {
+    pub fn new(provider: P, tokens: Arc<TokenRegistry>, prices: Arc<PriceOracle>) -> Self {
+        ChainActivity {
+            provider,
+            tokens,
+            prices,
+        }
+    }
+
+    async fn block_timestamp(&self, number: u64) -> Result<u64> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(number))
+            .await?
+            .ok_or_else(|| eyre::eyre!("Block {} not found", number))?;
+        Ok(block.header.timestamp)
+    }
+
+    // First block mined at or after `since`, found by bisection
+    async fn first_block_since(&self, since: u64, latest: u64) -> Result<u64> {
+        let (mut low, mut high) = (FIRST_BLOCK, latest + 1);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.block_timestamp(middle).await? < since {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        Ok(low)
+    }
+
+    async fn new_arbitrators(&self, from: u64, to: u64, since: u64) -> Result<Vec<NewArbitrator>> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &self.provider);
+        let logs = marketplace_data
+            .ArbitratorRegistered_filter()
+            .from_block(from)
+            .to_block(to)
+            .query()
+            .await?;
+        let mut arbitrators = Vec::new();
+        for (event, log) in logs {
+            // Not every node fills the block time of logs
+            let timestamp = match (log.block_timestamp, log.block_number) {
+                (Some(timestamp), _) => timestamp,
+                (None, Some(number)) => self.block_timestamp(number).await?,
+                (None, None) => continue,
+            };
+            if timestamp >= since {
+                arbitrators.push(NewArbitrator {
+                    address: event.addr.to_string(),
+                    name: event.name,
+                    timestamp,
+                });
+            }
+        }
+        Ok(arbitrators)
+    }
+}
+
+impl<P: Provider + Clone + Send + Sync> ActivitySource for ChainActivity<P> {
+    fn activity(&self, since: u64) -> BoxFuture<'_, Result<Activity>> {
+        Box::pin(async move {
+            let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &self.provider);
+            let latest = self.provider.get_block_number().await?;
+            let first = self.first_block_since(since, latest).await?;
+
+            // Events of each job in the window, by job id
+            let mut events: BTreeMap<u64, Vec<(JobEventType, u64)>> = BTreeMap::new();
+            let mut arbitrators = Vec::new();
+            for (from, to) in block_ranges(first, latest, LOG_BLOCK_RANGE) {
+                let logs = marketplace_data
+                    .JobEvent_filter()
+                    .from_block(from)
+                    .to_block(to)
+                    .query()
+                    .await?;
+                for (event, _) in logs {
+                    let timestamp = u64::from(event.eventData.timestamp_);
+                    if timestamp >= since {
+                        events
+                            .entry(event.jobId.saturating_to())
+                            .or_default()
+                            .push((JobEventType::from(event.eventData.type_), timestamp));
+                    }
+                }
+                arbitrators.extend(self.new_arbitrators(from, to, since).await?);
+            }
+
+            // Reward and tags of the jobs with events in the window
+            let job_ids: Vec<u64> = events.keys().copied().collect();
+            let mut jobs = Vec::new();
+            for chunk in job_ids.chunks(MULTICALL_SIZE) {
+                let posts = MulticallBuilder::new_dynamic(&self.provider)
+                    .extend(
+                        chunk
+                            .iter()
+                            .map(|job_id| marketplace_data.getJob(U256::from(*job_id))),
+                    )
+                    .aggregate()
+                    .await?;
+                for (job_id, post) in chunk.iter().zip(posts) {
+                    let job = post._0;
+                    let token = self.tokens.resolve(self.provider.clone(), job.token).await;
+                    let amount = TokenAmount::new(job.amount, token.decimals);
+                    let usd_value = self.prices.usd_value(&token, &amount).await;
+                    jobs.push(JobActivity {
+                        job_id: job_id.to_string(),
+                        tags: job.tags,
+                        token,
+                        amount,
+                        usd_value,
+                        events: events.remove(job_id).unwrap_or_default(),
+                    });
+                }
+            }
+            Ok(Activity { jobs, arbitrators })
+        })
+    }
+}
+
+// Slot each digest was last posted for, so restarts don't post twice
+#[derive(Debug, Default)]
+pub struct DigestState {
+    last_sent: JsonStore<BTreeMap<DigestPeriod, u64>>,
+}
+
+impl DigestState {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        Ok(DigestState {
+            last_sent: JsonStore::load(path, "digest state")?,
+        })
+    }
+
+    pub fn last_sent(&self, period: DigestPeriod) -> Option<u64> {
+        self.last_sent
+            .read(|last_sent| last_sent.get(&period).copied())
+    }
+
+    pub fn set_sent(&mut self, period: DigestPeriod, slot: u64) {
+        if let Err(e) = self
+            .last_sent
+            .update(|last_sent| last_sent.insert(period, slot))
+        {
+            tracing::error!("{}", e);
+        }
+    }
+}
+
+// Post the digests whose slot has passed, returns how many were sent
+// A fresh state only records the current slots, nothing is posted on the first start
+#[tracing::instrument(name = "send_due_digests", skip(source, settings, state, sinks))]
+pub async fn send_due_digests(
+    source: &dyn ActivitySource,
+    settings: &DigestSettings,
+    state: &mut DigestState,
+    sinks: &[mpsc::Sender<Notification>],
+    now: u64,
+) -> Result<usize, AppError> {
+    let mut sent = 0;
+    for period in &settings.periods {
+        let slot = settings.last_slot(*period, now);
+        match state.last_sent(*period) {
+            Some(last_sent) if last_sent >= slot => continue,
+            Some(_) => {}
+            None => {
+                state.set_sent(*period, slot);
+                continue;
+            }
+        }
+        let from = slot - period.secs();
+        let activity = source
+            .activity(from)
+            .await
+            .map_err(|e| AppError::Chain(format!("Can't read the marketplace activity: {}", e)))?;
+        let digest = Digest::compute(*period, from, slot, &activity, settings.top_tags);
+        tracing::info!("Sending {:?} digest: {:?}", period, digest);
+        for sink in sinks {
+            if let Err(e) = sink.send(Notification::Digest(digest.clone())).await {
+                tracing::error!("Error sending digest into the queue: {}", e);
+            }
+        }
+        state.set_sent(*period, slot);
+        sent += 1;
+    }
+    Ok(sent)
+}
+
+// Post every digest when its slot comes
+#[tracing::instrument(name = "digest_worker", skip(source, settings, sinks))]
+pub async fn digest_worker(
+    source: Arc<dyn ActivitySource>,
+    settings: DigestSettings,
+    sinks: Vec<mpsc::Sender<Notification>>,
+) -> Result<(), AppError> {
+    let mut state = DigestState::load(&settings.state_path())?;
+    loop {
+        let now = now_secs();
+        let mut wake = settings
+            .periods
+            .iter()
+            .map(|period| settings.last_slot(*period, now) + period.secs())
+            .min()
+            .unwrap_or(now + DAY_SECS);
+        if let Err(e) = send_due_digests(source.as_ref(), &settings, &mut state, &sinks, now).await
+        {
+            tracing::error!("Failed to send digests: {}", e);
+            wake = wake.min(now + RETRY_DELAY_SECS);
+        }
+        tokio::time::sleep(Duration::from_secs(wake.saturating_sub(now_secs()))).await;
+    }
+}
@@ -0,0 +1,307 @@
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use cid::Cid;
+use eyre::Result;
+use futures::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::{
+    error::AppError,
+    models::{ArbitratorProfile, UserProfile},
+    utils::JsonStore,
+    MarketPlaceData, Notification, MARKETPLACE_DATA_ADDRESS,
+};
+
+const DEFAULT_DIRECTORY_PATH: &str = "./directory.json";
+// Users and arbitrators read per getUsers / getArbitrators call
+const PAGE_SIZE: u64 = 50;
+// Delay before starting the directory worker again after it failed
+const WORKER_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+// Directory of the registered users and arbitrators, set under `directory` in the config file
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct DirectorySettings {
+    pub enabled: bool,
+    // Saved directory, defaults to ./directory.json
+    pub path: Option<PathBuf>,
+    // Post "new arbitrator available" when one registers
+    pub announce_arbitrators: bool,
+    // Sinks of the announcements
+    pub telegram: bool,
+    pub x: bool,
+}
+
+impl Default for DirectorySettings {
+    fn default() -> Self {
+        DirectorySettings {
+            enabled: false,
+            path: None,
+            announce_arbitrators: false,
+            telegram: true,
+            x: true,
+        }
+    }
+}
+
+impl DirectorySettings {
+    pub fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DIRECTORY_PATH))
+    }
+}
+
+// Link to an avatar: web links are kept, ipfs:// links and bare CIDs go through `gateway`
+// Only the link is built, the image itself is never fetched from IPFS
+pub fn avatar_url(avatar: &str, gateway: &str) -> Option<String> {
+    let avatar = avatar.trim();
+    if avatar.starts_with("https://") || avatar.starts_with("http://") {
+        return Some(avatar.to_string());
+    }
+    let cid = match avatar.strip_prefix("ipfs://") {
+        Some(path) => path.trim_start_matches("ipfs/"),
+        None => avatar,
+    };
+    // The CID may be followed by a path inside the directory it points to
+    let root = cid.split('/').next().unwrap_or_default();
+    if gateway.is_empty() || Cid::try_from(root).is_err() {
+        return None;
+    }
+    Some(format!("{}{}", gateway, cid))
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Profiles {
+    users: BTreeMap<String, UserProfile>,
+    arbitrators: BTreeMap<String, ArbitratorProfile>,
+}
+
+// Users and arbitrators by lowercase address, saved on each change
+#[derive(Debug, Default)]
+pub struct Directory {
+    // IPFS gateway prefix of the avatar links
+    gateway: String,
+    profiles: JsonStore<Profiles>,
+}
+
+impl Directory {
+    pub fn load(path: &Path, gateway: &str) -> Result<Self, AppError> {
+        Ok(Directory {
+            gateway: gateway.to_string(),
+            profiles: JsonStore::load(path, "directory")?,
+        })
+    }
+
+    fn update<F: FnOnce(&mut Profiles)>(&self, change: F) {
+        // The directory is read again from the chain on the next start
+        if let Err(e) = self.profiles.update(change) {
+            tracing::error!("{}", e);
+        }
+    }
+
+    pub fn user(&self, address: &str) -> Option<UserProfile> {
+        self.profiles
+            .read(|profiles| profiles.users.get(&address.to_lowercase()).cloned())
+    }
+
+    pub fn arbitrator(&self, address: &str) -> Option<ArbitratorProfile> {
+        self.profiles
+            .read(|profiles| profiles.arbitrators.get(&address.to_lowercase()).cloned())
+    }
+
+    // Name to show instead of an address, None when it isn't registered
+    pub fn name(&self, address: &str) -> Option<String> {
+        self.user(address)
+            .map(|user| user.name)
+            .or_else(|| self.arbitrator(address).map(|arbitrator| arbitrator.name))
+            .filter(|name| !name.is_empty())
+    }
+
+    pub fn users_len(&self) -> usize {
+        self.profiles.read(|profiles| profiles.users.len())
+    }
+
+    pub fn arbitrators_len(&self) -> usize {
+        self.profiles.read(|profiles| profiles.arbitrators.len())
+    }
+
+    pub fn set_users(&self, users: Vec<UserProfile>) {
+        self.update(|profiles| {
+            for mut user in users {
+                user.avatar_url = avatar_url(&user.avatar, &self.gateway);
+                profiles.users.insert(user.address.to_lowercase(), user);
+            }
+        });
+    }
+
+    pub fn set_arbitrators(&self, arbitrators: Vec<ArbitratorProfile>) {
+        self.update(|profiles| {
+            for mut arbitrator in arbitrators {
+                arbitrator.avatar_url = avatar_url(&arbitrator.avatar, &self.gateway);
+                profiles
+                    .arbitrators
+                    .insert(arbitrator.address.to_lowercase(), arbitrator);
+            }
+        });
+    }
+
+    // UserUpdated only carries the public fields, the rest is kept
+    pub fn update_user(&self, event: &MarketPlaceData::UserUpdated) {
+        let avatar_url = avatar_url(&event.avatar, &self.gateway);
+        self.update(|profiles| {
+            let user = profiles
+                .users
+                .entry(event.addr.to_string().to_lowercase())
+                .or_insert_with(|| UserProfile {
+                    address: event.addr.to_string(),
+                    ..Default::default()
+                });
+            user.name = event.name.clone();
+            user.bio = event.bio.clone();
+            user.avatar = event.avatar.clone();
+            user.avatar_url = avatar_url;
+        });
+    }
+
+    pub fn update_arbitrator(&self, event: &MarketPlaceData::ArbitratorUpdated) {
+        let avatar_url = avatar_url(&event.avatar, &self.gateway);
+        self.update(|profiles| {
+            let arbitrator = profiles
+                .arbitrators
+                .entry(event.addr.to_string().to_lowercase())
+                .or_insert_with(|| ArbitratorProfile {
+                    address: event.addr.to_string(),
+                    ..Default::default()
+                });
+            arbitrator.name = event.name.clone();
+            arbitrator.bio = event.bio.clone();
+            arbitrator.avatar = event.avatar.clone();
+            arbitrator.avatar_url = avatar_url;
+        });
+    }
+}
+
+// Read every registered user and arbitrator into the directory
+#[tracing::instrument(name = "backfill_directory", skip(provider, directory))]
+pub async fn backfill_directory<P: Provider + Clone>(
+    provider: P,
+    directory: &Directory,
+) -> Result<()> {
+    let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider);
+
+    let users_length: u64 = marketplace_data.usersLength().call().await?._0.to();
+    for index in (0..users_length).step_by(PAGE_SIZE as usize) {
+        let users = marketplace_data
+            .getUsers(U256::from(index), U256::from(PAGE_SIZE))
+            .call()
+            .await?
+            ._0;
+        directory.set_users(users.iter().map(UserProfile::from).collect());
+    }
+
+    let arbitrators_length: u64 = marketplace_data.arbitratorsLength().call().await?._0.to();
+    for index in (0..arbitrators_length).step_by(PAGE_SIZE as usize) {
+        let arbitrators = marketplace_data
+            .getArbitrators(U256::from(index), U256::from(PAGE_SIZE))
+            .call()
+            .await?
+            ._0;
+        directory.set_arbitrators(arbitrators.iter().map(ArbitratorProfile::from).collect());
+    }
+    tracing::info!(
+        "Directory loaded, {} users and {} arbitrators",
+        directory.users_len(),
+        directory.arbitrators_len()
+    );
+    Ok(())
+}
+
+// Keep the directory current from the registry events, started again whenever it stops
+// New arbitrators are announced on `sinks`, leave it empty to only track them
+#[tracing::instrument(name = "directory_worker", skip(provider, directory, sinks))]
+pub async fn directory_worker<P: Provider + Clone>(
+    provider: P,
+    directory: Arc<Directory>,
+    sinks: Vec<mpsc::Sender<Notification>>,
+) {
+    loop {
+        if let Err(e) = watch_registry(provider.clone(), &directory, &sinks).await {
+            tracing::error!("Directory worker failed, restarting: {}", e);
+        }
+        tokio::time::sleep(WORKER_RETRY_DELAY).await;
+    }
+}
+
+async fn watch_registry<P: Provider + Clone>(
+    provider: P,
+    directory: &Directory,
+    sinks: &[mpsc::Sender<Notification>],
+) -> Result<()> {
+    let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider.clone());
+    let mut user_registered = marketplace_data
+        .UserRegistered_filter()
+        .subscribe()
+        .await?
+        .into_stream();
+    let mut user_updated = marketplace_data
+        .UserUpdated_filter()
+        .subscribe()
+        .await?
+        .into_stream();
+    let mut arbitrator_registered = marketplace_data
+        .ArbitratorRegistered_filter()
+        .subscribe()
+        .await?
+        .into_stream();
+    let mut arbitrator_updated = marketplace_data
+        .ArbitratorUpdated_filter()
+        .subscribe()
+        .await?
+        .into_stream();
+    // Read after subscribing so nothing registered in between is missed
+    backfill_directory(provider, directory).await?;
+
+    loop {
+        tokio::select! {
+            Some(log) = user_registered.next() => match log {
+                Ok((event, _)) => {
+                    tracing::info!("User {} registered as {}", event.addr, event.name);
+                    directory.set_users(vec![UserProfile::from(&event)]);
+                }
+                Err(e) => tracing::error!("Error in UserRegistered stream: {:?}", e),
+            },
+            Some(log) = user_updated.next() => match log {
+                Ok((event, _)) => directory.update_user(&event),
+                Err(e) => tracing::error!("Error in UserUpdated stream: {:?}", e),
+            },
+            Some(log) = arbitrator_registered.next() => match log {
+                Ok((event, _)) => {
+                    tracing::info!("Arbitrator {} registered as {}", event.addr, event.name);
+                    directory.set_arbitrators(vec![ArbitratorProfile::from(&event)]);
+                    let Some(arbitrator) = directory.arbitrator(&event.addr.to_string()) else {
+                        continue;
+                    };
+                    for sink in sinks {
+                        let notification = Notification::ArbitratorRegistered(arbitrator.clone());
+                        if let Err(e) = sink.send(notification).await {
+                            tracing::error!("Failed to queue the arbitrator announcement: {}", e);
+                        }
+                    }
+                }
+                Err(e) => tracing::error!("Error in ArbitratorRegistered stream: {:?}", e),
+            },
+            Some(log) = arbitrator_updated.next() => match log {
+                Ok((event, _)) => directory.update_arbitrator(&event),
+                Err(e) => tracing::error!("Error in ArbitratorUpdated stream: {:?}", e),
+            },
+            else => return Err(eyre::eyre!("Registry event streams ended")),
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use thiserror::Error;
 
+use crate::events::JobEventType;
+use crate::job_state::{JobState, JobStatus};
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Environment variable error: {0}")]
@@ -11,15 +14,43 @@ pub enum AppError {
     #[error("X API error: {0}")]
     XApi(String),
 
+    // Unix time the exhausted window resets at
+    #[error("X rate limit reached until {0}")]
+    XRateLimited(u64),
+
     #[error("Telegram API error: {0}")]
     TelegramApi(String),
 
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("Config error: {0}")]
+    Config(String),
+
+    #[error("Template error: {0}")]
+    Template(#[from] tera::Error),
+
+    #[error("Image error: {0}")]
+    Image(String),
+
+    // Contract calls that failed or returned something unexpected
+    #[error("Chain read error: {0}")]
+    Chain(String),
+
     #[error("Event parsing error: {0}")]
     EventParsing(String),
 
-    #[error("Reqwest error: {0}")]
-    Reqwest(#[from] twitter_api_v1::endpoints::EndpointError),
+    #[error("{event:?} event on a {status:?} job")]
+    InvalidTransition {
+        status: JobStatus,
+        event: JobEventType,
+    },
+
+    // The events predicted another state than `getJob` returned
+    #[error("job {job_id} drifted: events say {expected:?}, getJob says {actual:?}")]
+    StateDrift {
+        job_id: String,
+        expected: JobStatus,
+        actual: JobState,
+    },
 }
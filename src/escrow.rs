@@ -0,0 +1,128 @@
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    job_state::JobState, MarketPlaceData, MarketplaceV1, Unicrow, MARKETPLACE_DATA_ADDRESS,
+};
+
+// Where the reward of a job is, as read from its `getJob` snapshot
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum FundingStatus {
+    // Open job, the reward should sit in the marketplace, only the snapshot is checked
+    Held,
+    // Taken job, its escrow record holds the reward
+    Escrowed,
+    // Taken job whose escrow record couldn't be read
+    Unverified,
+    // Closed before being taken, the reward waits for `withdrawCollateral`
+    CollateralOwed,
+    // Paid out or withdrawn
+    Settled,
+    Inconsistent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Funding {
+    pub status: FundingStatus,
+    // What doesn't add up, set for inconsistent jobs
+    pub issue: Option<String>,
+}
+
+impl Funding {
+    fn ok(status: FundingStatus) -> Self {
+        Funding {
+            status,
+            issue: None,
+        }
+    }
+
+    fn inconsistent(issue: String) -> Self {
+        Funding {
+            status: FundingStatus::Inconsistent,
+            issue: Some(issue),
+        }
+    }
+}
+
+// Check the escrow and collateral of a job against its state
+// `escrow` is the record of `job.escrowId`, taken jobs without it are left unverified
+pub fn check_funding(job: &MarketPlaceData::JobPost, escrow: Option<&Unicrow::Escrow>) -> Funding {
+    if job.collateralOwed > job.amount {
+        return Funding::inconsistent(format!(
+            "collateral owed {} is above the reward {}",
+            job.collateralOwed, job.amount
+        ));
+    }
+    match JobState::from(job.state) {
+        JobState::Open => {
+            if !job.escrowId.is_zero() {
+                Funding::inconsistent(format!("open job with escrow #{}", job.escrowId))
+            } else if !job.collateralOwed.is_zero() {
+                Funding::inconsistent("open job owing collateral".to_string())
+            } else {
+                Funding::ok(FundingStatus::Held)
+            }
+        }
+        JobState::Taken if job.escrowId.is_zero() => {
+            Funding::inconsistent("taken job without escrow".to_string())
+        }
+        JobState::Taken => match escrow.map(|escrow| check_escrow(job, escrow)) {
+            Some(Some(issue)) => Funding::inconsistent(issue),
+            Some(None) => Funding::ok(FundingStatus::Escrowed),
+            None => Funding::ok(FundingStatus::Unverified),
+        },
+        JobState::Closed if !job.collateralOwed.is_zero() => {
+            Funding::ok(FundingStatus::CollateralOwed)
+        }
+        JobState::Closed => Funding::ok(FundingStatus::Settled),
+        JobState::Unknown(state) => Funding::inconsistent(format!("unknown state {}", state)),
+    }
+}
+
+// What doesn't match between a taken job and its escrow record
+fn check_escrow(job: &MarketPlaceData::JobPost, escrow: &Unicrow::Escrow) -> Option<String> {
+    let id = &job.escrowId;
+    // Unknown escrows read as an empty record
+    if escrow.seller.is_zero() {
+        Some(format!("escrow #{} not found", id))
+    } else if escrow.seller != job.roles.worker {
+        Some(format!(
+            "escrow #{} pays {}, not the worker",
+            id, escrow.seller
+        ))
+    } else if escrow.currency != job.token {
+        Some(format!("escrow #{} is in another token", id))
+    } else if escrow.amount < job.amount {
+        Some(format!(
+            "escrow #{} holds {}, less than the reward {}",
+            id, escrow.amount, job.amount
+        ))
+    } else if escrow.claimed != 0 {
+        Some(format!("escrow #{} is already claimed", id))
+    } else {
+        None
+    }
+}
+
+// Record of the escrow `escrow_id`, read from the Unicrow contract of the marketplace
+#[tracing::instrument(name = "escrow_record", skip(provider))]
+pub async fn escrow_record<P: Provider + Clone>(
+    provider: P,
+    escrow_id: U256,
+) -> Result<Unicrow::Escrow> {
+    let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider.clone());
+    let marketplace = marketplace_data.marketplace().call().await?._0;
+    let unicrow = MarketplaceV1::new(marketplace, provider.clone())
+        .unicrow()
+        .call()
+        .await?
+        ._0;
+    let escrow = Unicrow::new(unicrow, provider)
+        .getEscrow(escrow_id)
+        .call()
+        .await?
+        ._0;
+    Ok(escrow)
+}
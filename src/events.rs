@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::JobNotification;
+
+// Job event types published by MarketplaceData through `JobEvent`
+// Mirrors the `JobEventType` enum of the EACC contracts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobEventType {
+    Created,
+    Taken,
+    Paid,
+    Updated,
+    Signed,
+    Completed,
+    Delivered,
+    Closed,
+    Reopened,
+    Rated,
+    Refunded,
+    Disputed,
+    Arbitrated,
+    ArbitrationRefused,
+    WhitelistedWorkerAdded,
+    WhitelistedWorkerRemoved,
+    CollateralWithdrawn,
+    WorkerMessage,
+    OwnerMessage,
+    Unknown(u8),
+}
+
+impl From<u8> for JobEventType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => JobEventType::Created,
+            1 => JobEventType::Taken,
+            2 => JobEventType::Paid,
+            3 => JobEventType::Updated,
+            4 => JobEventType::Signed,
+            5 => JobEventType::Completed,
+            6 => JobEventType::Delivered,
+            7 => JobEventType::Closed,
+            8 => JobEventType::Reopened,
+            9 => JobEventType::Rated,
+            10 => JobEventType::Refunded,
+            11 => JobEventType::Disputed,
+            12 => JobEventType::Arbitrated,
+            13 => JobEventType::ArbitrationRefused,
+            14 => JobEventType::WhitelistedWorkerAdded,
+            15 => JobEventType::WhitelistedWorkerRemoved,
+            16 => JobEventType::CollateralWithdrawn,
+            17 => JobEventType::WorkerMessage,
+            18 => JobEventType::OwnerMessage,
+            other => JobEventType::Unknown(other),
+        }
+    }
+}
+
+impl JobEventType {
+    // Status shown on the job announcements, None for events not changing them
+    pub fn status(self) -> Option<&'static str> {
+        match self {
+            // payStartJob takes the job for its whitelisted worker
+            JobEventType::Taken | JobEventType::Paid => Some("Taken"),
+            JobEventType::Completed => Some("Completed"),
+            JobEventType::Closed => Some("Closed"),
+            JobEventType::Reopened => Some("Reopened"),
+            JobEventType::Updated => Some("Updated"),
+            JobEventType::Refunded => Some("Refunded"),
+            JobEventType::Disputed => Some("Disputed"),
+            JobEventType::Arbitrated => Some("Arbitrated"),
+            JobEventType::ArbitrationRefused => Some("Arbitration refused"),
+            _ => None,
+        }
+    }
+}
+
+// A published job changed state, its announcements are edited
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JobStatusNotification {
+    pub event: JobEventType,
+    pub status: String,
+    // The job as it is after the event
+    pub job: JobNotification,
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{JobNotification, Notification};
+
+// Conditions a job must meet to be sent to a sink, set per sink in the config file
+// Every condition set must match, a list matches when any of its entries does
+// Empty lists and unset minimums match every job
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct FilterRule {
+    // Job tags, at least one must be present / none may be present
+    pub tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+    // Token symbols ("ARB") or addresses
+    pub tokens: Vec<String>,
+    // Minimum reward, jobs without a USD value never match `min_usd`
+    pub min_usd: Option<f64>,
+    pub min_amount: Option<f64>,
+    // e.g. "ipfs", "digital proof"
+    pub delivery_methods: Vec<String>,
+    // Creator addresses
+    pub creators: Vec<String>,
+    pub exclude_creators: Vec<String>,
+    // Case insensitive words looked up in the title and description
+    pub keywords: Vec<String>,
+    pub exclude_keywords: Vec<String>,
+    // Deliveries are checked against the same conditions as their job
+    pub deliveries: bool,
+}
+
+impl Default for FilterRule {
+    fn default() -> Self {
+        FilterRule {
+            tags: Vec::new(),
+            exclude_tags: Vec::new(),
+            tokens: Vec::new(),
+            min_usd: None,
+            min_amount: None,
+            delivery_methods: Vec::new(),
+            creators: Vec::new(),
+            exclude_creators: Vec::new(),
+            keywords: Vec::new(),
+            exclude_keywords: Vec::new(),
+            deliveries: true,
+        }
+    }
+}
+
+fn contains_ignore_case(list: &[String], value: &str) -> bool {
+    list.iter().any(|entry| entry.eq_ignore_ascii_case(value))
+}
+
+impl FilterRule {
+    pub fn matches(&self, notification: &Notification) -> bool {
+        match notification {
+            Notification::JobPublished(job) => self.matches_job(job),
+            Notification::JobDelivered(delivery) => {
+                self.deliveries && self.matches_job(&delivery.job)
+            }
+            // Only existing announcements are edited, they matched when posted
+            Notification::JobUpdated(status) => self.matches_job(&status.job),
+            // Digests and announcements go to the sinks enabled in their own settings
+            Notification::Digest(_) | Notification::ArbitratorRegistered(_) => true,
+        }
+    }
+
+    pub fn matches_job(&self, job: &JobNotification) -> bool {
+        // Short forms and the long forms of the MECE tags both match
+        let tags: Vec<&String> = job
+            .job
+            .tags
+            .iter()
+            .chain(job.tags.iter().map(|label| &label.name))
+            .collect();
+        if !self.tags.is_empty() && !tags.iter().any(|tag| contains_ignore_case(&self.tags, tag)) {
+            return false;
+        }
+        if tags
+            .iter()
+            .any(|tag| contains_ignore_case(&self.exclude_tags, tag))
+        {
+            return false;
+        }
+
+        if !self.tokens.is_empty()
+            && !contains_ignore_case(&self.tokens, &job.symbol)
+            && !contains_ignore_case(&self.tokens, &job.token.address.to_string())
+        {
+            return false;
+        }
+
+        if let Some(min_usd) = self.min_usd {
+            if job.usd_value.is_none_or(|usd_value| usd_value < min_usd) {
+                return false;
+            }
+        }
+        if let Some(min_amount) = self.min_amount {
+            if job.amount.to_f64() < min_amount {
+                return false;
+            }
+        }
+
+        if !self.delivery_methods.is_empty()
+            && !contains_ignore_case(&self.delivery_methods, &job.job.delivery_method)
+        {
+            return false;
+        }
+
+        if !self.creators.is_empty() && !contains_ignore_case(&self.creators, &job.job.creator) {
+            return false;
+        }
+        if contains_ignore_case(&self.exclude_creators, &job.job.creator) {
+            return false;
+        }
+
+        let text = format!("{}\n{}", job.title, job.description.markdown).to_lowercase();
+        self.matches_keywords(&text)
+    }
+
+    // `text` is expected lowercase
+    fn matches_keywords(&self, text: &str) -> bool {
+        let found = |keyword: &String| text.contains(&keyword.to_lowercase());
+        if !self.keywords.is_empty() && !self.keywords.iter().any(found) {
+            return false;
+        }
+        !self.exclude_keywords.iter().any(found)
+    }
+}
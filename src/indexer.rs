@@ -0,0 +1,379 @@
+use alloy::primitives::U256;
+use alloy::providers::{MulticallBuilder, Provider};
+use eyre::Result;
+use futures::stream::StreamExt;
+use serde::Deserialize;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::Row;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    digest::{block_ranges, LOG_BLOCK_RANGE},
+    events::JobEventType,
+    models::JobDetails,
+    MarketPlaceData, MARKETPLACE_DATA_ADDRESS,
+};
+
+// Batch sizes of the historical indexer, set under `indexer` in the config file
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct IndexerSettings {
+    // Jobs read per getJobs call, their event counts are read in one multicall
+    pub jobs_batch_size: u64,
+    // Most events read per getEvents call
+    pub events_batch_size: u64,
+    // getEvents calls packed in one multicall
+    pub multicall_size: usize,
+}
+
+impl Default for IndexerSettings {
+    fn default() -> Self {
+        IndexerSettings {
+            jobs_batch_size: 50,
+            events_batch_size: 100,
+            multicall_size: 20,
+        }
+    }
+}
+
+// Events of a job missing from the database, read with one getEvents call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventRange {
+    pub job_id: u64,
+    pub start: u64,
+    pub limit: u64,
+}
+
+// Ranges between the `stored` and `onchain` event counts, at most `batch` events each
+pub fn event_ranges(job_id: u64, stored: u64, onchain: u64, batch: u64) -> Vec<EventRange> {
+    let batch = batch.max(1);
+    (stored..onchain)
+        .step_by(batch as usize)
+        .map(|start| EventRange {
+            job_id,
+            start,
+            limit: batch.min(onchain - start),
+        })
+        .collect()
+}
+
+// Jobs and events mirrored in Postgres
+pub struct JobStore {
+    pool: PgPool,
+}
+
+impl JobStore {
+    // Connect and apply the pending migrations
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(database_url)
+            .await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        Ok(JobStore { pool })
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    pub async fn upsert_job(&self, job_id: u64, title: &str, job: &JobDetails) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO jobs (job_id, title, state, creator, worker, arbitrator, tags,
+                content_hash, token, amount, delivery_method, max_time, collateral_owed,
+                escrow_id, result_hash, rating, disputed, multiple_applicants,
+                whitelist_workers, job_timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10::numeric, $11, $12,
+                $13::numeric, $14::numeric, $15, $16, $17, $18, $19, $20)
+            ON CONFLICT (job_id) DO UPDATE SET
+                title = EXCLUDED.title,
+                state = EXCLUDED.state,
+                worker = EXCLUDED.worker,
+                arbitrator = EXCLUDED.arbitrator,
+                tags = EXCLUDED.tags,
+                content_hash = EXCLUDED.content_hash,
+                amount = EXCLUDED.amount,
+                delivery_method = EXCLUDED.delivery_method,
+                max_time = EXCLUDED.max_time,
+                collateral_owed = EXCLUDED.collateral_owed,
+                result_hash = EXCLUDED.result_hash,
+                rating = EXCLUDED.rating,
+                disputed = EXCLUDED.disputed,
+                multiple_applicants = EXCLUDED.multiple_applicants,
+                whitelist_workers = EXCLUDED.whitelist_workers,
+                job_timestamp = EXCLUDED.job_timestamp,
+                indexed_at = now()",
+        )
+        .bind(job_id as i64)
+        .bind(title)
+        .bind(i16::from(u8::from(job.state)))
+        .bind(&job.creator)
+        .bind(&job.worker)
+        .bind(&job.arbitrator)
+        .bind(&job.tags)
+        .bind(&job.content_hash)
+        .bind(&job.token)
+        .bind(&job.amount)
+        .bind(&job.delivery_method)
+        .bind(i64::from(job.max_time))
+        .bind(&job.collateral_owed)
+        .bind(&job.escrow_id)
+        .bind(&job.result_hash)
+        .bind(i16::from(job.rating))
+        .bind(job.disputed)
+        .bind(job.multiple_applicants)
+        .bind(job.whitelist_workers)
+        .bind(i64::from(job.timestamp))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // Events stored per job, for the jobs in `start..end`
+    pub async fn event_counts(&self, start: u64, end: u64) -> Result<HashMap<u64, u64>> {
+        let rows = sqlx::query(
+            "SELECT job_id, COUNT(*) AS events FROM job_events
+            WHERE job_id >= $1 AND job_id < $2 GROUP BY job_id",
+        )
+        .bind(start as i64)
+        .bind(end as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                let job_id: i64 = row.try_get("job_id")?;
+                let events: i64 = row.try_get("events")?;
+                Ok((job_id as u64, events as u64))
+            })
+            .collect()
+    }
+
+    // Last block whose job events are stored, None before the first full backfill
+    pub async fn last_block(&self) -> Result<Option<u64>> {
+        let row = sqlx::query("SELECT last_block FROM indexer_state")
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| Ok(row.try_get::<i64, _>("last_block")? as u64))
+            .transpose()
+    }
+
+    pub async fn set_last_block(&self, block: u64) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO indexer_state (id, last_block) VALUES (TRUE, $1)
+            ON CONFLICT (id) DO UPDATE SET last_block = EXCLUDED.last_block",
+        )
+        .bind(block as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // Store events starting at index `start`, already stored ones are kept
+    pub async fn insert_events(
+        &self,
+        job_id: u64,
+        start: u64,
+        events: &[MarketPlaceData::JobEventData],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for (index, event) in (start..).zip(events) {
+            sqlx::query(
+                "INSERT INTO job_events (job_id, event_index, event_type, event_name, address,
+                    data, event_timestamp)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (job_id, event_index) DO NOTHING",
+            )
+            .bind(job_id as i64)
+            .bind(index as i64)
+            .bind(i16::from(event.type_))
+            .bind(format!("{:?}", JobEventType::from(event.type_)))
+            .bind(event.address_.to_vec())
+            .bind(event.data_.to_vec())
+            .bind(i64::from(event.timestamp_))
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+// Copies every job and its event history from MarketplaceData into a JobStore
+pub struct Indexer<P> {
+    provider: P,
+    store: JobStore,
+    settings: IndexerSettings,
+}
+
+impl<P: Provider + Clone> Indexer<P> {
+    pub fn new(provider: P, store: JobStore, settings: IndexerSettings) -> Self {
+        Indexer {
+            provider,
+            store,
+            settings,
+        }
+    }
+
+    // Walk every job, only the events missing from the database are read
+    // Returns the number of events stored
+    #[tracing::instrument(name = "indexer_backfill", skip(self))]
+    pub async fn backfill(&self) -> Result<usize> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &self.provider);
+        // Jobs are read after this block, so its events are all covered
+        let latest = self.provider.get_block_number().await?;
+        let jobs_length: u64 = marketplace_data.jobsLength().call().await?._0.to();
+        let mut stored = 0;
+        let mut index = 0;
+        while index < jobs_length {
+            let limit = self
+                .settings
+                .jobs_batch_size
+                .max(1)
+                .min(jobs_length - index);
+            let jobs = marketplace_data
+                .getJobs(U256::from(index), U256::from(limit))
+                .call()
+                .await?
+                ._0;
+            let end = index + jobs.len() as u64;
+
+            // Event counts of the whole page in one call
+            let events_lengths = MulticallBuilder::new_dynamic(&self.provider)
+                .extend(
+                    (index..end).map(|job_id| marketplace_data.eventsLength(U256::from(job_id))),
+                )
+                .aggregate()
+                .await?;
+            let known = self.store.event_counts(index, end).await?;
+
+            let mut ranges = Vec::new();
+            for ((job_id, job), events_length) in (index..).zip(&jobs).zip(events_lengths) {
+                self.store
+                    .upsert_job(job_id, &job.title, &JobDetails::from(job))
+                    .await?;
+                ranges.extend(event_ranges(
+                    job_id,
+                    known.get(&job_id).copied().unwrap_or_default(),
+                    events_length._0.to(),
+                    self.settings.events_batch_size,
+                ));
+            }
+            stored += self.store_events(&ranges).await?;
+            tracing::info!("Indexed jobs {}..{} of {}", index, end, jobs_length);
+            index = end;
+        }
+        self.store.set_last_block(latest).await?;
+        Ok(stored)
+    }
+
+    // Sync the jobs that emitted a JobEvent since block `from`
+    #[tracing::instrument(name = "indexer_catch_up", skip(self))]
+    pub async fn catch_up(&self, from: u64) -> Result<usize> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &self.provider);
+        let latest = self.provider.get_block_number().await?;
+        let mut job_ids = BTreeSet::new();
+        for (from, to) in block_ranges(from, latest, LOG_BLOCK_RANGE) {
+            let logs = marketplace_data
+                .JobEvent_filter()
+                .from_block(from)
+                .to_block(to)
+                .query()
+                .await?;
+            job_ids.extend(
+                logs.iter()
+                    .map(|(event, _)| event.jobId.saturating_to::<u64>()),
+            );
+        }
+        let mut stored = 0;
+        for job_id in job_ids {
+            stored += self.sync_job(job_id).await?;
+        }
+        self.store.set_last_block(latest).await?;
+        Ok(stored)
+    }
+
+    // Read the event ranges with multicalls and store them
+    async fn store_events(&self, ranges: &[EventRange]) -> Result<usize> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &self.provider);
+        let mut stored = 0;
+        for chunk in ranges.chunks(self.settings.multicall_size.max(1)) {
+            let pages = MulticallBuilder::new_dynamic(&self.provider)
+                .extend(chunk.iter().map(|range| {
+                    marketplace_data.getEvents(
+                        U256::from(range.job_id),
+                        U256::from(range.start),
+                        U256::from(range.limit),
+                    )
+                }))
+                .aggregate()
+                .await?;
+            for (range, page) in chunk.iter().zip(pages) {
+                self.store
+                    .insert_events(range.job_id, range.start, &page._0)
+                    .await?;
+                stored += page._0.len();
+            }
+        }
+        Ok(stored)
+    }
+
+    // Refresh one job and store its new events
+    #[tracing::instrument(name = "indexer_sync_job", skip(self))]
+    pub async fn sync_job(&self, job_id: u64) -> Result<usize> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &self.provider);
+        let job = marketplace_data.getJob(U256::from(job_id)).call().await?._0;
+        self.store
+            .upsert_job(job_id, &job.title, &JobDetails::from(&job))
+            .await?;
+        let events_length: u64 = marketplace_data
+            .eventsLength(U256::from(job_id))
+            .call()
+            .await?
+            ._0
+            .to();
+        let known = self.store.event_counts(job_id, job_id + 1).await?;
+        let ranges = event_ranges(
+            job_id,
+            known.get(&job_id).copied().unwrap_or_default(),
+            events_length,
+            self.settings.events_batch_size,
+        );
+        self.store_events(&ranges).await
+    }
+
+    // Keep the mirror current from the live JobEvent stream
+    // After subscribing, only the blocks since the last indexed one are read,
+    // a full backfill runs when nothing was indexed yet
+    #[tracing::instrument(name = "indexer_follow", skip(self))]
+    pub async fn follow(&self) -> Result<()> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &self.provider);
+        let subscription = marketplace_data.JobEvent_filter().subscribe().await?;
+        let mut event_stream = subscription.into_stream();
+        let stored = match self.store.last_block().await? {
+            Some(block) => self.catch_up(block + 1).await?,
+            None => self.backfill().await?,
+        };
+        tracing::info!("Caught up, {} new events stored", stored);
+
+        while let Some(log) = event_stream.next().await {
+            match log {
+                Ok((event, raw_log)) => {
+                    let job_id: u64 = event.jobId.to();
+                    match self.sync_job(job_id).await {
+                        Ok(stored) => {
+                            tracing::info!("Indexed {} new events of job {}", stored, job_id);
+                            if let Some(block) = raw_log.block_number {
+                                if let Err(e) = self.store.set_last_block(block).await {
+                                    tracing::error!("Failed to store the last block: {}", e);
+                                }
+                            }
+                        }
+                        Err(e) => tracing::error!("Failed to index job {}: {}", job_id, e),
+                    }
+                }
+                Err(e) => tracing::error!("Error in stream: {:?}", e),
+            }
+        }
+        Err(eyre::eyre!("JobEvent stream ended"))
+    }
+}
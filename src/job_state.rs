@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{error::AppError, events::JobEventType, models::JobDetails};
+
+// `JobPost.state` as stored onchain
+// Serialized as the raw number, templates compare it with `job.state != 0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum JobState {
+    #[default]
+    Open,
+    Taken,
+    Closed,
+    Unknown(u8),
+}
+
+impl From<u8> for JobState {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => JobState::Open,
+            1 => JobState::Taken,
+            2 => JobState::Closed,
+            other => JobState::Unknown(other),
+        }
+    }
+}
+
+impl From<JobState> for u8 {
+    fn from(state: JobState) -> Self {
+        match state {
+            JobState::Open => 0,
+            JobState::Taken => 1,
+            JobState::Closed => 2,
+            JobState::Unknown(other) => other,
+        }
+    }
+}
+
+// Where a job is in its lifecycle, finer than the onchain state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum JobStatus {
+    #[default]
+    Open,
+    Taken,
+    // Taken, the result is waiting for the creator's approval
+    Delivered,
+    // Taken, waiting for the arbitrator
+    Disputed,
+    // Closed with the result approved
+    Completed,
+    // Closed by the arbitrator
+    Arbitrated,
+    // Closed without a result, by the creator or after a refused arbitration
+    Closed,
+    Unknown(u8),
+}
+
+impl JobStatus {
+    pub fn state(self) -> JobState {
+        match self {
+            JobStatus::Open => JobState::Open,
+            JobStatus::Taken | JobStatus::Delivered | JobStatus::Disputed => JobState::Taken,
+            JobStatus::Completed | JobStatus::Arbitrated | JobStatus::Closed => JobState::Closed,
+            JobStatus::Unknown(other) => JobState::Unknown(other),
+        }
+    }
+
+    // Best guess from a `getJob` snapshot, the events tell Completed and Closed apart reliably
+    pub fn from_snapshot(state: JobState, disputed: bool, delivered: bool) -> Self {
+        match (state, disputed, delivered) {
+            (JobState::Open, _, _) => JobStatus::Open,
+            (JobState::Taken, true, _) => JobStatus::Disputed,
+            (JobState::Taken, false, true) => JobStatus::Delivered,
+            (JobState::Taken, false, false) => JobStatus::Taken,
+            (JobState::Closed, true, _) => JobStatus::Arbitrated,
+            (JobState::Closed, false, true) => JobStatus::Completed,
+            (JobState::Closed, false, false) => JobStatus::Closed,
+            (JobState::Unknown(other), _, _) => JobStatus::Unknown(other),
+        }
+    }
+
+    // Status after `event`, messages and other bookkeeping events keep it
+    pub fn apply(self, event: JobEventType) -> Result<Self, AppError> {
+        use JobEventType as Event;
+        use JobStatus as Status;
+        let next = match (self, event) {
+            (Status::Open, Event::Taken | Event::Paid) => Status::Taken,
+            (Status::Open, Event::Closed) => Status::Closed,
+            (Status::Taken, Event::Delivered) => Status::Delivered,
+            (Status::Taken | Status::Delivered, Event::Completed) => Status::Completed,
+            (Status::Taken | Status::Delivered, Event::Disputed) => Status::Disputed,
+            (Status::Taken | Status::Delivered, Event::Refunded) => Status::Open,
+            (Status::Disputed, Event::Arbitrated) => Status::Arbitrated,
+            (Status::Disputed, Event::ArbitrationRefused) => Status::Closed,
+            (Status::Closed, Event::Reopened) => Status::Open,
+            (
+                _,
+                Event::Updated
+                | Event::Signed
+                | Event::Rated
+                | Event::WhitelistedWorkerAdded
+                | Event::WhitelistedWorkerRemoved
+                | Event::CollateralWithdrawn
+                | Event::WorkerMessage
+                | Event::OwnerMessage
+                | Event::Unknown(_),
+            ) => self,
+            (status, event) => return Err(AppError::InvalidTransition { status, event }),
+        };
+        Ok(next)
+    }
+
+    // Status after the full event history of a job, which starts with Created
+    pub fn replay(events: impl IntoIterator<Item = JobEventType>) -> Result<Self, AppError> {
+        let mut events = events.into_iter();
+        match events.next() {
+            Some(JobEventType::Created) => {}
+            Some(event) => {
+                return Err(AppError::EventParsing(format!(
+                    "job history starts with {:?}",
+                    event
+                )))
+            }
+            None => return Err(AppError::EventParsing("empty job history".to_string())),
+        }
+        events.try_fold(JobStatus::Open, JobStatus::apply)
+    }
+}
+
+// Status of the jobs seen on the JobEvent stream
+#[derive(Debug, Default)]
+pub struct JobTracker {
+    statuses: HashMap<String, JobStatus>,
+}
+
+impl JobTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self, job_id: &str) -> Option<JobStatus> {
+        self.statuses.get(job_id).copied()
+    }
+
+    // Advance a job without a snapshot, jobs seen for the first time are only tracked from Created
+    // An invalid transition forgets the job, it is seeded again by the next snapshot
+    pub fn advance(
+        &mut self,
+        job_id: &str,
+        event: JobEventType,
+    ) -> Result<Option<JobStatus>, AppError> {
+        let next = match (self.status(job_id), event) {
+            (_, JobEventType::Created) => JobStatus::Open,
+            (Some(status), event) => match status.apply(event) {
+                Ok(next) => next,
+                Err(e) => {
+                    self.statuses.remove(job_id);
+                    return Err(e);
+                }
+            },
+            (None, _) => return Ok(None),
+        };
+        self.statuses.insert(job_id.to_string(), next);
+        Ok(Some(next))
+    }
+
+    // Advance a job and check it against the `getJob` snapshot read after the event
+    // On drift the snapshot wins, the error says what the events predicted
+    pub fn observe(
+        &mut self,
+        job_id: &str,
+        event: JobEventType,
+        snapshot: &JobDetails,
+    ) -> Result<JobStatus, AppError> {
+        let expected = match self.advance(job_id, event) {
+            Ok(Some(status)) => status,
+            // Unknown job, the snapshot is all there is
+            Ok(None) => {
+                self.statuses.insert(job_id.to_string(), snapshot.status);
+                return Ok(snapshot.status);
+            }
+            Err(e) => {
+                self.statuses.insert(job_id.to_string(), snapshot.status);
+                return Err(e);
+            }
+        };
+        if expected.state() != snapshot.state {
+            self.statuses.insert(job_id.to_string(), snapshot.status);
+            return Err(AppError::StateDrift {
+                job_id: job_id.to_string(),
+                expected,
+                actual: snapshot.state,
+            });
+        }
+        Ok(expected)
+    }
+}
@@ -1,17 +1,61 @@
 // use actix_web::{HttpRequest, HttpResponse, Responder};
-use alloy::primitives::utils::format_units;
-use alloy::{consensus::Transaction, primitives::address, providers::Provider, sol};
+use alloy::{
+    consensus::Transaction,
+    primitives::{address, Address, U256},
+    providers::Provider,
+    sol,
+};
+use amount::TokenAmount;
+use content::JobContent;
+use deliverables::{fetch_deliverable, result_hash_from_event, DeliveryNotification};
+use digest::Digest;
+use directory::Directory;
+use escrow::{check_funding, escrow_record, Funding};
+use events::{JobEventType, JobStatusNotification};
 use eyre::Result;
 use futures::stream::StreamExt;
-use serde::Serialize;
+use job_state::{JobState, JobTracker};
+use models::{fetch_participants, ArbitratorProfile, JobDetails, UserProfile, UserRating};
+use pricing::PriceOracle;
+use reputation::ReviewIndex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tags::{JobTag, TagCatalogue};
+use tokens::{TokenInfo, TokenRegistry};
 use tokio::sync::mpsc;
 use utils::get_from_ipfs;
 
+pub mod admin;
+pub mod amount;
+pub mod cards;
+pub mod config;
+pub mod content;
+pub mod deliverables;
+pub mod digest;
+pub mod directory;
 pub mod error;
+pub mod escrow;
+pub mod events;
+pub mod filters;
+pub mod indexer;
+pub mod job_state;
+pub mod models;
+pub mod posts;
+pub mod pricing;
+pub mod reputation;
+pub mod tags;
 pub mod telegram_api;
+pub mod telegram_bot;
+pub mod telegram_format;
 pub mod telemetry;
+pub mod templates;
+pub mod tokens;
 pub mod utils;
 pub mod x_api;
+pub mod x_client;
+pub mod x_format;
+pub mod x_oauth;
+pub mod x_scheduler;
 
 sol!(
     #[allow(missing_docs)]
@@ -21,6 +65,34 @@ sol!(
     "./src/abis/MarketplaceData.json"
 );
 
+// Upgradeable proxy of the marketplace, only its admin events
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    #[derive(Debug)]
+    MarketplaceProxy,
+    "./src/abis/Marketplace.json"
+);
+
+// Implementation behind the proxy, a partial ABI with only the
+// `marketplaceData`, `unicrow` and `unicrowMarketplaceAddress` getters
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    #[derive(Debug)]
+    MarketplaceV1,
+    "./src/abis/MarketplaceV1.json"
+);
+
+// Escrow contract the marketplace pays taken jobs into, only `getEscrow`
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    #[derive(Debug)]
+    Unicrow,
+    "./src/abis/Unicrow.json"
+);
+
 sol!(
     #[allow(missing_docs)]
     #[sol(rpc)]
@@ -29,52 +101,376 @@ sol!(
     "./src/abis/IERC20.json"
 );
 
+pub const MARKETPLACE_DATA_ADDRESS: Address = address!("0191ae69d05F11C7978cCCa2DE15653BaB509d9a");
+
 // Job notification struct
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct JobNotification {
     pub job_id: String,
     pub title: String,
-    pub description: String,
-    pub amount: f64,
+    pub description: JobContent,
+    pub amount: TokenAmount,
     pub symbol: String,
+    pub token: TokenInfo,
+    pub job: JobDetails,
+    // Labels of `job.tags`, with the long forms of the MECE tags
+    #[serde(default)]
+    pub tags: Vec<JobTag>,
+    // Escrow and collateral check of the reward
+    #[serde(default)]
+    pub funding: Option<Funding>,
+    pub creator_profile: Option<UserProfile>,
+    pub creator_rating: Option<UserRating>,
+    pub arbitrator_profile: Option<ArbitratorProfile>,
+    // Set once the job is taken by a registered worker
+    #[serde(default)]
+    pub worker_profile: Option<UserProfile>,
+    // Reward value in USD, None when no price source knows the token
+    pub usd_value: Option<f64>,
+}
+
+// Notifications dispatched to the platform workers
+// Few notifications are in flight at once, boxing the job isn't worth it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Notification {
+    JobPublished(JobNotification),
+    JobDelivered(DeliveryNotification),
+    JobUpdated(JobStatusNotification),
+    // Scheduled activity summary, not about one job
+    Digest(Digest),
+    // "New arbitrator available" announcement
+    ArbitratorRegistered(ArbitratorProfile),
+}
+
+impl Notification {
+    pub fn job_id(&self) -> &str {
+        match self {
+            Notification::JobPublished(job) => &job.job_id,
+            Notification::JobDelivered(delivery) => &delivery.job_id,
+            Notification::JobUpdated(status) => &status.job.job_id,
+            Notification::Digest(_) | Notification::ArbitratorRegistered(_) => "",
+        }
+    }
+}
+
+// Everything the notifications show about a job, read from the chain and IPFS
+// Profiles missing from the chain reads are looked up in the directory
+#[tracing::instrument(
+    name = "build_job_notification",
+    skip(provider, tokens, prices, directory, reviews, catalogue)
+)]
+pub async fn build_job_notification(
+    provider: impl Provider + Clone,
+    job_id: U256,
+    tokens: &TokenRegistry,
+    prices: &PriceOracle,
+    directory: &Directory,
+    reviews: Option<&ReviewIndex>,
+    catalogue: &TagCatalogue,
+) -> Result<JobNotification> {
+    // Get The JobPost data
+    let job = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider.clone())
+        .getJob(job_id)
+        .call()
+        .await?
+        ._0;
+    // Token metadata is cached, only unknown tokens hit the RPC
+    let token = tokens.resolve(provider.clone(), job.token).await;
+    let token_symbol = token.symbol.clone();
+    // Keep the raw amount, it is only rounded when displayed
+    let amount = TokenAmount::new(job.amount, token.decimals);
+    let usd_value = prices.usd_value(&token, &amount).await;
+    tracing::debug!("    - Job Title: {}", job.title);
+
+    tracing::debug!("    - Job Amount: {} ${}", amount, token_symbol);
+    tracing::debug!("    - Job deliveryMethod: {}", job.deliveryMethod);
+    tracing::debug!("    - Job contentHash: {}", job.contentHash);
+
+    // Get content from IPFS
+    // Call the function
+    let job_description = match get_from_ipfs(&job.contentHash.to_string(), "").await {
+        Ok(data) => {
+            tracing::debug!("    - Job Description: {}", data);
+            data
+        }
+        Err(e) => {
+            return Err(eyre::eyre!(
+                "Failed to fetch job description from IPFS: {}",
+                e
+            ));
+        }
+    };
+
+    // Who posted the job and who arbitrates it
+    let participants =
+        match fetch_participants(provider.clone(), job.roles.creator, job.roles.arbitrator).await {
+            Ok(participants) => participants,
+            Err(e) => {
+                tracing::error!("    - Failed to fetch job participants: {}", e);
+                Default::default()
+            }
+        };
+    let creator_profile = participants
+        .creator_profile
+        .or_else(|| directory.user(&job.roles.creator.to_string()));
+    // With the review index the badge is built from the indexed reviews of the creator
+    let creator_rating = match reviews {
+        Some(reviews) => {
+            reviews
+                .rating(
+                    provider.clone(),
+                    job.roles.creator,
+                    participants.creator_rating.as_ref(),
+                )
+                .await
+        }
+        None => participants.creator_rating,
+    };
+    let arbitrator_profile = participants.arbitrator_profile.or_else(|| {
+        directory
+            .arbitrator(&job.roles.arbitrator.to_string())
+            .filter(|_| !job.roles.arbitrator.is_zero())
+    });
+    // Taken jobs are checked against their escrow, unverified when it can't be read
+    let escrow = if JobState::from(job.state) == JobState::Taken && !job.escrowId.is_zero() {
+        match escrow_record(provider.clone(), job.escrowId).await {
+            Ok(escrow) => Some(escrow),
+            Err(e) => {
+                tracing::error!("    - Failed to read escrow #{}: {}", job.escrowId, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let funding = check_funding(&job, escrow.as_ref());
+    if let Some(issue) = &funding.issue {
+        tracing::warn!("Job {} has inconsistent funding: {}", job_id, issue);
+    }
+    let worker_profile = directory
+        .user(&job.roles.worker.to_string())
+        .filter(|_| !job.roles.worker.is_zero());
+
+    Ok(JobNotification {
+        job_id: job_id.to_string(),
+        description: JobContent::parse(&job_description),
+        amount,
+        symbol: token_symbol,
+        token,
+        job: JobDetails::from(&job),
+        tags: catalogue.labels(&job_id.to_string(), &job.tags),
+        funding: Some(funding),
+        title: job.title,
+        creator_profile,
+        creator_rating,
+        arbitrator_profile,
+        worker_profile,
+        usd_value,
+    })
 }
 
 // Filter for PublishJobEvents
-#[tracing::instrument(name = "filter_publish_job_events", skip(provider))]
+#[tracing::instrument(
+    name = "filter_publish_job_events",
+    skip(provider, tokens, prices, directory, reviews, catalogue)
+)]
 pub async fn filter_publish_job_events(
     provider: impl Provider + Clone,
-    queue_sender: mpsc::Sender<JobNotification>,
+    queue_sender: mpsc::Sender<Notification>,
+    tokens: Arc<TokenRegistry>,
+    prices: Arc<PriceOracle>,
+    directory: Arc<Directory>,
+    reviews: Option<Arc<ReviewIndex>>,
+    catalogue: Arc<TagCatalogue>,
 ) -> Result<()> {
-    let marketplace_data = MarketPlaceData::new(
-        address!("0191ae69d05F11C7978cCCa2DE15653BaB509d9a"),
-        provider.clone(),
-    );
+    let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider.clone());
 
     let filter = marketplace_data.JobEvent_filter().from_block(278858754);
 
     match filter.subscribe().await {
         Ok(subscription) => {
             let mut event_stream = subscription.into_stream();
+            // Lifecycle of the jobs seen so far, checked against every snapshot read
+            let mut tracker = JobTracker::new();
 
             while let Some(log) = event_stream.next().await {
                 match log {
                     Ok((event, raw_log)) => {
+                        let job_id = event.jobId.to_string();
+                        let event_type = JobEventType::from(event.eventData.type_);
+                        // The events read with getJob below are checked there
+                        if event_type != JobEventType::Delivered && event_type.status().is_none() {
+                            if let Err(e) = tracker.advance(&job_id, event_type) {
+                                tracing::warn!("    - Job {}: {}", job_id, e);
+                            }
+                        }
+
+                        // Ratings come with a review of the worker
+                        if let (JobEventType::Rated, Some(reviews)) = (event_type, &reviews) {
+                            match marketplace_data.getJob(event.jobId).call().await {
+                                Ok(job) => {
+                                    let worker = job._0.roles.worker;
+                                    if let Err(e) = reviews.refresh(provider.clone(), worker).await
+                                    {
+                                        tracing::error!(
+                                            "    - Failed to read the reviews of {}: {}",
+                                            worker,
+                                            e
+                                        );
+                                    }
+                                }
+                                Err(e) => tracing::error!("    - Failed to load the job: {}", e),
+                            }
+                        }
+
+                        // Delivered events carry the result hash, no need to look at the caller
+                        if event_type == JobEventType::Delivered {
+                            tracing::info!("Handling Delivered event...");
+                            let job = match marketplace_data.getJob(event.jobId).call().await {
+                                Ok(job) => job._0,
+                                Err(e) => {
+                                    tracing::error!(
+                                        "    - Failed to load the delivered job: {}",
+                                        e
+                                    );
+                                    continue;
+                                }
+                            };
+                            if let Err(e) =
+                                tracker.observe(&job_id, event_type, &JobDetails::from(&job))
+                            {
+                                tracing::warn!("    - {}", e);
+                            }
+                            let result_hash =
+                                result_hash_from_event(&event.eventData.data_, job.resultHash);
+                            tracing::debug!("    - Job resultHash: {}", result_hash);
+
+                            let preview = match fetch_deliverable(result_hash).await {
+                                Ok(preview) => preview,
+                                Err(e) => {
+                                    tracing::error!(
+                                        "    - Failed to fetch deliverable from IPFS: {}",
+                                        e
+                                    );
+                                    None
+                                }
+                            };
+
+                            // Sinks filter deliveries on their job like its announcement
+                            let delivered = match build_job_notification(
+                                provider.clone(),
+                                event.jobId,
+                                &tokens,
+                                &prices,
+                                &directory,
+                                reviews.as_deref(),
+                                &catalogue,
+                            )
+                            .await
+                            {
+                                Ok(delivered) => delivered,
+                                Err(e) => {
+                                    tracing::error!(
+                                        "    - Failed to load the delivered job: {}",
+                                        e
+                                    );
+                                    JobNotification {
+                                        job_id: job_id.clone(),
+                                        title: job.title.clone(),
+                                        job: JobDetails::from(&job),
+                                        tags: catalogue.labels(&job_id, &job.tags),
+                                        ..Default::default()
+                                    }
+                                }
+                            };
+                            let notification = Notification::JobDelivered(DeliveryNotification {
+                                job_id,
+                                title: job.title,
+                                result_hash: result_hash.to_string(),
+                                preview,
+                                job: delivered,
+                            });
+                            if let Err(e) = queue_sender.send(notification).await {
+                                tracing::error!(
+                                    "    - Error returned sending notification into the queue is: {}",
+                                    e
+                                );
+                            }
+                            continue;
+                        }
+
+                        // State changes refresh the announcements of the job
+                        if let Some(status) = event_type.status() {
+                            tracing::info!("Handling {:?} event...", event_type);
+                            let mut job = match build_job_notification(
+                                provider.clone(),
+                                event.jobId,
+                                &tokens,
+                                &prices,
+                                &directory,
+                                reviews.as_deref(),
+                                &catalogue,
+                            )
+                            .await
+                            {
+                                Ok(job) => job,
+                                Err(e) => {
+                                    tracing::error!("    - Failed to load the updated job: {}", e);
+                                    continue;
+                                }
+                            };
+                            // The events tell e.g. a completed job from a closed one
+                            match tracker.observe(&job_id, event_type, &job.job) {
+                                Ok(status) => job.job.status = status,
+                                Err(e) => tracing::warn!("    - {}", e),
+                            }
+                            let notification = Notification::JobUpdated(JobStatusNotification {
+                                event: event_type,
+                                status: status.to_string(),
+                                job,
+                            });
+                            if let Err(e) = queue_sender.send(notification).await {
+                                tracing::error!(
+                                    "    - Error returned sending notification into the queue is: {}",
+                                    e
+                                );
+                            }
+                            continue;
+                        }
+
                         // Found a new JobEvent -> evaluate who called it
 
-                        let tx_hash = raw_log
-                            .transaction_hash
-                            .ok_or("No transaction hash in log")
-                            .unwrap();
+                        let Some(tx_hash) = raw_log.transaction_hash else {
+                            tracing::error!("    - No transaction hash in log");
+                            continue;
+                        };
                         tracing::info!("Tx hash -> {tx_hash}");
 
-                        let tx = provider
-                            .get_transaction_by_hash(tx_hash)
-                            .await?
-                            .ok_or("Transaction not found")
-                            .unwrap();
+                        let tx = match provider.get_transaction_by_hash(tx_hash).await {
+                            Ok(Some(tx)) => tx,
+                            Ok(None) => {
+                                tracing::error!("    - Transaction {} not found", tx_hash);
+                                continue;
+                            }
+                            Err(e) => {
+                                tracing::error!(
+                                    "    - Failed to load transaction {}: {}",
+                                    tx_hash,
+                                    e
+                                );
+                                continue;
+                            }
+                        };
 
                         let input_data = tx.input();
-                        let function_selector = &input_data[..4];
+                        let Some(function_selector) = input_data.get(..4) else {
+                            tracing::error!(
+                                "    - Transaction {} has no function selector",
+                                tx_hash
+                            );
+                            continue;
+                        };
                         // Map MethodIDs to function signatures
                         let function_signature = match function_selector {
                             [0x3a, 0x08, 0x08, 0x39] => {
@@ -97,55 +493,27 @@ pub async fn filter_publish_job_events(
                                 tracing::info!("Handling publishJobEvent...");
                                 // Access event data
                                 tracing::debug!("Event Data: {:?}", event_data);
-                                // Get The JobPost data
-                                let job = marketplace_data.getJob(event.jobId).call().await?._0;
-                                let token_contract = IERC20::new(job.token, provider.clone());
-
-                                // Use multicall when possible to reduce amount of requests to public RPC
-                                let multicall = provider
-                                    .multicall()
-                                    .add(token_contract.symbol())
-                                    .add(token_contract.decimals());
-                                let (token_symbol, token_decimals) = multicall.aggregate().await?;
-                                let token_symbol = token_symbol._0;
-
-                                let token_decimals = token_decimals._0;
-                                let formatted_amount = format_units(job.amount, token_decimals)?;
-                                let decimal_amount: f64 = formatted_amount.parse().unwrap();
-                                tracing::debug!("    - Job Title: {}", job.title);
-
-                                tracing::debug!(
-                                    "    - Job Amount: {} ${}",
-                                    decimal_amount,
-                                    token_symbol
-                                );
-                                tracing::debug!("    - Job deliveryMethod: {}", job.deliveryMethod);
-                                tracing::debug!("    - Job contentHash: {}", job.contentHash);
-
-                                // Get content from IPFS
-                                // Call the function
-                                let job_description =
-                                    match get_from_ipfs(&job.contentHash.to_string(), "").await {
-                                        Ok(data) => {
-                                            tracing::debug!("    - Job Description: {}", data);
-                                            data
-                                        }
-                                        Err(e) => {
-                                            return Err(eyre::eyre!(
-                                                "Failed to fetch job description from IPFS: {}",
-                                                e
-                                            ));
-                                        }
-                                    };
-
-                                let notification = JobNotification {
-                                    job_id: event.jobId.to_string(),
-                                    title: job.title,
-                                    description: job_description,
-                                    amount: decimal_amount,
-                                    symbol: token_symbol,
+                                let notification = match build_job_notification(
+                                    provider.clone(),
+                                    event.jobId,
+                                    &tokens,
+                                    &prices,
+                                    &directory,
+                                    reviews.as_deref(),
+                                    &catalogue,
+                                )
+                                .await
+                                {
+                                    Ok(notification) => notification,
+                                    Err(e) => {
+                                        tracing::error!("    - Failed to load the new job: {}", e);
+                                        continue;
+                                    }
                                 };
-                                match queue_sender.send(notification).await {
+                                match queue_sender
+                                    .send(Notification::JobPublished(notification))
+                                    .await
+                                {
                                     Ok(_) => {
                                         tracing::debug!("    - Notification sent to the queue");
                                     }
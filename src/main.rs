@@ -1,11 +1,25 @@
 use alloy::providers::{ProviderBuilder, WsConnect};
 use dotenvy::dotenv;
-use eacc_rs::telegram_api::telegram_worker;
+use eacc_rs::admin::{admin_worker, gated_channel, PauseState};
+use eacc_rs::config::Settings;
+use eacc_rs::digest::{digest_worker, ChainActivity};
+use eacc_rs::directory::{directory_worker, Directory};
+use eacc_rs::posts::PostStore;
+use eacc_rs::pricing::PriceOracle;
+use eacc_rs::reputation::{backfill_reviews, ReviewIndex};
+use eacc_rs::tags::{tag_worker, TagCatalogue};
+use eacc_rs::telegram_api::{telegram_worker, TelegramClient};
+use eacc_rs::telegram_bot::{
+    run_bot, telegram_bot_worker, ChainJobLookup, SubscriptionStore, TelegramBot,
+};
 use eacc_rs::telemetry::{get_subscriber, init_subscriber};
+use eacc_rs::templates::Templates;
+use eacc_rs::tokens::TokenRegistry;
 use eacc_rs::x_api::x_worker;
-use eacc_rs::{filter_publish_job_events, JobNotification};
+use eacc_rs::{filter_publish_job_events, Notification};
 use eyre::Result;
 use std::env;
+use std::sync::Arc;
 use tokio::signal;
 use tokio::sync::mpsc;
 
@@ -21,6 +35,9 @@ async fn main() -> Result<()> {
     let subscriber = get_subscriber("eacc_rs".into(), log_level, std::io::stdout);
     init_subscriber(subscriber);
 
+    // Templates and other non secret settings
+    let settings = Settings::load()?;
+
     // Create ws provider
     let ws = WsConnect::new(format!(
         "wss://arbitrum-mainnet.infura.io/ws/v3/{}",
@@ -29,27 +46,175 @@ async fn main() -> Result<()> {
     let provider = ProviderBuilder::new().on_ws(ws).await?;
 
     // Create event queue
-    let (event_tx, mut event_rx) = mpsc::channel::<JobNotification>(100);
+    let (event_tx, mut event_rx) = mpsc::channel::<Notification>(100);
+
+    // Pause state of the marketplace, followed with the other admin events
+    let pause = Arc::new(PauseState::default());
+    if settings.admin.enabled {
+        let telegram_bot_token = env::var("TELEGRAM_BOT_API").expect("TELEGRAM_BOT_API not set");
+        // Checked here, the worker can't report it
+        let chat_id = settings
+            .admin
+            .chat_id()
+            .expect("TG_ADMIN_CHAT_ID not found in .env file");
+        tokio::spawn(admin_worker(
+            provider.clone(),
+            settings.admin.clone(),
+            chat_id,
+            pause.clone(),
+            TelegramClient::new(
+                reqwest::Client::new(),
+                settings.telegram.api_url(),
+                &telegram_bot_token,
+            ),
+        ));
+    }
+    // Announcements are held back while the marketplace is paused
+    let gate = (settings.admin.enabled && settings.admin.suspend_while_paused).then_some(pause);
 
     // Create platform-specific notification queues
-    let (telegram_tx, telegram_rx) = mpsc::channel::<JobNotification>(100);
-    let (twitter_tx, twitter_rx) = mpsc::channel::<JobNotification>(100);
+    let (telegram_tx, telegram_rx) = gated_channel(gate.clone(), 100);
+    let (twitter_tx, twitter_rx) = gated_channel(gate.clone(), 100);
+
+    // Message ids of the announcements, to edit them when jobs change state
+    let posts = Arc::new(PostStore::load(&settings.posts_path())?);
+
+    // Token metadata shared by everything formatting amounts
+    let tokens = Arc::new(TokenRegistry::bundled());
+    // USD quotes for job rewards
+    let prices = Arc::new(PriceOracle::from_settings(
+        &settings.pricing,
+        provider.clone(),
+        tokens.clone(),
+    ));
+
+    // Registered users and arbitrators, to show names instead of addresses
+    let directory = if settings.directory.enabled {
+        let gateway = env::var("IPFS_GATEWAY").expect("IPFS_GATEWAY not found in .env file");
+        Arc::new(Directory::load(&settings.directory.path(), &gateway)?)
+    } else {
+        Arc::new(Directory::default())
+    };
+    if settings.directory.enabled {
+        let mut sinks = Vec::new();
+        if settings.directory.announce_arbitrators && settings.directory.telegram {
+            sinks.push(telegram_tx.clone());
+        }
+        if settings.directory.announce_arbitrators && settings.directory.x {
+            sinks.push(twitter_tx.clone());
+        }
+        tokio::spawn(directory_worker(provider.clone(), directory.clone(), sinks));
+    }
+
+    // MECE tag long forms, read from the contract now and then
+    let catalogue = Arc::new(TagCatalogue::bundled());
+    tokio::spawn(tag_worker(
+        provider.clone(),
+        catalogue.clone(),
+        settings.tags.clone(),
+    ));
+
+    // Reviews received by the users, read again when a job is rated
+    let reviews = if settings.reputation.enabled {
+        let reviews = Arc::new(ReviewIndex::load(&settings.reputation.path())?);
+        // Jobs announced meanwhile read the reviews of their creator on miss
+        let (provider, index) = (provider.clone(), reviews.clone());
+        tokio::spawn(async move {
+            if let Err(e) = backfill_reviews(provider, &index).await {
+                tracing::error!("Failed to backfill the reviews: {}", e);
+            }
+        });
+        Some(reviews)
+    } else {
+        None
+    };
+
+    // Interactive bot sending personalised alerts by DM
+    let bot_tx = if settings.telegram.bot.enabled {
+        let bot_settings = &settings.telegram.bot;
+        let telegram_bot_token = env::var("TELEGRAM_BOT_API").expect("TELEGRAM_BOT_API not set");
+        let bot = Arc::new(TelegramBot::new(
+            TelegramClient::new(
+                reqwest::Client::new(),
+                settings.telegram.api_url(),
+                &telegram_bot_token,
+            ),
+            Templates::from_settings(&settings)?,
+            SubscriptionStore::load(&bot_settings.subscriptions_path)?,
+            Arc::new(ChainJobLookup::new(
+                provider.clone(),
+                tokens.clone(),
+                prices.clone(),
+                directory.clone(),
+                reviews.clone(),
+                catalogue.clone(),
+            )),
+        ));
+        let (bot_tx, bot_rx) = gated_channel(gate, 100);
+        tokio::spawn(run_bot(bot.clone(), bot_settings.poll_timeout_secs));
+        tokio::spawn(telegram_bot_worker(bot_rx, bot));
+        Some(bot_tx)
+    } else {
+        None
+    };
+
+    // Scheduled activity summaries, posted through the platform workers
+    if settings.digest.enabled {
+        let mut sinks = Vec::new();
+        if settings.digest.telegram {
+            sinks.push(telegram_tx.clone());
+        }
+        if settings.digest.x {
+            sinks.push(twitter_tx.clone());
+        }
+        tokio::spawn(digest_worker(
+            Arc::new(ChainActivity::new(
+                provider.clone(),
+                tokens.clone(),
+                prices.clone(),
+            )),
+            settings.digest.clone(),
+            sinks,
+        ));
+    }
 
     // Spawn event fetching task
-    tokio::spawn(filter_publish_job_events(provider, event_tx));
+    tokio::spawn(filter_publish_job_events(
+        provider,
+        event_tx,
+        tokens.clone(),
+        prices,
+        directory,
+        reviews,
+        catalogue,
+    ));
 
-    // Event dispatcher
+    // Event dispatcher, each platform only gets the notifications matching its filter
+    let telegram_filter = settings.telegram.filter.clone();
+    let x_filter = settings.x.filter.clone();
     tokio::spawn(async move {
         while let Some(job) = event_rx.recv().await {
-            let _ = telegram_tx.send(job.clone()).await;
-            let _ = twitter_tx.send(job.clone()).await;
+            if telegram_filter.matches(&job) {
+                let _ = telegram_tx.send(job.clone()).await;
+            }
+            if x_filter.matches(&job) {
+                let _ = twitter_tx.send(job.clone()).await;
+            }
+            // Subscriptions are matched by the bot itself
+            if let Some(bot_tx) = &bot_tx {
+                let _ = bot_tx.send(job.clone()).await;
+            }
             // Add more platforms as needed
         }
     });
 
     // Platform-specific workers
-    tokio::spawn(telegram_worker(telegram_rx));
-    tokio::spawn(x_worker(twitter_rx));
+    tokio::spawn(telegram_worker(
+        telegram_rx,
+        settings.clone(),
+        posts.clone(),
+    ));
+    tokio::spawn(x_worker(twitter_rx, settings.clone(), posts.clone()));
     // ...
     // add more platforms as needed
 
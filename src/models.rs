@@ -0,0 +1,233 @@
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::job_state::{JobState, JobStatus};
+use crate::reputation::reputation_badge;
+use crate::{MarketPlaceData, MARKETPLACE_DATA_ADDRESS};
+
+// Ratings are stored onchain multiplied by 10000
+const RATING_SCALE: f64 = 10000.0;
+// Arbitrator fees are expressed in basis points
+const FEE_SCALE: f64 = 100.0;
+
+// JobPost as returned by `getJob`, with serializable fields
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct JobDetails {
+    pub state: JobState,
+    // Derived from the snapshot, refined by the events when tracked
+    #[serde(default)]
+    pub status: JobStatus,
+    pub whitelist_workers: bool,
+    pub creator: String,
+    pub arbitrator: String,
+    pub worker: String,
+    pub tags: Vec<String>,
+    pub content_hash: String,
+    pub multiple_applicants: bool,
+    pub amount: String,
+    pub token: String,
+    pub timestamp: u32,
+    pub max_time: u32,
+    pub delivery_method: String,
+    pub collateral_owed: String,
+    pub escrow_id: String,
+    pub result_hash: String,
+    pub rating: u8,
+    pub disputed: bool,
+}
+
+impl From<&MarketPlaceData::JobPost> for JobDetails {
+    fn from(job: &MarketPlaceData::JobPost) -> Self {
+        let state = JobState::from(job.state);
+        JobDetails {
+            state,
+            status: JobStatus::from_snapshot(state, job.disputed, !job.resultHash.is_zero()),
+            whitelist_workers: job.whitelistWorkers,
+            creator: job.roles.creator.to_string(),
+            arbitrator: job.roles.arbitrator.to_string(),
+            worker: job.roles.worker.to_string(),
+            tags: job.tags.clone(),
+            content_hash: job.contentHash.to_string(),
+            multiple_applicants: job.multipleApplicants,
+            amount: job.amount.to_string(),
+            token: job.token.to_string(),
+            timestamp: job.timestamp,
+            max_time: job.maxTime,
+            delivery_method: job.deliveryMethod.clone(),
+            collateral_owed: job.collateralOwed.to_string(),
+            escrow_id: job.escrowId.to_string(),
+            result_hash: job.resultHash.to_string(),
+            rating: job.rating,
+            disputed: job.disputed,
+        }
+    }
+}
+
+// User profile as returned by `getUser`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct UserProfile {
+    pub address: String,
+    #[serde(default)]
+    pub public_key: String,
+    pub name: String,
+    pub bio: String,
+    pub avatar: String,
+    // Avatar link usable by the platforms, set by the directory
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    pub reputation_up: u16,
+    pub reputation_down: u16,
+}
+
+impl From<&MarketPlaceData::User> for UserProfile {
+    fn from(user: &MarketPlaceData::User) -> Self {
+        UserProfile {
+            address: user.address_.to_string(),
+            public_key: user.publicKey.to_string(),
+            name: user.name.clone(),
+            bio: user.bio.clone(),
+            avatar: user.avatar.clone(),
+            avatar_url: None,
+            reputation_up: user.reputationUp,
+            reputation_down: user.reputationDown,
+        }
+    }
+}
+
+impl From<&MarketPlaceData::UserRegistered> for UserProfile {
+    fn from(event: &MarketPlaceData::UserRegistered) -> Self {
+        UserProfile {
+            address: event.addr.to_string(),
+            public_key: event.pubkey.to_string(),
+            name: event.name.clone(),
+            bio: event.bio.clone(),
+            avatar: event.avatar.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+// User rating as returned by `getUserRating`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct UserRating {
+    // Average in stars, from 0 to 5
+    pub average_rating: f64,
+    pub number_of_reviews: u64,
+    // ★4.8 from 12 reviews, None without reviews
+    #[serde(default)]
+    pub badge: Option<String>,
+}
+
+impl UserRating {
+    pub fn new(average_rating: f64, number_of_reviews: u64) -> Self {
+        UserRating {
+            average_rating,
+            number_of_reviews,
+            badge: reputation_badge(average_rating, number_of_reviews),
+        }
+    }
+}
+
+impl From<&MarketPlaceData::UserRating> for UserRating {
+    fn from(rating: &MarketPlaceData::UserRating) -> Self {
+        UserRating::new(
+            f64::from(rating.averageRating) / RATING_SCALE,
+            rating.numberOfReviews.saturating_to(),
+        )
+    }
+}
+
+// Arbitrator as returned by `getArbitrator`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ArbitratorProfile {
+    pub address: String,
+    #[serde(default)]
+    pub public_key: String,
+    pub name: String,
+    pub bio: String,
+    pub avatar: String,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    // Fee in basis points and in percent
+    pub fee: u16,
+    pub fee_percent: f64,
+    pub settled_count: u16,
+    pub refused_count: u16,
+}
+
+impl From<&MarketPlaceData::JobArbitrator> for ArbitratorProfile {
+    fn from(arbitrator: &MarketPlaceData::JobArbitrator) -> Self {
+        ArbitratorProfile {
+            address: arbitrator.address_.to_string(),
+            public_key: arbitrator.publicKey.to_string(),
+            name: arbitrator.name.clone(),
+            bio: arbitrator.bio.clone(),
+            avatar: arbitrator.avatar.clone(),
+            avatar_url: None,
+            fee: arbitrator.fee,
+            fee_percent: f64::from(arbitrator.fee) / FEE_SCALE,
+            settled_count: arbitrator.settledCount,
+            refused_count: arbitrator.refusedCount,
+        }
+    }
+}
+
+impl From<&MarketPlaceData::ArbitratorRegistered> for ArbitratorProfile {
+    fn from(event: &MarketPlaceData::ArbitratorRegistered) -> Self {
+        ArbitratorProfile {
+            address: event.addr.to_string(),
+            public_key: event.pubkey.to_string(),
+            name: event.name.clone(),
+            bio: event.bio.clone(),
+            avatar: event.avatar.clone(),
+            fee: event.fee,
+            fee_percent: f64::from(event.fee) / FEE_SCALE,
+            ..Default::default()
+        }
+    }
+}
+
+// Profiles of the people involved in a job, None when not registered
+#[derive(Debug, Clone, Default)]
+pub struct Participants {
+    pub creator_profile: Option<UserProfile>,
+    pub creator_rating: Option<UserRating>,
+    pub arbitrator_profile: Option<ArbitratorProfile>,
+}
+
+// Fetch the creator and arbitrator records of a job in a single multicall
+#[tracing::instrument(name = "fetch_participants", skip(provider))]
+pub async fn fetch_participants<P: Provider + Clone>(
+    provider: P,
+    creator: Address,
+    arbitrator: Address,
+) -> Result<Participants> {
+    let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider.clone());
+
+    // Calls are allowed to fail, e.g. when the job has no arbitrator
+    let multicall = provider
+        .multicall()
+        .add(marketplace_data.getUser(creator))
+        .add(marketplace_data.getUserRating(creator))
+        .add(marketplace_data.getArbitrator(arbitrator));
+    let (user, rating, arbitrator_record) = multicall.try_aggregate(false).await?;
+
+    let creator_profile = user
+        .ok()
+        .map(|user| UserProfile::from(&user._0))
+        .filter(|profile| !profile.name.is_empty());
+    let creator_rating = rating.ok().map(|rating| UserRating::from(&rating._0));
+    let arbitrator_profile = arbitrator_record
+        .ok()
+        .filter(|_| !arbitrator.is_zero())
+        .map(|arbitrator| ArbitratorProfile::from(&arbitrator._0))
+        .filter(|profile| !profile.name.is_empty());
+
+    Ok(Participants {
+        creator_profile,
+        creator_rating,
+        arbitrator_profile,
+    })
+}
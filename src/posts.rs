@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::{error::AppError, utils::JsonStore};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TelegramPost {
+    pub chat_id: String,
+    pub message_id: i64,
+    // Card with a caption, edited with editMessageCaption
+    #[serde(default)]
+    pub photo: bool,
+}
+
+// Announcements of a job on every platform
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct JobPosts {
+    pub telegram: Vec<TelegramPost>,
+    pub x: Option<u64>,
+    // Replies holding the description, in thread order
+    pub x_thread: Vec<u64>,
+}
+
+// Message ids of the job announcements, saved on each change
+// so posts can still be edited after a restart
+#[derive(Debug, Default)]
+pub struct PostStore {
+    jobs: JsonStore<BTreeMap<String, JobPosts>>,
+}
+
+impl PostStore {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        Ok(PostStore {
+            jobs: JsonStore::load(path, "posts")?,
+        })
+    }
+
+    fn update<F: FnOnce(&mut BTreeMap<String, JobPosts>)>(&self, change: F) {
+        let saved = self.jobs.update(|jobs| {
+            change(jobs);
+            jobs.retain(|_, posts| *posts != JobPosts::default());
+        });
+        // Losing the file only means old posts can't be edited anymore
+        if let Err(e) = saved {
+            tracing::error!("{}", e);
+        }
+    }
+
+    pub fn get(&self, job_id: &str) -> JobPosts {
+        self.jobs
+            .read(|jobs| jobs.get(job_id).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn add_telegram(&self, job_id: &str, post: TelegramPost) {
+        self.update(|jobs| {
+            jobs.entry(job_id.to_string())
+                .or_default()
+                .telegram
+                .push(post)
+        });
+    }
+
+    pub fn clear_telegram(&self, job_id: &str) {
+        self.update(|jobs| {
+            if let Some(posts) = jobs.get_mut(job_id) {
+                posts.telegram.clear();
+            }
+        });
+    }
+
+    pub fn set_x(&self, job_id: &str, tweet_id: Option<u64>) {
+        self.update(|jobs| jobs.entry(job_id.to_string()).or_default().x = tweet_id);
+    }
+
+    pub fn set_x_thread(&self, job_id: &str, tweet_ids: Vec<u64>) {
+        self.update(|jobs| jobs.entry(job_id.to_string()).or_default().x_thread = tweet_ids);
+    }
+}
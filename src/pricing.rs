@@ -0,0 +1,361 @@
+use alloy::primitives::{address, Address, I256, U256};
+use alloy::providers::Provider;
+use alloy::sol;
+use eyre::Result;
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    amount::TokenAmount,
+    tokens::{is_native, TokenInfo, TokenRegistry},
+};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface AggregatorV3Interface {
+        function decimals() external view returns (uint8);
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    }
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IUniswapV3Pool {
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+        function observe(uint32[] calldata secondsAgos) external view returns (int56[] memory tickCumulatives, uint160[] memory secondsPerLiquidityCumulativeX128s);
+    }
+);
+
+const WETH: Address = address!("82aF49447D8a07e3bd95BD0d56f35241523fBab1");
+
+// Chainlink USD feeds on Arbitrum, keyed by token
+const BUNDLED_CHAINLINK_FEEDS: &str = include_str!("data/chainlink_feeds.json");
+
+// Price sources, set under `pricing` in the config file
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PricingSettings {
+    // Sources tried in order: "chainlink", "uniswap_twap", "http"
+    pub sources: Vec<String>,
+    pub cache_ttl_secs: u64,
+    // Chainlink answers older than this are ignored
+    pub max_price_age_secs: u64,
+    // Extra Chainlink feeds, token address -> USD feed address
+    pub chainlink_feeds: HashMap<Address, Address>,
+    // Uniswap V3 pools pairing a token with a USD stablecoin
+    pub uniswap_pools: HashMap<Address, Address>,
+    pub twap_window_secs: u32,
+    // CoinGecko compatible API, can point to a local stub
+    pub http_api_url: String,
+}
+
+impl Default for PricingSettings {
+    fn default() -> Self {
+        PricingSettings {
+            sources: vec!["chainlink".to_string(), "http".to_string()],
+            cache_ttl_secs: 300,
+            max_price_age_secs: 86400,
+            chainlink_feeds: HashMap::new(),
+            uniswap_pools: HashMap::new(),
+            twap_window_secs: 1800,
+            http_api_url: "https://api.coingecko.com/api/v3".to_string(),
+        }
+    }
+}
+
+// Something able to quote a token in USD
+pub trait PriceSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // USD price of one whole token, None when the source doesn't know the token
+    fn usd_price<'a>(&'a self, token: &'a TokenInfo) -> BoxFuture<'a, Result<Option<f64>>>;
+}
+
+// Address the sources know the token by, the native asset is quoted as WETH
+fn quoted_address(token: &TokenInfo) -> Address {
+    if is_native(token.address) {
+        WETH
+    } else {
+        token.address
+    }
+}
+
+// Divide a fixed point integer by 10^decimals
+fn scaled(value: U256, decimals: u8) -> f64 {
+    let value: f64 = value.to_string().parse().unwrap_or_default();
+    value / 10f64.powi(i32::from(decimals))
+}
+
+// Chainlink aggregator reads over the existing provider
+pub struct ChainlinkSource<P> {
+    provider: P,
+    feeds: HashMap<Address, Address>,
+    max_age: Duration,
+}
+
+impl<P: Provider + Clone> ChainlinkSource<P> {
+    pub fn new(provider: P, extra_feeds: &HashMap<Address, Address>, max_age: Duration) -> Self {
+        let mut feeds: HashMap<Address, Address> = serde_json::from_str(BUNDLED_CHAINLINK_FEEDS)
+            .expect("Bundled Chainlink feeds must be valid");
+        feeds.extend(extra_feeds.iter().map(|(token, feed)| (*token, *feed)));
+        ChainlinkSource {
+            provider,
+            feeds,
+            max_age,
+        }
+    }
+
+    fn feed_for(&self, token: &TokenInfo) -> Option<Address> {
+        self.feeds.get(&quoted_address(token)).copied()
+    }
+}
+
+impl<P: Provider + Clone + Send + Sync> PriceSource for ChainlinkSource<P> {
+    fn name(&self) -> &'static str {
+        "chainlink"
+    }
+
+    fn usd_price<'a>(&'a self, token: &'a TokenInfo) -> BoxFuture<'a, Result<Option<f64>>> {
+        Box::pin(async move {
+            let Some(feed) = self.feed_for(token) else {
+                return Ok(None);
+            };
+            let aggregator = AggregatorV3Interface::new(feed, self.provider.clone());
+            let multicall = self
+                .provider
+                .multicall()
+                .add(aggregator.decimals())
+                .add(aggregator.latestRoundData());
+            let (decimals, round) = multicall.aggregate().await?;
+
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            let updated_at: u64 = round.updatedAt.saturating_to();
+            if now.saturating_sub(updated_at) > self.max_age.as_secs() {
+                tracing::warn!("Chainlink feed {} is stale, ignoring it", feed);
+                return Ok(None);
+            }
+            if round.answer <= I256::ZERO {
+                return Ok(None);
+            }
+            Ok(Some(scaled(round.answer.into_raw(), decimals._0)))
+        })
+    }
+}
+
+// Time weighted average price from a Uniswap V3 pool against a USD stablecoin
+pub struct UniswapTwapSource<P> {
+    provider: P,
+    tokens: Arc<TokenRegistry>,
+    pools: HashMap<Address, Address>,
+    window: u32,
+}
+
+impl<P: Provider + Clone> UniswapTwapSource<P> {
+    pub fn new(
+        provider: P,
+        tokens: Arc<TokenRegistry>,
+        pools: HashMap<Address, Address>,
+        window: u32,
+    ) -> Self {
+        UniswapTwapSource {
+            provider,
+            tokens,
+            pools,
+            window,
+        }
+    }
+}
+
+// Price of token0 in token1 units for an average tick
+pub fn tick_to_price(tick: f64, decimals0: u8, decimals1: u8) -> f64 {
+    1.0001f64.powf(tick) * 10f64.powi(i32::from(decimals0) - i32::from(decimals1))
+}
+
+impl<P: Provider + Clone + Send + Sync> PriceSource for UniswapTwapSource<P> {
+    fn name(&self) -> &'static str {
+        "uniswap_twap"
+    }
+
+    fn usd_price<'a>(&'a self, token: &'a TokenInfo) -> BoxFuture<'a, Result<Option<f64>>> {
+        Box::pin(async move {
+            let address = quoted_address(token);
+            let Some(pool_address) = self.pools.get(&address).copied() else {
+                return Ok(None);
+            };
+            let pool = IUniswapV3Pool::new(pool_address, self.provider.clone());
+            let multicall = self
+                .provider
+                .multicall()
+                .add(pool.token0())
+                .add(pool.token1())
+                .add(pool.observe(vec![self.window, 0]));
+            let (token0, token1, observation) = multicall.aggregate().await?;
+            let cumulatives = observation.tickCumulatives;
+            if cumulatives.len() != 2 || self.window == 0 {
+                return Ok(None);
+            }
+            let tick_delta = cumulatives[1].as_i64() - cumulatives[0].as_i64();
+            let average_tick = tick_delta as f64 / f64::from(self.window);
+
+            // The other side of the pool is assumed to be worth one dollar
+            let (token0, token1) = (token0._0, token1._0);
+            let decimals0 = self
+                .tokens
+                .resolve(self.provider.clone(), token0)
+                .await
+                .decimals;
+            let decimals1 = self
+                .tokens
+                .resolve(self.provider.clone(), token1)
+                .await
+                .decimals;
+            let price0_in_1 = tick_to_price(average_tick, decimals0, decimals1);
+            if token0 == address {
+                Ok(Some(price0_in_1))
+            } else if token1 == address && price0_in_1 > 0.0 {
+                Ok(Some(1.0 / price0_in_1))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+}
+
+// CoinGecko style HTTP price API
+pub struct HttpPriceSource {
+    client: Client,
+    base_url: String,
+}
+
+impl HttpPriceSource {
+    pub fn new(client: Client, base_url: &str) -> Self {
+        HttpPriceSource {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl PriceSource for HttpPriceSource {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn usd_price<'a>(&'a self, token: &'a TokenInfo) -> BoxFuture<'a, Result<Option<f64>>> {
+        Box::pin(async move {
+            let key = format!("{:#x}", quoted_address(token));
+            let url = format!(
+                "{}/simple/token_price/arbitrum-one?contract_addresses={}&vs_currencies=usd",
+                self.base_url, key
+            );
+            let response = self.client.get(&url).send().await?;
+            if !response.status().is_success() {
+                return Err(eyre::eyre!(
+                    "Price API error {}: {}",
+                    url,
+                    response.status()
+                ));
+            }
+            // {"0xaddress": {"usd": 1.23}}, keys are lowercase
+            let prices: HashMap<String, HashMap<String, f64>> = response.json().await?;
+            Ok(prices.get(&key).and_then(|quote| quote.get("usd").copied()))
+        })
+    }
+}
+
+// Converts token amounts to USD, trying every source in order and caching the quotes
+pub struct PriceOracle {
+    sources: Vec<Box<dyn PriceSource>>,
+    cache: RwLock<HashMap<Address, (f64, Instant)>>,
+    ttl: Duration,
+}
+
+impl PriceOracle {
+    pub fn new(sources: Vec<Box<dyn PriceSource>>, ttl: Duration) -> Self {
+        PriceOracle {
+            sources,
+            cache: RwLock::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    // Oracle with the sources enabled in the config
+    pub fn from_settings<P>(
+        settings: &PricingSettings,
+        provider: P,
+        tokens: Arc<TokenRegistry>,
+    ) -> Self
+    where
+        P: Provider + Clone + Send + Sync + 'static,
+    {
+        let mut sources: Vec<Box<dyn PriceSource>> = Vec::new();
+        for name in &settings.sources {
+            match name.as_str() {
+                "chainlink" => sources.push(Box::new(ChainlinkSource::new(
+                    provider.clone(),
+                    &settings.chainlink_feeds,
+                    Duration::from_secs(settings.max_price_age_secs),
+                ))),
+                "uniswap_twap" => sources.push(Box::new(UniswapTwapSource::new(
+                    provider.clone(),
+                    tokens.clone(),
+                    settings.uniswap_pools.clone(),
+                    settings.twap_window_secs,
+                ))),
+                "http" => sources.push(Box::new(HttpPriceSource::new(
+                    Client::new(),
+                    &settings.http_api_url,
+                ))),
+                other => tracing::warn!("Unknown price source {}, skipping it", other),
+            }
+        }
+        PriceOracle::new(sources, Duration::from_secs(settings.cache_ttl_secs))
+    }
+
+    // USD price of one whole token
+    #[tracing::instrument(name = "usd_price", skip(self))]
+    pub async fn usd_price(&self, token: &TokenInfo) -> Option<f64> {
+        if let Some((price, quoted_at)) = self
+            .cache
+            .read()
+            .expect("Price cache lock poisoned")
+            .get(&token.address)
+        {
+            if quoted_at.elapsed() < self.ttl {
+                return Some(*price);
+            }
+        }
+
+        for source in &self.sources {
+            match source.usd_price(token).await {
+                Ok(Some(price)) => {
+                    tracing::debug!("{} quoted {} at ${}", source.name(), token.symbol, price);
+                    self.cache
+                        .write()
+                        .expect("Price cache lock poisoned")
+                        .insert(token.address, (price, Instant::now()));
+                    return Some(price);
+                }
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!("{} failed to quote {}: {}", source.name(), token.symbol, e)
+                }
+            }
+        }
+        None
+    }
+
+    // USD value of a token amount, None when no source knows the token
+    pub async fn usd_value(&self, token: &TokenInfo, amount: &TokenAmount) -> Option<f64> {
+        self.usd_price(token)
+            .await
+            .map(|price| price * amount.to_f64())
+    }
+}
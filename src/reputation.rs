@@ -0,0 +1,257 @@
+use alloy::primitives::{Address, U256};
+use alloy::providers::{MulticallBuilder, Provider};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::AppError, models::UserRating, utils::JsonStore, MarketPlaceData,
+    MARKETPLACE_DATA_ADDRESS,
+};
+
+const DEFAULT_REVIEWS_PATH: &str = "./reviews.json";
+// Reviews read per getReviews call
+const PAGE_SIZE: u64 = 50;
+
+// Review index, set under `reputation` in the config file
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ReputationSettings {
+    pub enabled: bool,
+    // Saved reviews, defaults to ./reviews.json
+    pub path: Option<PathBuf>,
+}
+
+impl ReputationSettings {
+    pub fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_REVIEWS_PATH))
+    }
+}
+
+// Review as returned by `getReviews`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Review {
+    pub reviewer: String,
+    pub job_id: String,
+    // Stars, from 1 to 5
+    pub rating: u8,
+    pub text: String,
+    pub timestamp: u32,
+}
+
+impl From<&MarketPlaceData::Review> for Review {
+    fn from(review: &MarketPlaceData::Review) -> Self {
+        Review {
+            reviewer: review.reviewer.to_string(),
+            job_id: review.jobId.to_string(),
+            rating: review.rating,
+            text: review.text.clone(),
+            timestamp: review.timestamp,
+        }
+    }
+}
+
+// ★4.8 from 12 reviews, None before the first review
+pub fn reputation_badge(average_rating: f64, number_of_reviews: u64) -> Option<String> {
+    if number_of_reviews == 0 {
+        return None;
+    }
+    Some(format!(
+        "★{:.1} from {} review{}",
+        average_rating,
+        number_of_reviews,
+        if number_of_reviews == 1 { "" } else { "s" }
+    ))
+}
+
+// Rating summary of a list of reviews
+pub fn summarize(reviews: &[Review]) -> UserRating {
+    let total: u64 = reviews.iter().map(|review| u64::from(review.rating)).sum();
+    let average_rating = match reviews.len() {
+        0 => 0.0,
+        count => total as f64 / count as f64,
+    };
+    UserRating::new(average_rating, reviews.len() as u64)
+}
+
+// Reviews received by each user, by lowercase address
+// Saved once all the new reviews of a user were read
+#[derive(Debug, Default)]
+pub struct ReviewIndex {
+    reviews: JsonStore<BTreeMap<String, Vec<Review>>>,
+}
+
+impl ReviewIndex {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        Ok(ReviewIndex {
+            reviews: JsonStore::load(path, "reviews")?,
+        })
+    }
+
+    pub fn reviews(&self, address: &str) -> Vec<Review> {
+        self.reviews
+            .read(|reviews| reviews.get(&address.to_lowercase()).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn summary(&self, address: &str) -> UserRating {
+        summarize(&self.reviews(address))
+    }
+
+    // Store the reviews of `address` starting at index `start`, already stored ones are kept
+    // Only kept in memory until `save`
+    pub fn add_reviews(&self, address: &str, start: usize, new_reviews: Vec<Review>) {
+        self.reviews.change(|reviews| {
+            let stored = reviews.entry(address.to_lowercase()).or_default();
+            if start > stored.len() {
+                tracing::error!("Gap in the reviews of {} at {}", address, stored.len());
+                return;
+            }
+            let skip = stored.len() - start;
+            stored.extend(new_reviews.into_iter().skip(skip));
+        });
+    }
+
+    pub fn save(&self) {
+        // Missing reviews are read again on the next start
+        if let Err(e) = self.reviews.save() {
+            tracing::error!("{}", e);
+        }
+    }
+
+    // Read the reviews of `target` not indexed yet, returns how many were added
+    #[tracing::instrument(name = "refresh_reviews", skip(self, provider))]
+    pub async fn refresh<P: Provider + Clone>(
+        &self,
+        provider: P,
+        target: Address,
+    ) -> Result<usize> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider.clone());
+        // The rating counts every review, getReviews can't read past it
+        let count: u64 = marketplace_data
+            .getUserRating(target)
+            .call()
+            .await?
+            ._0
+            .numberOfReviews
+            .saturating_to();
+        self.read_reviews(provider, target, count).await
+    }
+
+    // Rating of `target` from the indexed reviews
+    // The index is refreshed when it holds fewer reviews than `onchain`, or when `onchain` is unknown
+    #[tracing::instrument(name = "indexed_rating", skip(self, provider, onchain))]
+    pub async fn rating<P: Provider + Clone>(
+        &self,
+        provider: P,
+        target: Address,
+        onchain: Option<&UserRating>,
+    ) -> Option<UserRating> {
+        let address = target.to_string();
+        let stored = self.reviews(&address).len() as u64;
+        let refreshed = match onchain {
+            Some(rating) if rating.number_of_reviews <= stored => Ok(0),
+            Some(rating) => {
+                self.read_reviews(provider, target, rating.number_of_reviews)
+                    .await
+            }
+            None => self.refresh(provider, target).await,
+        };
+        match refreshed {
+            Ok(_) => Some(self.summary(&address)),
+            Err(e) => {
+                // The contract average is still right, only the index is behind
+                tracing::error!("Failed to read the reviews of {}: {}", target, e);
+                onchain.cloned()
+            }
+        }
+    }
+
+    // Read the reviews of `target` from the stored ones up to `count`, then save the index
+    async fn read_reviews<P: Provider + Clone>(
+        &self,
+        provider: P,
+        target: Address,
+        count: u64,
+    ) -> Result<usize> {
+        let read = self.read_pages(provider, target, count).await;
+        if !matches!(read, Ok(0)) {
+            // Pages read before a failure are kept
+            self.save();
+        }
+        read
+    }
+
+    async fn read_pages<P: Provider + Clone>(
+        &self,
+        provider: P,
+        target: Address,
+        count: u64,
+    ) -> Result<usize> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider);
+        let address = target.to_string();
+        let mut added = 0;
+        let mut index = self.reviews(&address).len() as u64;
+        while index < count {
+            let limit = PAGE_SIZE.min(count - index);
+            let page = marketplace_data
+                .getReviews(target, U256::from(index), U256::from(limit))
+                .call()
+                .await?
+                ._0;
+            if page.is_empty() {
+                break;
+            }
+            let len = page.len();
+            self.add_reviews(
+                &address,
+                index as usize,
+                page.iter().map(Review::from).collect(),
+            );
+            added += len;
+            index += len as u64;
+        }
+        Ok(added)
+    }
+}
+
+// Index the reviews of every registered user not read yet
+// The review counts of each page of users are read in one multicall
+#[tracing::instrument(name = "backfill_reviews", skip(provider, reviews))]
+pub async fn backfill_reviews<P: Provider + Clone>(
+    provider: P,
+    reviews: &ReviewIndex,
+) -> Result<()> {
+    let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, provider.clone());
+
+    let users_length: u64 = marketplace_data.usersLength().call().await?._0.to();
+    let mut added = 0;
+    for index in (0..users_length).step_by(PAGE_SIZE as usize) {
+        let users = marketplace_data
+            .getUsers(U256::from(index), U256::from(PAGE_SIZE))
+            .call()
+            .await?
+            ._0;
+        let ratings = MulticallBuilder::new_dynamic(&provider)
+            .extend(
+                users
+                    .iter()
+                    .map(|user| marketplace_data.getUserRating(user.address_)),
+            )
+            .aggregate()
+            .await?;
+        for (user, rating) in users.iter().zip(ratings) {
+            let count: u64 = rating._0.numberOfReviews.saturating_to();
+            if count > reviews.reviews(&user.address_.to_string()).len() as u64 {
+                added += reviews
+                    .read_reviews(provider.clone(), user.address_, count)
+                    .await?;
+            }
+        }
+    }
+    tracing::info!("Reviews loaded, {} new for {} users", added, users_length);
+    Ok(())
+}
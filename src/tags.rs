@@ -0,0 +1,205 @@
+use alloy::providers::{MulticallBuilder, Provider};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::{MarketPlaceData, MARKETPLACE_DATA_ADDRESS};
+
+// MECE categories of the EACC frontend, the contract has no way to list them
+const BUNDLED_MECE_TAGS: [(&str, &str); 8] = [
+    ("DA", "Digital Audio"),
+    ("DV", "Digital Video"),
+    ("DT", "Digital Text"),
+    ("DS", "Digital Software"),
+    ("DO", "Digital Others"),
+    ("NDG", "Non-Digital Goods"),
+    ("NDS", "Non-Digital Services"),
+    ("NDO", "Non-Digital Others"),
+];
+
+// Tag catalogue refresh, set under `tags` in the config file
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct TagSettings {
+    // Seconds between two reads of the catalogue from the contract
+    pub refresh_secs: u64,
+}
+
+impl Default for TagSettings {
+    fn default() -> Self {
+        TagSettings { refresh_secs: 3600 }
+    }
+}
+
+// A job tag as shown on the platforms
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct JobTag {
+    // As stored on the job
+    pub tag: String,
+    // Long form of MECE tags, the tag itself otherwise
+    pub name: String,
+    // #DigitalAudio, None when the name has no letters or digits
+    pub hashtag: Option<String>,
+    // Part of the MECE catalogue
+    pub mece: bool,
+}
+
+// "Non-Digital Goods" -> #NonDigitalGoods, single words are kept as they are
+pub fn hashtag(name: &str) -> Option<String> {
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let hashtag: String = match words.as_slice() {
+        [] => return None,
+        [word] => word.to_string(),
+        words => words
+            .iter()
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            })
+            .collect(),
+    };
+    Some(format!("#{}", hashtag))
+}
+
+// MECE short forms and their long forms, as set with `updateMeceTag`
+#[derive(Debug)]
+pub struct TagCatalogue {
+    tags: RwLock<BTreeMap<String, String>>,
+    // Short forms seen on jobs but not in the catalogue, looked up on the next refresh
+    unknown: RwLock<BTreeSet<String>>,
+}
+
+impl Default for TagCatalogue {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+impl TagCatalogue {
+    pub fn bundled() -> Self {
+        TagCatalogue {
+            tags: RwLock::new(
+                BUNDLED_MECE_TAGS
+                    .iter()
+                    .map(|(short, long)| (short.to_string(), long.to_string()))
+                    .collect(),
+            ),
+            unknown: RwLock::new(BTreeSet::new()),
+        }
+    }
+
+    pub fn get(&self, short_form: &str) -> Option<String> {
+        self.tags
+            .read()
+            .expect("Tag catalogue lock poisoned")
+            .get(short_form)
+            .cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.read().expect("Tag catalogue lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Set a long form, an empty one removes the tag like `removeMeceTag`
+    pub fn set(&self, short_form: &str, long_form: &str) {
+        let mut tags = self.tags.write().expect("Tag catalogue lock poisoned");
+        if long_form.is_empty() {
+            tags.remove(short_form);
+        } else {
+            tags.insert(short_form.to_string(), long_form.to_string());
+        }
+    }
+
+    // Labels of the tags of a job
+    // The frontend puts the MECE category first, another first tag is reported and looked up later
+    pub fn labels(&self, job_id: &str, tags: &[String]) -> Vec<JobTag> {
+        let labels: Vec<JobTag> = tags
+            .iter()
+            .map(|tag| {
+                let long_form = self.get(tag);
+                let name = long_form.clone().unwrap_or_else(|| tag.clone());
+                JobTag {
+                    tag: tag.clone(),
+                    hashtag: hashtag(&name),
+                    name,
+                    mece: long_form.is_some(),
+                }
+            })
+            .collect();
+        if let Some(first) = labels.first().filter(|label| !label.mece) {
+            tracing::warn!("Job {} has the unknown MECE tag {}", job_id, first.tag);
+            self.unknown
+                .write()
+                .expect("Tag catalogue lock poisoned")
+                .insert(first.tag.clone());
+        }
+        labels
+    }
+
+    // Read the long form of every known and reported tag from the contract
+    #[tracing::instrument(name = "refresh_tags", skip(self, provider))]
+    pub async fn refresh<P: Provider + Clone>(&self, provider: P) -> Result<()> {
+        let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &provider);
+        let mut short_forms: BTreeSet<String> = self
+            .tags
+            .read()
+            .expect("Tag catalogue lock poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        // Unknown tags are only cleared once read, a failed read tries them again
+        let unknown = self
+            .unknown
+            .read()
+            .expect("Tag catalogue lock poisoned")
+            .clone();
+        short_forms.extend(unknown.iter().cloned());
+        let short_forms: Vec<String> = short_forms.into_iter().collect();
+
+        // Unset tags read as empty strings
+        let long_forms = MulticallBuilder::new_dynamic(&provider)
+            .extend(
+                short_forms
+                    .iter()
+                    .map(|short_form| marketplace_data.meceTags(short_form.clone())),
+            )
+            .aggregate()
+            .await?;
+        for (short_form, long_form) in short_forms.iter().zip(long_forms) {
+            self.set(short_form, &long_form._0);
+        }
+        self.unknown
+            .write()
+            .expect("Tag catalogue lock poisoned")
+            .retain(|tag| !unknown.contains(tag));
+        tracing::info!("Tag catalogue refreshed, {} MECE tags", self.len());
+        Ok(())
+    }
+}
+
+// Refresh the catalogue every `settings.refresh_secs`
+#[tracing::instrument(name = "tag_worker", skip(provider, catalogue))]
+pub async fn tag_worker<P: Provider + Clone>(
+    provider: P,
+    catalogue: Arc<TagCatalogue>,
+    settings: TagSettings,
+) {
+    loop {
+        if let Err(e) = catalogue.refresh(provider.clone()).await {
+            tracing::error!("Failed to refresh the tag catalogue: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(settings.refresh_secs.max(60))).await;
+    }
+}
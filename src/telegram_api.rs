@@ -1,67 +1,742 @@
-use reqwest::Client;
+use reqwest::{multipart, Client};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
 
-use crate::{error::AppError, JobNotification};
+use crate::{
+    cards::CardRenderer,
+    config::{Settings, TelegramDestination},
+    error::AppError,
+    events::JobStatusNotification,
+    job_state::JobState,
+    posts::{PostStore, TelegramPost},
+    telegram_format::{
+        job_url, render_delivery_message, render_job_message, render_status_message,
+        CAPTION_MAX_LEN, MESSAGE_MAX_LEN,
+    },
+    templates::{
+        arbitrator_context, digest_context, Templates, TELEGRAM_ARBITRATOR, TELEGRAM_DIGEST,
+    },
+    JobNotification, Notification,
+};
 
-// Send notification to Telegram
-#[tracing::instrument(name = "send_telegram_notification", skip(client, bot_token))]
-async fn send_telegram_notification(
-    client: &Client,
-    notification: &JobNotification,
-    bot_token: &str,
-    chat_id: &str,
-) -> Result<(), AppError> {
-    let message = format!(
-        "<b>A new job has been published in EACC</b>\n\n\n<b>Title</b>:<a href='https://effectiveacceleration.ai/dashboard/jobs/{}'>{}</a>\n<b>Job Description:</b>\n{}\n\n<b>Job Reward</b>: {} ${}\n\n",
-        notification.job_id,
-        notification.title,
-        notification.description.trim(),
-        notification.amount,
-        notification.symbol
-    );
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
-    let response = client
-        .post(&url)
-        .json(&serde_json::json!({
+// Bot API endpoint, overridden with `telegram.api_url` (e.g. a local mock server)
+pub const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Chat {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Message {
+    pub message_id: i64,
+    pub chat: Chat,
+    // Forum topic of the message, replies go to the same one
+    pub message_thread_id: Option<i64>,
+    #[serde(default)]
+    pub is_topic_message: bool,
+    pub text: Option<String>,
+    // Sizes of a sent photo, smallest first
+    pub photo: Option<Vec<PhotoSize>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PhotoSize {
+    pub file_id: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Photo to send, uploaded the first time then reused by file id
+#[derive(Debug, Clone)]
+pub enum InputPhoto {
+    Upload(Vec<u8>),
+    FileId(String),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct User {
+    pub id: i64,
+    pub username: Option<String>,
+}
+
+// Press on an inline keyboard button carrying callback data
+#[derive(Debug, Deserialize, Clone)]
+pub struct CallbackQuery {
+    pub id: String,
+    pub from: User,
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+    pub callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct InlineKeyboardButton {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_data: Option<String>,
+}
+
+impl InlineKeyboardButton {
+    pub fn url(text: &str, url: String) -> Self {
+        InlineKeyboardButton {
+            text: text.to_string(),
+            url: Some(url),
+            callback_data: None,
+        }
+    }
+
+    pub fn callback(text: &str, action: &CallbackAction) -> Self {
+        InlineKeyboardButton {
+            text: text.to_string(),
+            url: None,
+            callback_data: Some(action.to_data()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Default)]
+pub struct InlineKeyboardMarkup {
+    pub inline_keyboard: Vec<Vec<InlineKeyboardButton>>,
+}
+
+// Non-URL buttons of the job keyboard, handled by the bot
+// Encoded as "action:job_id", Telegram allows 64 bytes of callback data
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallbackAction {
+    CreatorProfile(String),
+    SubscribeSimilar(String),
+}
+
+impl CallbackAction {
+    pub fn to_data(&self) -> String {
+        match self {
+            CallbackAction::CreatorProfile(job_id) => format!("profile:{}", job_id),
+            CallbackAction::SubscribeSimilar(job_id) => format!("similar:{}", job_id),
+        }
+    }
+
+    pub fn parse(data: &str) -> Option<Self> {
+        match data.split_once(':')? {
+            ("profile", job_id) => Some(CallbackAction::CreatorProfile(job_id.to_string())),
+            ("similar", job_id) => Some(CallbackAction::SubscribeSimilar(job_id.to_string())),
+            _ => None,
+        }
+    }
+}
+
+// Buttons under a job announcement, the callback ones need the bot to be running
+pub fn job_keyboard(job: &JobNotification, callbacks: bool) -> InlineKeyboardMarkup {
+    let url = job_url(&job.job_id);
+    let share_url = url::Url::parse_with_params(
+        "https://t.me/share/url",
+        &[("url", url.as_str()), ("text", job.title.as_str())],
+    )
+    .map(|share_url| share_url.to_string())
+    .unwrap_or_else(|_| url.clone());
+
+    let mut keyboard = vec![vec![
+        InlineKeyboardButton::url("Open job", url),
+        InlineKeyboardButton::url("Share", share_url),
+    ]];
+    if callbacks {
+        let mut row = Vec::new();
+        if job.creator_profile.is_some() {
+            row.push(InlineKeyboardButton::callback(
+                "Creator profile",
+                &CallbackAction::CreatorProfile(job.job_id.clone()),
+            ));
+        }
+        row.push(InlineKeyboardButton::callback(
+            "Subscribe to similar",
+            &CallbackAction::SubscribeSimilar(job.job_id.clone()),
+        ));
+        keyboard.push(row);
+    }
+    InlineKeyboardMarkup {
+        inline_keyboard: keyboard,
+    }
+}
+
+// Keyboard of the job announcements, deliveries have none
+pub fn notification_keyboard(
+    notification: &Notification,
+    callbacks: bool,
+) -> Option<InlineKeyboardMarkup> {
+    match notification {
+        Notification::JobPublished(job) => Some(job_keyboard(job, callbacks)),
+        Notification::JobUpdated(status) => Some(job_keyboard(&status.job, callbacks)),
+        Notification::JobDelivered(_)
+        | Notification::Digest(_)
+        | Notification::ArbitratorRegistered(_) => None,
+    }
+}
+
+// Every Bot API response is wrapped in {"ok": .., "result": ..}
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+// Minimal Bot API client
+#[derive(Clone)]
+pub struct TelegramClient {
+    client: Client,
+    base_url: String,
+    bot_token: String,
+}
+
+impl TelegramClient {
+    pub fn new(client: Client, base_url: &str, bot_token: &str) -> Self {
+        TelegramClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            bot_token: bot_token.to_string(),
+        }
+    }
+
+    #[tracing::instrument(name = "telegram_call", skip(self, body))]
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        body: serde_json::Value,
+        timeout: Option<Duration>,
+    ) -> Result<T, AppError> {
+        let url = format!("{}/bot{}/{}", self.base_url, self.bot_token, method);
+        let mut request = self.client.post(&url).json(&body);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+        let resp_status = response.status();
+        if !resp_status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AppError::TelegramApi(format!(
+                "Telegram API error: status: {}, text: {}",
+                resp_status, error_text
+            )));
+        }
+
+        let response: ApiResponse<T> = response.json().await?;
+        match (response.ok, response.result) {
+            (true, Some(result)) => Ok(result),
+            _ => Err(AppError::TelegramApi(format!(
+                "Telegram API error: {}",
+                response.description.unwrap_or_default()
+            ))),
+        }
+    }
+
+    // Same as `call` with a multipart body, for file uploads
+    #[tracing::instrument(name = "telegram_call_multipart", skip(self, form))]
+    async fn call_multipart<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        form: multipart::Form,
+    ) -> Result<T, AppError> {
+        let url = format!("{}/bot{}/{}", self.base_url, self.bot_token, method);
+        let response = self.client.post(&url).multipart(form).send().await?;
+        let resp_status = response.status();
+        if !resp_status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AppError::TelegramApi(format!(
+                "Telegram API error: status: {}, text: {}",
+                resp_status, error_text
+            )));
+        }
+
+        let response: ApiResponse<T> = response.json().await?;
+        match (response.ok, response.result) {
+            (true, Some(result)) => Ok(result),
+            _ => Err(AppError::TelegramApi(format!(
+                "Telegram API error: {}",
+                response.description.unwrap_or_default()
+            ))),
+        }
+    }
+
+    // Send an HTML message
+    pub async fn send_message(&self, chat_id: &str, text: &str) -> Result<Message, AppError> {
+        self.send_message_to_thread(chat_id, None, text, None).await
+    }
+
+    // Send an HTML message to a forum topic, None posts to the main thread
+    pub async fn send_message_to_thread(
+        &self,
+        chat_id: &str,
+        message_thread_id: Option<i64>,
+        text: &str,
+        keyboard: Option<&InlineKeyboardMarkup>,
+    ) -> Result<Message, AppError> {
+        let mut body = serde_json::json!({
             "chat_id": chat_id,
-            "text": message,
+            "text": text,
             "parse_mode": "html"
-        }))
-        .send()
-        .await?;
+        });
+        if let Some(message_thread_id) = message_thread_id {
+            body["message_thread_id"] = message_thread_id.into();
+        }
+        if let Some(keyboard) = keyboard {
+            body["reply_markup"] = serde_json::to_value(keyboard)?;
+        }
+        self.call("sendMessage", body, None).await
+    }
+
+    // Send a photo with an HTML caption, uploads go as multipart and file ids as JSON
+    pub async fn send_photo(
+        &self,
+        chat_id: &str,
+        message_thread_id: Option<i64>,
+        photo: &InputPhoto,
+        caption: &str,
+        keyboard: Option<&InlineKeyboardMarkup>,
+    ) -> Result<Message, AppError> {
+        match photo {
+            InputPhoto::FileId(file_id) => {
+                let mut body = serde_json::json!({
+                    "chat_id": chat_id,
+                    "photo": file_id,
+                    "caption": caption,
+                    "parse_mode": "html"
+                });
+                if let Some(message_thread_id) = message_thread_id {
+                    body["message_thread_id"] = message_thread_id.into();
+                }
+                if let Some(keyboard) = keyboard {
+                    body["reply_markup"] = serde_json::to_value(keyboard)?;
+                }
+                self.call("sendPhoto", body, None).await
+            }
+            InputPhoto::Upload(bytes) => {
+                let file = multipart::Part::bytes(bytes.clone())
+                    .file_name("card.png")
+                    .mime_str("image/png")?;
+                let mut form = multipart::Form::new()
+                    .text("chat_id", chat_id.to_string())
+                    .text("caption", caption.to_string())
+                    .text("parse_mode", "html")
+                    .part("photo", file);
+                if let Some(message_thread_id) = message_thread_id {
+                    form = form.text("message_thread_id", message_thread_id.to_string());
+                }
+                if let Some(keyboard) = keyboard {
+                    form = form.text("reply_markup", serde_json::to_string(keyboard)?);
+                }
+                self.call_multipart("sendPhoto", form).await
+            }
+        }
+    }
 
-    if response.status().is_success() {
-        tracing::info!("Sent Telegram notification for job {}", notification.job_id);
+    pub async fn edit_message_caption(
+        &self,
+        chat_id: &str,
+        message_id: i64,
+        caption: &str,
+        keyboard: Option<&InlineKeyboardMarkup>,
+    ) -> Result<(), AppError> {
+        let mut body = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "caption": caption,
+            "parse_mode": "html"
+        });
+        if let Some(keyboard) = keyboard {
+            body["reply_markup"] = serde_json::to_value(keyboard)?;
+        }
+        let _: serde_json::Value = self.call("editMessageCaption", body, None).await?;
         Ok(())
-    } else {
-        let resp_status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
+    }
+
+    // The keyboard is removed unless it is sent again
+    pub async fn edit_message_text(
+        &self,
+        chat_id: &str,
+        message_id: i64,
+        text: &str,
+        keyboard: Option<&InlineKeyboardMarkup>,
+    ) -> Result<(), AppError> {
+        let mut body = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "text": text,
+            "parse_mode": "html"
+        });
+        if let Some(keyboard) = keyboard {
+            body["reply_markup"] = serde_json::to_value(keyboard)?;
+        }
+        // Returns the edited message, or true for inline messages
+        let _: serde_json::Value = self.call("editMessageText", body, None).await?;
+        Ok(())
+    }
+
+    // Stop the loading animation of a pressed button, `text` is shown as a toast or an alert
+    pub async fn answer_callback_query(
+        &self,
+        callback_query_id: &str,
+        text: &str,
+        show_alert: bool,
+    ) -> Result<(), AppError> {
+        let _: bool = self
+            .call(
+                "answerCallbackQuery",
+                serde_json::json!({
+                    "callback_query_id": callback_query_id,
+                    "text": text,
+                    "show_alert": show_alert
+                }),
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_message(&self, chat_id: &str, message_id: i64) -> Result<(), AppError> {
+        let _: bool = self
+            .call(
+                "deleteMessage",
+                serde_json::json!({
+                    "chat_id": chat_id,
+                    "message_id": message_id
+                }),
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    // The bot itself, for its username
+    pub async fn get_me(&self) -> Result<User, AppError> {
+        self.call("getMe", serde_json::json!({}), None).await
+    }
+
+    // Long poll for updates newer than `offset`
+    pub async fn get_updates(
+        &self,
+        offset: i64,
+        timeout_secs: u64,
+    ) -> Result<Vec<Update>, AppError> {
+        self.call(
+            "getUpdates",
+            serde_json::json!({
+                "offset": offset,
+                "timeout": timeout_secs,
+                "allowed_updates": ["message", "callback_query"]
+            }),
+            // Leave the server time to answer before giving up
+            Some(Duration::from_secs(timeout_secs + 10)),
+        )
+        .await
+    }
+}
+
+// Render a notification with the Telegram templates
+pub fn render_notification(
+    templates: &Templates,
+    notification: &Notification,
+) -> Result<String, AppError> {
+    match notification {
+        Notification::JobPublished(job) => render_job_message(templates, job, MESSAGE_MAX_LEN),
+        Notification::JobDelivered(delivery) => {
+            render_delivery_message(templates, delivery, MESSAGE_MAX_LEN)
+        }
+        Notification::JobUpdated(status) => {
+            render_status_message(templates, status, MESSAGE_MAX_LEN)
+        }
+        Notification::Digest(digest) => templates.render(TELEGRAM_DIGEST, &digest_context(digest)?),
+        Notification::ArbitratorRegistered(arbitrator) => {
+            templates.render(TELEGRAM_ARBITRATOR, &arbitrator_context(arbitrator)?)
+        }
+    }
+}
+
+// Send notification to every destination it is routed to
+// State changes edit the posts made for the job instead
+// `callbacks` adds the buttons handled by the bot
+// New jobs are sent as `card` with the message as caption when there is one
+#[tracing::instrument(
+    name = "send_telegram_notification",
+    skip(api, templates, destinations, posts, card)
+)]
+pub async fn send_telegram_notification(
+    api: &TelegramClient,
+    templates: &Templates,
+    notification: &Notification,
+    destinations: &[TelegramDestination],
+    posts: &PostStore,
+    callbacks: bool,
+    card: Option<Vec<u8>>,
+) -> Result<(), AppError> {
+    let job_id = notification.job_id();
+    let routes = route_notification(destinations, notification);
+    if routes.is_empty() {
+        tracing::debug!("No Telegram destination for job {}", job_id);
+        return Ok(());
+    }
+
+    let message = render_notification(templates, notification)?;
+    let keyboard = notification_keyboard(notification, callbacks);
+    let mut photo = match (notification, card) {
+        (Notification::JobPublished(job), Some(card)) => Some((
+            InputPhoto::Upload(card),
+            render_job_message(templates, job, CAPTION_MAX_LEN)?,
+        )),
+        _ => None,
+    };
+    for (chat_id, message_thread_id) in routes {
+        let post = Post {
+            chat_id,
+            message_thread_id,
+            text: &message,
+            photo: photo
+                .as_ref()
+                .map(|(photo, caption)| (photo, caption.as_str())),
+            keyboard: keyboard.as_ref(),
+        };
+        let sent = match post.send(api).await {
+            // A deleted or mistyped topic shouldn't lose the message
+            Err(AppError::TelegramApi(e))
+                if message_thread_id.is_some() && e.contains("thread not found") =>
+            {
+                tracing::warn!(
+                    "Topic {:?} not found in chat {}, posting to the main thread",
+                    message_thread_id,
+                    chat_id
+                );
+                Post {
+                    message_thread_id: None,
+                    ..post
+                }
+                .send(api)
+                .await
+            }
+            sent => sent,
+        };
+        match sent {
+            Ok(message) => {
+                tracing::info!(
+                    "Sent Telegram notification for job {} to chat {}",
+                    job_id,
+                    chat_id
+                );
+                // The card is uploaded once, other chats get its file id
+                if let (Some((photo, _)), Some(size)) = (
+                    photo.as_mut(),
+                    message.photo.as_ref().and_then(|sizes| sizes.last()),
+                ) {
+                    *photo = InputPhoto::FileId(size.file_id.clone());
+                }
+                if let Notification::JobPublished(_) = notification {
+                    posts.add_telegram(
+                        job_id,
+                        TelegramPost {
+                            chat_id: chat_id.to_string(),
+                            message_id: message.message_id,
+                            photo: message.photo.is_some(),
+                        },
+                    );
+                }
+            }
+            Err(e) => tracing::error!("Failed to send Telegram notification to {}: {}", chat_id, e),
+        }
+    }
+    Ok(())
+}
+
+// One announcement in one chat
+#[derive(Clone, Copy)]
+struct Post<'a> {
+    chat_id: &'a str,
+    message_thread_id: Option<i64>,
+    text: &'a str,
+    // Card and its caption
+    photo: Option<(&'a InputPhoto, &'a str)>,
+    keyboard: Option<&'a InlineKeyboardMarkup>,
+}
+
+impl Post<'_> {
+    // Send the card, or the text message when there is none or it is refused
+    async fn send(&self, api: &TelegramClient) -> Result<Message, AppError> {
+        if let Some((photo, caption)) = self.photo {
+            match api
+                .send_photo(
+                    self.chat_id,
+                    self.message_thread_id,
+                    photo,
+                    caption,
+                    self.keyboard,
+                )
+                .await
+            {
+                Err(AppError::TelegramApi(e)) if e.contains("thread not found") => {
+                    return Err(AppError::TelegramApi(e))
+                }
+                Err(e) => tracing::warn!(
+                    "Can't send card to chat {}, sending text: {}",
+                    self.chat_id,
+                    e
+                ),
+                sent => return sent,
+            }
+        }
+        api.send_message_to_thread(
+            self.chat_id,
+            self.message_thread_id,
+            self.text,
+            self.keyboard,
+        )
+        .await
+    }
+}
 
-        Err(AppError::TelegramApi(format!(
-            "Telegram API error: status: {}, text: {}",
-            resp_status, error_text
-        )))
+// Edit the announcements of a job with its new status, or delete them once closed
+#[tracing::instrument(name = "update_telegram_posts", skip(api, templates, posts))]
+pub async fn update_telegram_posts(
+    api: &TelegramClient,
+    templates: &Templates,
+    notification: &JobStatusNotification,
+    posts: &PostStore,
+    delete_closed: bool,
+    callbacks: bool,
+) -> Result<(), AppError> {
+    let job_id = &notification.job.job_id;
+    let job_posts = posts.get(job_id).telegram;
+    if job_posts.is_empty() {
+        tracing::debug!("No Telegram post to update for job {}", job_id);
+        return Ok(());
+    }
+
+    if delete_closed && notification.job.job.state == JobState::Closed {
+        for post in &job_posts {
+            // Bots can't delete messages older than 48 hours
+            if let Err(e) = api.delete_message(&post.chat_id, post.message_id).await {
+                tracing::error!("Failed to delete post of job {}: {}", job_id, e);
+            }
+        }
+        posts.clear_telegram(job_id);
+        return Ok(());
     }
+
+    let message = render_status_message(templates, notification, MESSAGE_MAX_LEN)?;
+    let caption = render_status_message(templates, notification, CAPTION_MAX_LEN)?;
+    let keyboard = job_keyboard(&notification.job, callbacks);
+    for post in &job_posts {
+        let edited = if post.photo {
+            api.edit_message_caption(&post.chat_id, post.message_id, &caption, Some(&keyboard))
+                .await
+        } else {
+            api.edit_message_text(&post.chat_id, post.message_id, &message, Some(&keyboard))
+                .await
+        };
+        match edited {
+            Ok(()) => tracing::info!("Updated post of job {} in chat {}", job_id, post.chat_id),
+            // Events that don't change the text, e.g. a second update
+            Err(AppError::TelegramApi(e)) if e.contains("message is not modified") => {}
+            Err(e) => tracing::error!("Failed to update post of job {}: {}", job_id, e),
+        }
+    }
+    Ok(())
+}
+
+// Chats and forum topics a notification goes to
+pub fn route_notification<'a>(
+    destinations: &'a [TelegramDestination],
+    notification: &Notification,
+) -> Vec<(&'a str, Option<i64>)> {
+    destinations
+        .iter()
+        .filter(|destination| destination.filter.matches(notification))
+        .map(|destination| {
+            (
+                destination.chat_id.as_str(),
+                destination.thread_for(notification),
+            )
+        })
+        .collect()
 }
 
 // Notification worker
-#[tracing::instrument(name = "telegram_worker", skip(rx))]
-pub async fn telegram_worker(mut rx: mpsc::Receiver<JobNotification>) -> Result<(), AppError> {
-    let tg_client = Client::new();
+#[tracing::instrument(name = "telegram_worker", skip(rx, settings, posts))]
+pub async fn telegram_worker(
+    mut rx: mpsc::Receiver<Notification>,
+    settings: Settings,
+    posts: Arc<PostStore>,
+) -> Result<(), AppError> {
+    let templates = Templates::from_settings(&settings)?;
     let telegram_bot_token = env::var("TELEGRAM_BOT_API").expect("TELEGRAM_BOT_API not set");
-    let telegram_chat_id = env::var("TG_CHAT_ID").expect("TG_CHAT_ID not set");
+    let api = TelegramClient::new(
+        Client::new(),
+        settings.telegram.api_url(),
+        &telegram_bot_token,
+    );
+    // Without configured destinations everything goes to TG_CHAT_ID
+    let destinations = if settings.telegram.destinations.is_empty() {
+        vec![TelegramDestination {
+            chat_id: env::var("TG_CHAT_ID").expect("TG_CHAT_ID not set"),
+            ..Default::default()
+        }]
+    } else {
+        settings.telegram.destinations.clone()
+    };
+
+    // Profile and subscribe buttons only work while the bot answers them
+    let callbacks = settings.telegram.bot.enabled;
+    let cards = settings
+        .cards
+        .enabled
+        .then(|| CardRenderer::new(Client::new(), settings.number_format.clone()));
 
     while let Some(notification) = rx.recv().await {
         // Send to Telegram
-        if let Err(e) = send_telegram_notification(
-            &tg_client,
-            &notification,
-            &telegram_bot_token,
-            &telegram_chat_id,
-        )
-        .await
-        {
+        let sent = match &notification {
+            Notification::JobUpdated(status) => {
+                update_telegram_posts(
+                    &api,
+                    &templates,
+                    status,
+                    &posts,
+                    settings.telegram.delete_closed,
+                    callbacks,
+                )
+                .await
+            }
+            _ => {
+                let card = match (&notification, &cards) {
+                    (Notification::JobPublished(job), Some(cards)) => {
+                        match cards.render(job).await {
+                            Ok(card) => Some(card),
+                            Err(e) => {
+                                tracing::error!("Failed to render card: {}", e);
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                send_telegram_notification(
+                    &api,
+                    &templates,
+                    &notification,
+                    &destinations,
+                    &posts,
+                    callbacks,
+                    card,
+                )
+                .await
+            }
+        };
+        if let Err(e) = sent {
             tracing::error!("Failed to send Telegram notification: {}", e);
         }
         tracing::info!("Notification processed in telegram: {:?}", notification);
@@ -0,0 +1,596 @@
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use eyre::Result;
+use futures::future::BoxFuture;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::{
+    build_job_notification,
+    content::escape_html,
+    directory::Directory,
+    error::AppError,
+    filters::FilterRule,
+    pricing::PriceOracle,
+    reputation::ReviewIndex,
+    tags::TagCatalogue,
+    telegram_api::{
+        job_keyboard, notification_keyboard, render_notification, CallbackAction, CallbackQuery,
+        InlineKeyboardMarkup, Message, TelegramClient,
+    },
+    templates::Templates,
+    tokens::TokenRegistry,
+    utils::JsonStore,
+    JobNotification, MarketPlaceData, Notification, MARKETPLACE_DATA_ADDRESS,
+};
+
+// Wait before polling again after a failed getUpdates
+const POLL_RETRY_DELAY: Duration = Duration::from_secs(5);
+// Longest text of a callback query answer
+const ALERT_MAX_LEN: usize = 200;
+
+const HELP: &str = "<b>EACC job alerts</b>
+
+/subscribe [terms] - get new jobs matching every term by DM
+    <code>tag:rust</code> job tag
+    <code>token:ARB</code> reward token
+    <code>min:100</code> minimum reward in USD
+    <code>amount:500</code> minimum reward in tokens
+    <code>delivery:ipfs</code> delivery method
+    <code>creator:0x..</code> job creator
+    <code>word</code> / <code>-word</code> keyword in / not in the job
+/unsubscribe [number] - remove one subscription, or all of them
+/list - show your subscriptions
+/job &lt;id&gt; - show a job";
+
+// Looks up a job for `/job <id>`
+pub trait JobLookup: Send + Sync {
+    // None when the id isn't a job
+    fn job<'a>(&'a self, job_id: &'a str) -> BoxFuture<'a, Result<Option<JobNotification>>>;
+}
+
+// Reads jobs from the marketplace contract
+pub struct ChainJobLookup<P> {
+    provider: P,
+    tokens: Arc<TokenRegistry>,
+    prices: Arc<PriceOracle>,
+    directory: Arc<Directory>,
+    reviews: Option<Arc<ReviewIndex>>,
+    catalogue: Arc<TagCatalogue>,
+}
+
+impl<P: Provider + Clone> ChainJobLookup<P> {
+    pub fn new(
+        provider: P,
+        tokens: Arc<TokenRegistry>,
+        prices: Arc<PriceOracle>,
+        directory: Arc<Directory>,
+        reviews: Option<Arc<ReviewIndex>>,
+        catalogue: Arc<TagCatalogue>,
+    ) -> Self {
+        ChainJobLookup {
+            provider,
+            tokens,
+            prices,
+            directory,
+            reviews,
+            catalogue,
+        }
+    }
+}
+
+impl<P: Provider + Clone + Send + Sync> JobLookup for ChainJobLookup<P> {
+    fn job<'a>(&'a self, job_id: &'a str) -> BoxFuture<'a, Result<Option<JobNotification>>> {
+        Box::pin(async move {
+            let Ok(job_id) = job_id.parse::<U256>() else {
+                return Ok(None);
+            };
+            // Job ids are indexes in the jobs array
+            let marketplace_data = MarketPlaceData::new(MARKETPLACE_DATA_ADDRESS, &self.provider);
+            if job_id >= marketplace_data.jobsLength().call().await?._0 {
+                return Ok(None);
+            }
+            let job = build_job_notification(
+                self.provider.clone(),
+                job_id,
+                &self.tokens,
+                &self.prices,
+                &self.directory,
+                self.reviews.as_deref(),
+                &self.catalogue,
+            )
+            .await?;
+            Ok(Some(job))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    Subscribe(Box<FilterRule>),
+    // 1-based index, None removes every subscription
+    Unsubscribe(Option<usize>),
+    List,
+    Job(String),
+}
+
+impl Command {
+    // Commands are messages starting with /, those suffixed with another bot name are for it
+    // Without a known `username` every suffix is accepted
+    pub fn is_addressed_to(text: &str, username: Option<&str>) -> bool {
+        let Some(command) = text.split_whitespace().next() else {
+            return false;
+        };
+        if !command.starts_with('/') {
+            return false;
+        }
+        match (command.split_once('@'), username) {
+            (Some((_, suffix)), Some(username)) => suffix.eq_ignore_ascii_case(username),
+            _ => true,
+        }
+    }
+
+    // Parse a message text, the error is the reply explaining what's wrong
+    pub fn parse(text: &str) -> Result<Command, String> {
+        let mut words = text.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        // Commands sent in groups are suffixed with the bot name: /list@eacc_bot
+        let command = command.split('@').next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+
+        match command {
+            "/start" | "/help" => Ok(Command::Help),
+            "/subscribe" => {
+                parse_subscription(&args).map(|rule| Command::Subscribe(Box::new(rule)))
+            }
+            "/unsubscribe" => match args.first() {
+                None => Ok(Command::Unsubscribe(None)),
+                Some(index) => index
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index > 0)
+                    .map(|index| Command::Unsubscribe(Some(index)))
+                    .ok_or_else(|| "Usage: /unsubscribe [number from /list]".to_string()),
+            },
+            "/list" => Ok(Command::List),
+            "/job" => match args.first() {
+                Some(job_id) => Ok(Command::Job(job_id.to_string())),
+                None => Err("Usage: /job &lt;id&gt;".to_string()),
+            },
+            _ => Err("Unknown command, see /help".to_string()),
+        }
+    }
+}
+
+// `/subscribe tag:rust min:100 audit` -> FilterRule
+pub fn parse_subscription(terms: &[&str]) -> Result<FilterRule, String> {
+    let mut rule = FilterRule {
+        // Subscribers want new jobs, not every delivery
+        deliveries: false,
+        ..Default::default()
+    };
+    for term in terms {
+        let parse_number = |value: &str| {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite() && *value >= 0.0)
+                .ok_or_else(|| format!("{} is not a valid amount", escape_html(term)))
+        };
+        match term.split_once(':') {
+            Some(("tag", tag)) if !tag.is_empty() => rule.tags.push(tag.to_string()),
+            Some(("token", token)) if !token.is_empty() => rule.tokens.push(token.to_string()),
+            Some(("min", value)) => rule.min_usd = Some(parse_number(value)?),
+            Some(("amount", value)) => rule.min_amount = Some(parse_number(value)?),
+            Some(("delivery", method)) if !method.is_empty() => {
+                rule.delivery_methods.push(method.to_string())
+            }
+            Some(("creator", creator)) if !creator.is_empty() => {
+                rule.creators.push(creator.to_string())
+            }
+            Some((key, _)) if !key.starts_with("http") => {
+                return Err(format!("Unknown filter {}, see /help", escape_html(key)))
+            }
+            _ => match term.strip_prefix('-') {
+                Some(keyword) if !keyword.is_empty() => {
+                    rule.exclude_keywords.push(keyword.to_string())
+                }
+                _ => rule.keywords.push(term.to_string()),
+            },
+        }
+    }
+    Ok(rule)
+}
+
+// Subscription to the jobs sharing a tag with `job`, or to every job when it has none
+pub fn similar_jobs_rule(job: &JobNotification) -> FilterRule {
+    FilterRule {
+        tags: job.job.tags.clone(),
+        deliveries: false,
+        ..Default::default()
+    }
+}
+
+// Plain text alert shown by the "Creator profile" button
+pub fn describe_creator(job: &JobNotification) -> String {
+    let Some(profile) = &job.creator_profile else {
+        return "The creator of this job has no profile yet".to_string();
+    };
+    let mut lines = vec![profile.name.clone()];
+    if let Some(badge) = job
+        .creator_rating
+        .as_ref()
+        .and_then(|rating| rating.badge.clone())
+    {
+        lines.push(badge);
+    }
+    lines.push(format!(
+        "👍 {} 👎 {}",
+        profile.reputation_up, profile.reputation_down
+    ));
+    if !profile.bio.is_empty() {
+        lines.push(profile.bio.clone());
+    }
+    let text = lines.join("\n");
+    if text.chars().count() <= ALERT_MAX_LEN {
+        return text;
+    }
+    let mut text: String = text.chars().take(ALERT_MAX_LEN - 1).collect();
+    text.push('…');
+    text
+}
+
+// FilterRule written back in the /subscribe syntax
+pub fn describe_subscription(rule: &FilterRule) -> String {
+    let mut terms = Vec::new();
+    terms.extend(rule.tags.iter().map(|tag| format!("tag:{}", tag)));
+    terms.extend(rule.tokens.iter().map(|token| format!("token:{}", token)));
+    terms.extend(rule.min_usd.map(|min| format!("min:{}", min)));
+    terms.extend(rule.min_amount.map(|min| format!("amount:{}", min)));
+    terms.extend(
+        rule.delivery_methods
+            .iter()
+            .map(|method| format!("delivery:{}", method)),
+    );
+    terms.extend(
+        rule.creators
+            .iter()
+            .map(|creator| format!("creator:{}", creator)),
+    );
+    terms.extend(rule.keywords.iter().cloned());
+    terms.extend(
+        rule.exclude_keywords
+            .iter()
+            .map(|keyword| format!("-{}", keyword)),
+    );
+    if terms.is_empty() {
+        "all jobs".to_string()
+    } else {
+        terms.join(" ")
+    }
+}
+
+// Subscriptions of every chat, saved on each change
+#[derive(Debug, Default)]
+pub struct SubscriptionStore {
+    chats: JsonStore<BTreeMap<i64, Vec<FilterRule>>>,
+}
+
+impl SubscriptionStore {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        Ok(SubscriptionStore {
+            chats: JsonStore::load(path, "subscriptions")?,
+        })
+    }
+
+    // Returns the number of subscriptions of the chat
+    pub fn subscribe(&self, chat_id: i64, rule: FilterRule) -> Result<usize, AppError> {
+        self.chats.update(|chats| {
+            let rules = chats.entry(chat_id).or_default();
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+            rules.len()
+        })
+    }
+
+    // Remove one subscription (1-based) or all of them, returns how many were removed
+    pub fn unsubscribe(&self, chat_id: i64, index: Option<usize>) -> Result<usize, AppError> {
+        let removed = self.chats.change(|chats| {
+            let removed = match (chats.get_mut(&chat_id), index) {
+                (None, _) => 0,
+                (Some(rules), Some(index)) if index <= rules.len() => {
+                    rules.remove(index - 1);
+                    1
+                }
+                (Some(_), Some(_)) => 0,
+                (Some(rules), None) => std::mem::take(rules).len(),
+            };
+            chats.retain(|_, rules| !rules.is_empty());
+            removed
+        });
+        if removed > 0 {
+            self.chats.save()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn list(&self, chat_id: i64) -> Vec<FilterRule> {
+        self.chats
+            .read(|chats| chats.get(&chat_id).cloned())
+            .unwrap_or_default()
+    }
+
+    // Chats with at least one subscription matching the notification
+    pub fn matching_chats(&self, notification: &Notification) -> Vec<i64> {
+        self.chats.read(|chats| {
+            chats
+                .iter()
+                .filter(|(_, rules)| rules.iter().any(|rule| rule.matches(notification)))
+                .map(|(chat_id, _)| *chat_id)
+                .collect()
+        })
+    }
+}
+
+pub struct TelegramBot {
+    api: TelegramClient,
+    templates: Templates,
+    store: SubscriptionStore,
+    jobs: Arc<dyn JobLookup>,
+    // Username of the bot, to tell its commands from other bots' in groups
+    username: OnceLock<String>,
+}
+
+impl TelegramBot {
+    pub fn new(
+        api: TelegramClient,
+        templates: Templates,
+        store: SubscriptionStore,
+        jobs: Arc<dyn JobLookup>,
+    ) -> Self {
+        TelegramBot {
+            api,
+            templates,
+            store,
+            jobs,
+            username: OnceLock::new(),
+        }
+    }
+
+    pub fn set_username(&self, username: &str) {
+        let _ = self.username.set(username.to_string());
+    }
+
+    pub fn store(&self) -> &SubscriptionStore {
+        &self.store
+    }
+
+    // Handle one batch of updates, returns the offset of the next getUpdates
+    #[tracing::instrument(name = "poll_updates", skip(self))]
+    pub async fn poll_once(&self, offset: i64, timeout_secs: u64) -> Result<i64, AppError> {
+        let updates = self.api.get_updates(offset, timeout_secs).await?;
+        let mut next_offset = offset;
+        for update in updates {
+            next_offset = next_offset.max(update.update_id + 1);
+            if let Some(message) = update.message {
+                if let Err(e) = self.handle_message(&message).await {
+                    tracing::error!("Failed to handle Telegram message: {}", e);
+                }
+            }
+            if let Some(query) = update.callback_query {
+                if let Err(e) = self.handle_callback(&query).await {
+                    tracing::error!("Failed to handle Telegram callback: {}", e);
+                }
+            }
+        }
+        Ok(next_offset)
+    }
+
+    #[tracing::instrument(name = "handle_message", skip(self, message))]
+    pub async fn handle_message(&self, message: &Message) -> Result<(), AppError> {
+        let Some(text) = message.text.as_deref() else {
+            return Ok(());
+        };
+        // Groups talk among themselves, only commands for this bot get an answer
+        if !Command::is_addressed_to(text, self.username.get().map(String::as_str)) {
+            return Ok(());
+        }
+        let chat_id = message.chat.id;
+        let thread_id = message
+            .message_thread_id
+            .filter(|_| message.is_topic_message);
+        let (reply, keyboard) = match Command::parse(text) {
+            Ok(command) => self.run_command(chat_id, command).await?,
+            Err(reply) => (reply, None),
+        };
+        self.api
+            .send_message_to_thread(&chat_id.to_string(), thread_id, &reply, keyboard.as_ref())
+            .await?;
+        Ok(())
+    }
+
+    // Buttons of the job announcements, the answer is shown to the user who pressed it
+    #[tracing::instrument(name = "handle_callback", skip(self, query))]
+    pub async fn handle_callback(&self, query: &CallbackQuery) -> Result<(), AppError> {
+        let Some(action) = query.data.as_deref().and_then(CallbackAction::parse) else {
+            return self
+                .api
+                .answer_callback_query(&query.id, "This button is no longer supported", false)
+                .await;
+        };
+        let job_id = match &action {
+            CallbackAction::CreatorProfile(job_id) | CallbackAction::SubscribeSimilar(job_id) => {
+                job_id
+            }
+        };
+        let job = match self.jobs.job(job_id).await {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                return self
+                    .api
+                    .answer_callback_query(&query.id, "Job not found", false)
+                    .await
+            }
+            Err(e) => {
+                tracing::error!("Failed to look up job {}: {}", job_id, e);
+                return self
+                    .api
+                    .answer_callback_query(&query.id, "Can't load the job right now", false)
+                    .await;
+            }
+        };
+
+        let (text, show_alert) = match action {
+            CallbackAction::CreatorProfile(_) => (describe_creator(&job), true),
+            CallbackAction::SubscribeSimilar(_) => {
+                // Alerts are DMed to the user, not to the chat holding the announcement
+                let rule = similar_jobs_rule(&job);
+                let description = describe_subscription(&rule);
+                self.store.subscribe(query.from.id, rule)?;
+                (
+                    format!(
+                        "Subscribed to {}. Start a chat with the bot to receive the alerts.",
+                        description
+                    ),
+                    false,
+                )
+            }
+        };
+        self.api
+            .answer_callback_query(&query.id, &text, show_alert)
+            .await
+    }
+
+    async fn run_command(
+        &self,
+        chat_id: i64,
+        command: Command,
+    ) -> Result<(String, Option<InlineKeyboardMarkup>), AppError> {
+        let reply = match command {
+            Command::Help => HELP.to_string(),
+            Command::Subscribe(rule) => {
+                let description = escape_html(&describe_subscription(&rule));
+                let count = self.store.subscribe(chat_id, *rule)?;
+                format!(
+                    "Subscribed to {}. You have {} subscription{}.",
+                    description,
+                    count,
+                    if count == 1 { "" } else { "s" }
+                )
+            }
+            Command::Unsubscribe(index) => match self.store.unsubscribe(chat_id, index)? {
+                0 => "Nothing to remove, see /list".to_string(),
+                1 => "Removed 1 subscription.".to_string(),
+                removed => format!("Removed {} subscriptions.", removed),
+            },
+            Command::List => {
+                let rules = self.store.list(chat_id);
+                if rules.is_empty() {
+                    "No subscriptions yet, see /help".to_string()
+                } else {
+                    let lines: Vec<String> = rules
+                        .iter()
+                        .enumerate()
+                        .map(|(i, rule)| {
+                            format!("{}. {}", i + 1, escape_html(&describe_subscription(rule)))
+                        })
+                        .collect();
+                    format!("<b>Your subscriptions</b>\n{}", lines.join("\n"))
+                }
+            }
+            Command::Job(job_id) => match self.jobs.job(&job_id).await {
+                Ok(Some(job)) => {
+                    let keyboard = job_keyboard(&job, true);
+                    let message =
+                        render_notification(&self.templates, &Notification::JobPublished(job))?;
+                    return Ok((message, Some(keyboard)));
+                }
+                Ok(None) => format!("Job {} not found", escape_html(&job_id)),
+                Err(e) => {
+                    tracing::error!("Failed to look up job {}: {}", job_id, e);
+                    format!("Can't load job {} right now", escape_html(&job_id))
+                }
+            },
+        };
+        Ok((reply, None))
+    }
+
+    // DM the notification to every chat subscribed to it
+    #[tracing::instrument(name = "deliver_subscriptions", skip(self, notification))]
+    pub async fn deliver(&self, notification: &Notification) -> Result<usize, AppError> {
+        // Alerts aren't edited, state changes would only be noise in a DM
+        if let Notification::JobUpdated(_)
+        | Notification::Digest(_)
+        | Notification::ArbitratorRegistered(_) = notification
+        {
+            return Ok(0);
+        }
+        let chats = self.store.matching_chats(notification);
+        if chats.is_empty() {
+            return Ok(0);
+        }
+        let message = render_notification(&self.templates, notification)?;
+        let keyboard = notification_keyboard(notification, true);
+        let mut sent = 0;
+        for chat_id in chats {
+            match self
+                .api
+                .send_message_to_thread(&chat_id.to_string(), None, &message, keyboard.as_ref())
+                .await
+            {
+                Ok(_) => sent += 1,
+                Err(e) => tracing::error!("Failed to send alert to chat {}: {}", chat_id, e),
+            }
+        }
+        Ok(sent)
+    }
+}
+
+// Long polling loop answering commands
+#[tracing::instrument(name = "telegram_bot", skip(bot))]
+pub async fn run_bot(bot: Arc<TelegramBot>, poll_timeout_secs: u64) {
+    loop {
+        match bot.api.get_me().await {
+            Ok(me) => {
+                if let Some(username) = me.username {
+                    bot.set_username(&username);
+                }
+                break;
+            }
+            Err(e) => {
+                tracing::error!("Failed to get the bot username: {}", e);
+                tokio::time::sleep(POLL_RETRY_DELAY).await;
+            }
+        }
+    }
+    let mut offset = 0;
+    loop {
+        match bot.poll_once(offset, poll_timeout_secs).await {
+            Ok(next_offset) => offset = next_offset,
+            Err(e) => {
+                tracing::error!("Failed to get Telegram updates: {}", e);
+                tokio::time::sleep(POLL_RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+// Notification worker sending personalised alerts
+#[tracing::instrument(name = "telegram_bot_worker", skip(rx, bot))]
+pub async fn telegram_bot_worker(
+    mut rx: mpsc::Receiver<Notification>,
+    bot: Arc<TelegramBot>,
+) -> Result<(), AppError> {
+    while let Some(notification) = rx.recv().await {
+        match bot.deliver(&notification).await {
+            Ok(sent) => tracing::info!("Notification sent to {} subscribers", sent),
+            Err(e) => tracing::error!("Failed to deliver subscriptions: {}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use tera::Context;
+
+use crate::{
+    content::escape_html,
+    deliverables::DeliveryNotification,
+    error::AppError,
+    events::JobStatusNotification,
+    templates::{
+        delivery_context, job_context, status_context, Templates, TELEGRAM_DELIVERY, TELEGRAM_JOB,
+    },
+    JobNotification,
+};
+
+// Telegram limits, counted in UTF-16 code units of the text after entity parsing
+pub const MESSAGE_MAX_LEN: usize = 4096;
+pub const CAPTION_MAX_LEN: usize = 1024;
+
+pub const DASHBOARD_URL: &str = "https://effectiveacceleration.ai/dashboard";
+
+pub fn job_url(job_id: &str) -> String {
+    format!("{}/jobs/{}", DASHBOARD_URL, job_id)
+}
+
+// Piece of a Telegram HTML string
+enum Token<'a> {
+    OpenTag(&'a str, &'a str),
+    CloseTag(&'a str),
+    // Entity or single char, and its visible length in UTF-16 code units
+    Text(&'a str, usize),
+}
+
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        let tag_end = if c == '<' { rest.find('>') } else { None };
+        let entity_end = if c == '&' {
+            rest.find(';').filter(|end| *end <= 10)
+        } else {
+            None
+        };
+
+        let len = if let Some(end) = tag_end {
+            let raw = &rest[..=end];
+            let inner = &raw[1..raw.len() - 1];
+            match inner.strip_prefix('/') {
+                Some(name) => tokens.push(Token::CloseTag(name.trim())),
+                None => {
+                    let name = inner.split_whitespace().next().unwrap_or_default();
+                    tokens.push(Token::OpenTag(name, raw));
+                }
+            }
+            end + 1
+        } else if let Some(end) = entity_end {
+            tokens.push(Token::Text(&rest[..=end], 1));
+            end + 1
+        } else {
+            let len = c.len_utf8();
+            tokens.push(Token::Text(&rest[..len], c.len_utf16()));
+            len
+        };
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+// Length of a plain string as counted by Telegram
+pub fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// Length of an HTML string once tags are stripped and entities decoded
+pub fn visible_len(html: &str) -> usize {
+    tokenize(html)
+        .iter()
+        .map(|token| match token {
+            Token::Text(_, len) => *len,
+            _ => 0,
+        })
+        .sum()
+}
+
+// Cut an HTML string to at most `max_len` visible UTF-16 code units
+// Tags and entities are never split and every tag left open is closed
+pub fn truncate_html(html: &str, max_len: usize) -> (String, bool) {
+    if visible_len(html) <= max_len {
+        return (html.to_string(), false);
+    }
+    let ellipsis = "…";
+    let budget = max_len.saturating_sub(utf16_len(ellipsis));
+
+    let mut out = String::with_capacity(html.len());
+    let mut open_tags: Vec<&str> = Vec::new();
+    let mut used = 0;
+    for token in tokenize(html) {
+        match token {
+            Token::OpenTag(name, raw) => {
+                open_tags.push(name);
+                out.push_str(raw);
+            }
+            Token::CloseTag(name) => {
+                if let Some(position) = open_tags.iter().rposition(|open| *open == name) {
+                    open_tags.truncate(position);
+                }
+                out.push_str(&format!("</{}>", name));
+            }
+            Token::Text(text, len) => {
+                if used + len > budget {
+                    break;
+                }
+                used += len;
+                out.push_str(text);
+            }
+        }
+    }
+
+    // Drop tags opened right before the cut, they would be empty
+    while let Some(name) = open_tags.last() {
+        let Some(start) = out.rfind('<') else {
+            break;
+        };
+        let is_trailing_open = out.ends_with('>')
+            && out[start..].starts_with(&format!("<{}", name))
+            && !out[start..].starts_with("</");
+        if !is_trailing_open {
+            break;
+        }
+        out.truncate(start);
+        open_tags.pop();
+    }
+
+    let mut out = out.trim_end().to_string();
+    out.push_str(ellipsis);
+    for name in open_tags.iter().rev() {
+        out.push_str(&format!("</{}>", name));
+    }
+    (out, true)
+}
+
+// Render a template around a body cut so the message stays within `max_len`
+// `render` receives the body and whether it was truncated
+pub fn fit_template<F>(mut render: F, body: &str, max_len: usize) -> Result<String, AppError>
+where
+    F: FnMut(&str, bool) -> Result<String, AppError>,
+{
+    let message = render(body, false)?;
+    if visible_len(&message) <= max_len {
+        return Ok(message);
+    }
+    // Everything the template adds around the body, read more link included
+    let overhead = visible_len(&render("", true)?);
+    let (body, _) = truncate_html(body, max_len.saturating_sub(overhead));
+    let message = render(&body, true)?;
+    // Templates using the body more than once can still overflow
+    Ok(truncate_html(&message, max_len).0)
+}
+
+// Render a new job notification within `max_len`
+pub fn render_job_message(
+    templates: &Templates,
+    notification: &JobNotification,
+    max_len: usize,
+) -> Result<String, AppError> {
+    render_job_with_context(templates, notification, job_context(notification)?, max_len)
+}
+
+// Render a job announcement with its status banner within `max_len`
+pub fn render_status_message(
+    templates: &Templates,
+    notification: &JobStatusNotification,
+    max_len: usize,
+) -> Result<String, AppError> {
+    render_job_with_context(
+        templates,
+        &notification.job,
+        status_context(notification)?,
+        max_len,
+    )
+}
+
+fn render_job_with_context(
+    templates: &Templates,
+    notification: &JobNotification,
+    mut context: Context,
+    max_len: usize,
+) -> Result<String, AppError> {
+    let description_html = notification.description.to_telegram_html();
+    fit_template(
+        |body, truncated| {
+            context.insert("description_html", body);
+            context.insert("description_truncated", &truncated);
+            templates.render(TELEGRAM_JOB, &context)
+        },
+        &description_html,
+        max_len,
+    )
+}
+
+// Render a job delivered notification within `max_len`
+pub fn render_delivery_message(
+    templates: &Templates,
+    notification: &DeliveryNotification,
+    max_len: usize,
+) -> Result<String, AppError> {
+    let mut context = delivery_context(notification)?;
+    let preview_html = notification
+        .preview
+        .as_deref()
+        .map(escape_html)
+        .unwrap_or_default();
+    fit_template(
+        |body, _| {
+            context.insert("preview_html", body);
+            templates.render(TELEGRAM_DELIVERY, &context)
+        },
+        &preview_html,
+        max_len,
+    )
+}
@@ -0,0 +1,292 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tera::{Context, Filter, Tera, Value};
+
+use crate::{
+    amount::{AmountFormat, TokenAmount},
+    config::Settings,
+    content::escape_html,
+    deliverables::DeliveryNotification,
+    digest::Digest,
+    error::AppError,
+    events::JobStatusNotification,
+    models::ArbitratorProfile,
+    telegram_format::{job_url, truncate_html, DASHBOARD_URL},
+    JobNotification,
+};
+
+// Template names, `.html` templates are escaped for Telegram automatically
+pub const TELEGRAM_JOB: &str = "telegram_job.html";
+pub const TELEGRAM_DELIVERY: &str = "telegram_delivery.html";
+pub const TELEGRAM_DIGEST: &str = "telegram_digest.html";
+pub const TELEGRAM_ARBITRATOR: &str = "telegram_arbitrator.html";
+pub const X_JOB: &str = "x_job.txt";
+pub const X_STATUS: &str = "x_status.txt";
+pub const X_SUMMARY: &str = "x_summary.txt";
+pub const X_DIGEST: &str = "x_digest.txt";
+pub const X_ARBITRATOR: &str = "x_arbitrator.txt";
+
+// Templates shipped with the binary, used when the config doesn't override them
+const BUNDLED: [(&str, &str); 9] = [
+    (TELEGRAM_JOB, include_str!("../templates/telegram_job.html")),
+    (
+        TELEGRAM_DELIVERY,
+        include_str!("../templates/telegram_delivery.html"),
+    ),
+    (X_JOB, include_str!("../templates/x_job.txt")),
+    (X_STATUS, include_str!("../templates/x_status.txt")),
+    (X_SUMMARY, include_str!("../templates/x_summary.txt")),
+    (
+        TELEGRAM_DIGEST,
+        include_str!("../templates/telegram_digest.html"),
+    ),
+    (X_DIGEST, include_str!("../templates/x_digest.txt")),
+    (
+        TELEGRAM_ARBITRATOR,
+        include_str!("../templates/telegram_arbitrator.html"),
+    ),
+    (X_ARBITRATOR, include_str!("../templates/x_arbitrator.txt")),
+];
+
+#[derive(Clone)]
+pub struct Templates {
+    tera: Tera,
+}
+
+// Filter whose output is already valid Telegram HTML
+struct SafeFilter(fn(&Value, &HashMap<String, Value>) -> tera::Result<Value>);
+
+impl Filter for SafeFilter {
+    fn filter(&self, value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        (self.0)(value, args)
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// {{ amount | currency(symbol=symbol) }} -> 1,500 $ARB
+// Accepts a TokenAmount, a number or a decimal string
+struct CurrencyFilter(AmountFormat);
+
+impl Filter for CurrencyFilter {
+    fn filter(&self, value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let formatted = match value {
+            Value::Object(_) => serde_json::from_value::<TokenAmount>(value.clone())
+                .map_err(|e| tera::Error::msg(format!("currency filter: {}", e)))?
+                .format(&self.0),
+            Value::Number(_) | Value::String(_) => {
+                self.0.format_decimal_str(&value_to_string(value))
+            }
+            _ => return Err(tera::Error::msg("currency filter expects an amount")),
+        };
+        let formatted = match args.get("symbol") {
+            Some(symbol) => format!("{} ${}", formatted, value_to_string(symbol)),
+            None => formatted,
+        };
+        Ok(Value::String(formatted))
+    }
+}
+
+// {{ usd_value | usd }} -> $1,234.50
+struct UsdFilter(AmountFormat);
+
+impl Filter for UsdFilter {
+    fn filter(&self, value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+        let value = value
+            .as_f64()
+            .ok_or_else(|| tera::Error::msg("usd filter expects a number"))?;
+        Ok(Value::String(self.0.format_usd(value)))
+    }
+}
+
+// Human readable duration from seconds: 259200 -> 3 days
+pub fn format_duration(seconds: u64) -> String {
+    let plural =
+        |value: u64, unit: &str| format!("{} {}{}", value, unit, if value == 1 { "" } else { "s" });
+    match seconds {
+        s if s >= 86400 => match s % 86400 / 3600 {
+            0 => plural(s / 86400, "day"),
+            hours => format!("{} and {}", plural(s / 86400, "day"), plural(hours, "hour")),
+        },
+        s if s >= 3600 => plural(s / 3600, "hour"),
+        s if s >= 60 => plural(s / 60, "minute"),
+        s => plural(s, "second"),
+    }
+}
+
+// {{ job.max_time | duration }} -> 3 days
+fn duration(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let seconds = value
+        .as_u64()
+        .ok_or_else(|| tera::Error::msg("duration filter expects a number of seconds"))?;
+    Ok(Value::String(format_duration(seconds)))
+}
+
+// {{ title | tg_escape }} escapes text placed in a template that isn't escaped
+fn tg_escape(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(Value::String(escape_html(&value_to_string(value))))
+}
+
+// {{ description_html | tg_truncate(length=200) }} cuts HTML without breaking tags
+fn tg_truncate(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let length = args
+        .get("length")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| tera::Error::msg("tg_truncate filter expects a `length` argument"))?;
+    let (truncated, _) = truncate_html(&value_to_string(value), length as usize);
+    Ok(Value::String(truncated))
+}
+
+impl Templates {
+    fn with_templates(
+        templates: &[(&str, String)],
+        amount_format: &AmountFormat,
+    ) -> Result<Self, AppError> {
+        let mut tera = Tera::default();
+        tera.autoescape_on(vec![".html"]);
+        tera.set_escape_fn(escape_html);
+        tera.register_filter("currency", CurrencyFilter(amount_format.clone()));
+        tera.register_filter("usd", UsdFilter(amount_format.clone()));
+        tera.register_filter("duration", duration);
+        tera.register_filter("tg_escape", SafeFilter(tg_escape));
+        tera.register_filter("tg_truncate", SafeFilter(tg_truncate));
+        for (name, content) in templates {
+            tera.add_raw_template(name, content)?;
+        }
+        Ok(Templates { tera })
+    }
+
+    // Templates bundled with the binary
+    pub fn bundled() -> Self {
+        let templates: Vec<(&str, String)> = BUNDLED
+            .iter()
+            .map(|(name, content)| (*name, content.to_string()))
+            .collect();
+        Self::with_templates(&templates, &AmountFormat::default())
+            .expect("Bundled templates must be valid")
+    }
+
+    // Bundled templates overridden by the files set in the config
+    #[tracing::instrument(name = "load_templates", skip(settings))]
+    pub fn from_settings(settings: &Settings) -> Result<Self, AppError> {
+        let overrides = [
+            (TELEGRAM_JOB, settings.telegram.job_template.as_deref()),
+            (
+                TELEGRAM_DELIVERY,
+                settings.telegram.delivery_template.as_deref(),
+            ),
+            (X_JOB, settings.x.job_template.as_deref()),
+            (X_STATUS, settings.x.status_template.as_deref()),
+            (X_SUMMARY, settings.x.summary_template.as_deref()),
+            (
+                TELEGRAM_DIGEST,
+                settings.telegram.digest_template.as_deref(),
+            ),
+            (X_DIGEST, settings.x.digest_template.as_deref()),
+            (
+                TELEGRAM_ARBITRATOR,
+                settings.telegram.arbitrator_template.as_deref(),
+            ),
+            (X_ARBITRATOR, settings.x.arbitrator_template.as_deref()),
+        ];
+
+        let overrides: HashMap<&str, &Path> = overrides
+            .into_iter()
+            .filter_map(|(name, path)| Some((name, path?)))
+            .collect();
+
+        let mut templates = Vec::new();
+        for (name, bundled) in BUNDLED {
+            let content = match overrides.get(name) {
+                Some(path) => read_template(path)?,
+                None => bundled.to_string(),
+            };
+            templates.push((name, content));
+        }
+        Self::with_templates(&templates, &settings.number_format)
+    }
+
+    pub fn render(&self, name: &str, context: &Context) -> Result<String, AppError> {
+        let rendered = self.tera.render(name, context)?;
+        Ok(rendered.trim().to_string())
+    }
+}
+
+fn read_template(path: &Path) -> Result<String, AppError> {
+    tracing::info!("Loading template from {}", path.display());
+    std::fs::read_to_string(path)
+        .map_err(|e| AppError::Config(format!("Can't read template {}: {}", path.display(), e)))
+}
+
+fn base_context<T: Serialize>(notification: &T, job_id: &str) -> Result<Context, AppError> {
+    let mut context = Context::from_serialize(notification)?;
+    context.insert("job_url", &job_url(job_id));
+    Ok(context)
+}
+
+// Every JobNotification field plus the rendered description
+pub fn job_context(notification: &JobNotification) -> Result<Context, AppError> {
+    let mut context = base_context(notification, &notification.job_id)?;
+    context.insert(
+        "description_html",
+        &notification.description.to_telegram_html(),
+    );
+    context.insert("description_text", &notification.description.to_x_text());
+    context.insert("description_truncated", &false);
+    Ok(context)
+}
+
+// The job context plus `status` and `event`, used for the edited announcements
+pub fn status_context(notification: &JobStatusNotification) -> Result<Context, AppError> {
+    let mut context = job_context(&notification.job)?;
+    context.insert("status", &notification.status);
+    context.insert("event", &notification.event);
+    Ok(context)
+}
+
+// `count` new jobs of which the first `shown` are listed, `more` is the rest
+pub fn summary_context(jobs: &[JobNotification], shown: usize) -> Result<Context, AppError> {
+    let shown = shown.min(jobs.len());
+    let mut context = Context::new();
+    context.insert("count", &jobs.len());
+    context.insert("jobs", &jobs[..shown]);
+    context.insert("more", &(jobs.len() - shown));
+    context.insert("dashboard_url", DASHBOARD_URL);
+    Ok(context)
+}
+
+// Every Digest field plus `dashboard_url`
+pub fn digest_context(digest: &Digest) -> Result<Context, AppError> {
+    let mut context = Context::from_serialize(digest)?;
+    context.insert("dashboard_url", DASHBOARD_URL);
+    Ok(context)
+}
+
+// Every ArbitratorProfile field plus `dashboard_url`
+pub fn arbitrator_context(arbitrator: &ArbitratorProfile) -> Result<Context, AppError> {
+    let mut context = Context::from_serialize(arbitrator)?;
+    context.insert("dashboard_url", DASHBOARD_URL);
+    Ok(context)
+}
+
+// Every DeliveryNotification field plus the escaped preview
+pub fn delivery_context(notification: &DeliveryNotification) -> Result<Context, AppError> {
+    let mut context = base_context(notification, &notification.job_id)?;
+    let preview_html = notification
+        .preview
+        .as_deref()
+        .map(escape_html)
+        .unwrap_or_default();
+    context.insert("preview_html", &preview_html);
+    Ok(context)
+}
@@ -0,0 +1,179 @@
+use alloy::primitives::{address, Address, Bytes};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::{SolCall, SolValue};
+use alloy::transports::RpcError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::IERC20;
+
+// Sentinel used by many contracts for the chain native asset
+pub const NATIVE_ETH_SENTINEL: Address = address!("EeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+// Decimals assumed when a token doesn't implement `decimals()`
+const DEFAULT_DECIMALS: u8 = 18;
+
+// Known Arbitrum tokens, bundled so the common ones never hit the RPC
+const BUNDLED_TOKENS: &str = include_str!("data/arbitrum_tokens.json");
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TokenInfo {
+    pub address: Address,
+    pub symbol: String,
+    // Display name, e.g. "USD Coin"
+    pub name: String,
+    pub decimals: u8,
+    pub logo_url: Option<String>,
+}
+
+impl Default for TokenInfo {
+    fn default() -> Self {
+        TokenInfo {
+            address: Address::ZERO,
+            symbol: String::new(),
+            name: String::new(),
+            decimals: DEFAULT_DECIMALS,
+            logo_url: None,
+        }
+    }
+}
+
+impl TokenInfo {
+    pub fn native_eth(address: Address) -> Self {
+        TokenInfo {
+            address,
+            symbol: "ETH".to_string(),
+            name: "Ether".to_string(),
+            decimals: 18,
+            logo_url: None,
+        }
+    }
+}
+
+pub fn is_native(address: Address) -> bool {
+    address.is_zero() || address == NATIVE_ETH_SENTINEL
+}
+
+// Symbol of tokens without one, e.g. 0x1234…cdef
+pub fn short_address(address: &Address) -> String {
+    let address = address.to_string();
+    format!("{}…{}", &address[..6], &address[address.len() - 4..])
+}
+
+// Decode a `symbol()`/`name()` return value, either an ABI string or a bytes32 (e.g. MKR)
+pub fn decode_string_or_bytes32(data: &[u8]) -> Option<String> {
+    let decoded = if data.len() == 32 {
+        let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+        String::from_utf8(data[..end].to_vec()).ok()?
+    } else {
+        String::abi_decode(data, true).ok()?
+    };
+    let decoded = decoded.trim_matches('\0').trim().to_string();
+    (!decoded.is_empty()).then_some(decoded)
+}
+
+// Token metadata cache shared by every code path formatting amounts
+#[derive(Debug, Default)]
+pub struct TokenRegistry {
+    tokens: RwLock<HashMap<Address, TokenInfo>>,
+}
+
+impl TokenRegistry {
+    // Registry seeded with the bundled list of known Arbitrum tokens
+    pub fn bundled() -> Self {
+        let tokens: Vec<TokenInfo> =
+            serde_json::from_str(BUNDLED_TOKENS).expect("Bundled token list must be valid");
+        let registry = TokenRegistry::default();
+        for token in tokens {
+            registry.insert(token);
+        }
+        registry
+    }
+
+    pub fn get(&self, address: Address) -> Option<TokenInfo> {
+        if is_native(address) {
+            return Some(TokenInfo::native_eth(address));
+        }
+        self.tokens
+            .read()
+            .expect("Token registry lock poisoned")
+            .get(&address)
+            .cloned()
+    }
+
+    pub fn insert(&self, token: TokenInfo) {
+        self.tokens
+            .write()
+            .expect("Token registry lock poisoned")
+            .insert(token.address, token);
+    }
+
+    // Cached metadata, fetched from the token contract on the first lookup
+    // Tokens that don't answer get a placeholder, cached too so they aren't asked again
+    // Transport errors aren't the token's fault, their placeholder is used once and the token read again
+    #[tracing::instrument(name = "resolve_token", skip(self, provider))]
+    pub async fn resolve<P: Provider + Clone>(&self, provider: P, address: Address) -> TokenInfo {
+        if let Some(token) = self.get(address) {
+            return token;
+        }
+
+        // Raw calls so bytes32 symbols and missing functions don't fail the lookup,
+        // cache misses are rare enough not to need a multicall
+        // A revert is an answer, the token lacks the function
+        let mut failed = None;
+        let mut raw_call = async |input: Vec<u8>| {
+            let tx = TransactionRequest::default()
+                .to(address)
+                .input(Bytes::from(input).into());
+            match provider.call(tx).await {
+                Ok(data) => Some(data),
+                Err(RpcError::ErrorResp(_)) => None,
+                Err(e) => {
+                    failed = Some(e);
+                    None
+                }
+            }
+        };
+
+        let symbol = raw_call(IERC20::symbolCall {}.abi_encode())
+            .await
+            .and_then(|data| decode_string_or_bytes32(&data));
+        let name = raw_call(IERC20::nameCall {}.abi_encode())
+            .await
+            .and_then(|data| decode_string_or_bytes32(&data));
+        let decimals = raw_call(IERC20::decimalsCall {}.abi_encode())
+            .await
+            .and_then(|data| IERC20::decimalsCall::abi_decode_returns(&data, false).ok())
+            .map(|decimals| decimals._0);
+
+        let symbol = symbol.unwrap_or_else(|| {
+            tracing::warn!("Token {} has no readable symbol", address);
+            short_address(&address)
+        });
+        let decimals = decimals.unwrap_or_else(|| {
+            tracing::warn!(
+                "Token {} has no decimals(), assuming {}",
+                address,
+                DEFAULT_DECIMALS
+            );
+            DEFAULT_DECIMALS
+        });
+
+        let token = TokenInfo {
+            address,
+            name: name.unwrap_or_else(|| symbol.clone()),
+            symbol,
+            decimals,
+            logo_url: None,
+        };
+        match failed {
+            Some(e) => tracing::warn!("Can't read token {}, not caching it: {}", address, e),
+            None => {
+                tracing::debug!("Caching token metadata: {:?}", token);
+                self.insert(token.clone());
+            }
+        }
+        token
+    }
+}
@@ -4,7 +4,15 @@ use cid::multihash::Multihash;
 use cid::Cid;
 use eyre::Result;
 use reqwest::ClientBuilder;
-use std::{env, time::Duration};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Duration,
+};
+
+use crate::error::AppError;
 
 // Placeholder decryption for UTF-8 data
 #[tracing::instrument(name = "decrypt_utf8_data", skip(_session_key))]
@@ -92,3 +100,83 @@ pub async fn get_from_ipfs(content_hash: &str, session_key: &str) -> Result<Stri
     tracing::info!("Cleaned job description: {}", cleaned);
     Ok(cleaned)
 }
+
+// Read a JSON file, the default value when it doesn't exist yet
+// `what` names the file in the error, e.g. "posts"
+pub fn load_json_or_default<T: DeserializeOwned + Default>(
+    path: &Path,
+    what: &str,
+) -> Result<T, AppError> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => Ok(serde_json::from_str(&raw)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(AppError::Config(format!(
+            "Can't read {} {}: {}",
+            what,
+            path.display(),
+            e
+        ))),
+    }
+}
+
+// Write a JSON file through a temporary file so a crash never leaves it truncated
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, serde_json::to_string_pretty(value)?)?;
+    std::fs::rename(&tmp_path, path)
+}
+
+// Value kept in memory and saved to a JSON file, only in memory without a path
+#[derive(Debug, Default)]
+pub struct JsonStore<T> {
+    path: Option<PathBuf>,
+    // What the file holds, for the error messages
+    what: &'static str,
+    value: RwLock<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default> JsonStore<T> {
+    // Load the value saved at `path`, starting from the default when the file doesn't exist
+    pub fn load(path: &Path, what: &'static str) -> Result<Self, AppError> {
+        Ok(JsonStore {
+            path: Some(path.to_path_buf()),
+            what,
+            value: RwLock::new(load_json_or_default(path, what)?),
+        })
+    }
+
+    pub fn read<R>(&self, read: impl FnOnce(&T) -> R) -> R {
+        read(&self.value.read().expect("JSON store lock poisoned"))
+    }
+
+    // Change the value in memory only, `save` writes it
+    pub fn change<R>(&self, change: impl FnOnce(&mut T) -> R) -> R {
+        change(&mut self.value.write().expect("JSON store lock poisoned"))
+    }
+
+    // Change the value and save it, the change is kept in memory when saving fails
+    pub fn update<R>(&self, change: impl FnOnce(&mut T) -> R) -> Result<R, AppError> {
+        let mut value = self.value.write().expect("JSON store lock poisoned");
+        let result = change(&mut value);
+        self.write(&value)?;
+        Ok(result)
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        self.write(&self.value.read().expect("JSON store lock poisoned"))
+    }
+
+    fn write(&self, value: &T) -> Result<(), AppError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        save_json(path, value).map_err(|e| {
+            AppError::Config(format!(
+                "Can't save {} {}: {}",
+                self.what,
+                path.display(),
+                e
+            ))
+        })
+    }
+}
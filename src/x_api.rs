@@ -1,147 +1,329 @@
 use std::env;
-use std::fs::File;
-use std::io::Read;
-
-use reqwest::Body;
-use std::path::Path;
-
-use crate::{error::AppError, JobNotification};
-use reqwest::Client;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
-use twitter_api_v1::endpoints::tweets::manage_tweets::create_tweet;
-use twitter_api_v1::endpoints::EndpointRet;
-use twitter_api_v1::objects::MediaCategory;
-use twitter_api_v1::TokenSecrets;
 
-#[tracing::instrument(name = "send_x_notification", skip(client, token_secrets))]
+use crate::{
+    cards::CardRenderer,
+    config::{Settings, XSettings, XStatusPost},
+    error::AppError,
+    events::JobStatusNotification,
+    job_state::JobState,
+    posts::PostStore,
+    templates::{
+        arbitrator_context, digest_context, job_context, status_context, summary_context,
+        Templates, X_ARBITRATOR, X_DIGEST, X_JOB, X_STATUS, X_SUMMARY,
+    },
+    x_client::{now_secs, NewTweet, TweetMedia, TweetReply, XClient},
+    x_format::{split_thread, weighted_len, TWEET_MAX_WEIGHT},
+    x_scheduler::{XPost, XScheduler},
+    JobNotification, Notification,
+};
+
+#[tracing::instrument(name = "send_x_notification", skip(x, templates))]
 async fn send_x_notification(
-    client: Client,
-    token_secrets: TokenSecrets,
+    x: &XClient,
+    templates: &Templates,
     notification: &JobNotification,
-    media_id: u64,
+    media_id: Option<String>,
 ) -> Result<u64, AppError> {
-    let message = format!(
-        "Title: {}\nReward: {} ${}\nDetails: https://staging.effectiveacceleration.ai/dashboard/jobs/{}",
-        notification.title,
-        notification.amount,
-        notification.symbol,
-        notification.job_id
-    );
+    let message = templates.render(X_JOB, &job_context(notification)?)?;
+    let tweet_id = x
+        .create_tweet(&NewTweet {
+            text: message,
+            media: media_id.map(|media_id| TweetMedia {
+                media_ids: vec![media_id],
+            }),
+            ..Default::default()
+        })
+        .await?;
+    tracing::info!("Posted tweet {} for job {}", tweet_id, notification.job_id);
+    Ok(tweet_id)
+}
 
-    let ret = create_tweet(
-        &token_secrets,
-        client,
-        Some(&message),
-        Some(vec![media_id]),
-        None,
-    )
-    .await?;
+// Reply to or quote the announcement of a job with its new status,
+// or delete the announcement once the job is closed
+// Returns the number of posts created
+#[tracing::instrument(name = "send_x_status_update", skip(x, templates, posts, x_settings))]
+async fn send_x_status_update(
+    x: &XClient,
+    templates: &Templates,
+    notification: &JobStatusNotification,
+    posts: &PostStore,
+    x_settings: &XSettings,
+) -> Result<usize, AppError> {
+    let job_id = &notification.job.job_id;
+    let Some(tweet_id) = posts.get(job_id).x else {
+        tracing::debug!("No X post to update for job {}", job_id);
+        return Ok(0);
+    };
 
-    match ret {
-        EndpointRet::Ok(ok_json) => {
-            tracing::info!("create_tweet:{ok_json:?}");
-            Ok(ok_json.id)
+    if x_settings.delete_closed && notification.job.job.state == JobState::Closed {
+        // Replies first, so the thread never hangs without its announcement
+        for reply_id in posts.get(job_id).x_thread.iter().rev() {
+            if let Err(e) = x.delete_tweet(*reply_id).await {
+                tracing::error!(
+                    "Failed to delete reply {} of job {}: {}",
+                    reply_id,
+                    job_id,
+                    e
+                );
+            }
         }
-        _x => Err(AppError::XApi(format!("{:?}", _x))),
+        posts.set_x_thread(job_id, Vec::new());
+        x.delete_tweet(tweet_id).await?;
+        tracing::info!("Deleted tweet {}", tweet_id);
+        posts.set_x(job_id, None);
+        return Ok(0);
     }
-}
 
-#[tracing::instrument(name = "x_upload_image", skip_all)]
-pub async fn x_upload_image(
-    token_secrets: &TokenSecrets,
-    client: Client,
-    media_category: twitter_api_v1::objects::MediaCategory,
-    stream: reqwest::Body,
-    stream_length: Option<u64>,
-    file_name: Option<String>,
-) -> Result<u64, AppError> {
-    let ret = twitter_api_v1::endpoints::media::upload_media::upload_image(
-        token_secrets,
-        client,
-        media_category,
-        stream,
-        stream_length,
-        file_name,
-    )
-    .await?;
+    let text = templates.render(X_STATUS, &status_context(notification)?)?;
+    let tweet = match x_settings.status_post {
+        XStatusPost::Reply => NewTweet {
+            text,
+            reply: Some(TweetReply {
+                in_reply_to_tweet_id: tweet_id.to_string(),
+            }),
+            ..Default::default()
+        },
+        XStatusPost::Quote => NewTweet {
+            text,
+            quote_tweet_id: Some(tweet_id.to_string()),
+            ..Default::default()
+        },
+        XStatusPost::None => return Ok(0),
+    };
+    let status_id = x.create_tweet(&tweet).await?;
+    tracing::info!("Posted status update {} for job {}", status_id, job_id);
+    Ok(1)
+}
 
-    match ret {
-        EndpointRet::Ok(ok_json) => {
-            println!("upload_media:{ok_json:?}");
-            Ok(ok_json.media_id)
+// "N new jobs" post listing as many jobs as fit in one post
+pub fn render_summary(templates: &Templates, jobs: &[JobNotification]) -> Result<String, AppError> {
+    for shown in (1..=jobs.len()).rev() {
+        let text = templates.render(X_SUMMARY, &summary_context(jobs, shown)?)?;
+        if weighted_len(&text) <= TWEET_MAX_WEIGHT {
+            return Ok(text);
         }
-        _x => Err(AppError::XApi(format!("{:?}", _x))),
     }
+    templates.render(X_SUMMARY, &summary_context(jobs, 0)?)
 }
 
-#[tracing::instrument(name = "x_worker", skip(rx))]
-pub async fn x_worker(mut rx: mpsc::Receiver<JobNotification>) -> Result<(), AppError> {
-    let consumer_key = env::var("X_API_KEY").expect("X_API_KEY not found in .env file");
-    let consumer_secret =
-        env::var("X_API_KEY_SECRET").expect("X_API_KEY_SECRET not found in .env file");
-    let access_token = env::var("X_ACCESS_TOKEN").expect("ACCESS_TOKEN not found in .env file");
-    let access_token_secret =
-        env::var("X_ACCESS_TOKEN_SECRET").expect("ACCESS_TOKEN_SECRET not found in .env file");
+// Post the description as numbered replies chained under the announcement
+// Returns the ids of the replies posted before any error
+#[tracing::instrument(name = "send_x_thread", skip(x, notification), fields(job_id = %notification.job_id))]
+pub async fn send_x_thread(
+    x: &XClient,
+    notification: &JobNotification,
+    tweet_id: u64,
+    max_posts: usize,
+) -> Vec<u64> {
+    let mut reply_ids = Vec::new();
+    let mut previous = tweet_id;
+    for text in split_thread(&notification.description.to_x_text(), max_posts) {
+        let reply = NewTweet {
+            text,
+            reply: Some(TweetReply {
+                in_reply_to_tweet_id: previous.to_string(),
+            }),
+            ..Default::default()
+        };
+        match x.create_tweet(&reply).await {
+            Ok(reply_id) => {
+                reply_ids.push(reply_id);
+                previous = reply_id;
+            }
+            Err(e) => {
+                tracing::error!("Failed to post the description thread: {}", e);
+                break;
+            }
+        }
+    }
+    reply_ids
+}
 
-    let token_secrets = TokenSecrets::new(
-        consumer_key,
-        consumer_secret,
-        access_token,
-        access_token_secret,
-    );
-    let client = reqwest::Client::builder()
-        .connection_verbose(env::var("RUST_LOG").map(|x| x.starts_with("trace")) == Ok(true))
-        .danger_accept_invalid_certs(true)
-        .build()?;
+// Render the card of a job and upload it
+#[tracing::instrument(name = "x_upload_card", skip_all, fields(job_id = %job.job_id))]
+async fn upload_card(
+    x: &XClient,
+    cards: &CardRenderer,
+    job: &JobNotification,
+) -> Result<String, AppError> {
+    let card = cards.render(job).await?;
+    x.upload_image(card, &format!("job_{}.png", job.job_id), "image/png")
+        .await
+}
 
-    // Upload image to X and fetch the media ID
-    // Media ID are persistent, so no need to re-upload it every time
-    // Path to the image file
+// Media ids expire after a day, so the static image is uploaded with every post
+#[tracing::instrument(name = "x_upload_static_image", skip_all)]
+async fn upload_static_image(x: &XClient) -> Result<String, AppError> {
     // TODO: Update media file
     let image_path = "./media/tweet_img.png";
+    let image = std::fs::read(image_path)
+        .map_err(|e| AppError::XApi(format!("Can't read image {image_path}, error: {e}")))?;
+    x.upload_image(image, "tweet_img.png", "image/png").await
+}
 
-    let mut file = File::open(image_path)
-        .map_err(|e| AppError::XApi(format!("Can't find image, error: {e}")))?;
+// Everything the X worker needs to send a scheduled post
+struct XSink<'a> {
+    x: &'a XClient,
+    templates: &'a Templates,
+    cards: Option<&'a CardRenderer>,
+    posts: &'a PostStore,
+    x_settings: &'a XSettings,
+}
 
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .expect("Failed to read image file");
+impl XSink<'_> {
+    // Returns the number of posts created, `budget` caps the description thread
+    async fn send(&self, post: &XPost, budget: Option<usize>) -> Result<usize, AppError> {
+        match post {
+            XPost::Job(job) => self.send_job(job, budget).await,
+            XPost::Status(status) => {
+                send_x_status_update(self.x, self.templates, status, self.posts, self.x_settings)
+                    .await
+            }
+            XPost::Summary(jobs) => {
+                let text = render_summary(self.templates, jobs)?;
+                let tweet_id = self
+                    .x
+                    .create_tweet(&NewTweet {
+                        text,
+                        ..Default::default()
+                    })
+                    .await?;
+                tracing::info!("Posted summary {} of {} jobs", tweet_id, jobs.len());
+                Ok(1)
+            }
+            XPost::Digest(digest) => {
+                let text = self.templates.render(X_DIGEST, &digest_context(digest)?)?;
+                // Cut with … when a template override is too long
+                let text = split_thread(&text, 1).pop().unwrap_or_default();
+                let tweet_id = self
+                    .x
+                    .create_tweet(&NewTweet {
+                        text,
+                        ..Default::default()
+                    })
+                    .await?;
+                tracing::info!("Posted {:?} digest {}", digest.period, tweet_id);
+                Ok(1)
+            }
+            XPost::Arbitrator(arbitrator) => {
+                let text = self
+                    .templates
+                    .render(X_ARBITRATOR, &arbitrator_context(arbitrator)?)?;
+                let text = split_thread(&text, 1).pop().unwrap_or_default();
+                let tweet_id = self
+                    .x
+                    .create_tweet(&NewTweet {
+                        text,
+                        ..Default::default()
+                    })
+                    .await?;
+                tracing::info!(
+                    "Announced arbitrator {} in {}",
+                    arbitrator.address,
+                    tweet_id
+                );
+                Ok(1)
+            }
+        }
+    }
 
-    // Get the file size for stream_length
-    let file_size = file
-        .metadata()
-        .map_err(|e| AppError::XApi(format!("Error checking the file size: {e}")))?
-        .len();
+    async fn send_job(
+        &self,
+        job: &JobNotification,
+        budget: Option<usize>,
+    ) -> Result<usize, AppError> {
+        let mut media_id = match self.cards {
+            Some(cards) => match upload_card(self.x, cards, job).await {
+                Ok(media_id) => Some(media_id),
+                Err(e) => {
+                    tracing::error!("Failed to upload card, using the static image: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        if media_id.is_none() {
+            media_id = upload_static_image(self.x)
+                .await
+                .map_err(|e| tracing::error!("Failed to upload the static image: {}", e))
+                .ok();
+        }
+        let tweet_id = send_x_notification(self.x, self.templates, job, media_id).await?;
+        self.posts.set_x(&job.job_id, Some(tweet_id));
+        if !self.x_settings.thread.enabled {
+            return Ok(1);
+        }
+        let max_posts = budget.map_or(self.x_settings.thread.max_posts, |budget| {
+            self.x_settings
+                .thread
+                .max_posts
+                .min(budget.saturating_sub(1))
+        });
+        let reply_ids = send_x_thread(self.x, job, tweet_id, max_posts).await;
+        let count = 1 + reply_ids.len();
+        self.posts.set_x_thread(&job.job_id, reply_ids);
+        Ok(count)
+    }
+}
 
-    let media_category = MediaCategory::TweetImage;
-    let stream = Body::from(buffer);
-    let file_name = Some(
-        Path::new(image_path)
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned(),
-    );
-    let media_id = x_upload_image(
-        &token_secrets,
-        client.clone(),
-        media_category,
-        stream,
-        Some(file_size),
-        file_name,
-    )
-    .await?;
+// Notifications are queued and sent when the daily budget and the X rate limits allow it
+#[tracing::instrument(name = "x_worker", skip(rx, settings, posts))]
+pub async fn x_worker(
+    mut rx: mpsc::Receiver<Notification>,
+    settings: Settings,
+    posts: Arc<PostStore>,
+) -> Result<(), AppError> {
+    let templates = Templates::from_settings(&settings)?;
+    let client = reqwest::Client::builder()
+        .connection_verbose(env::var("RUST_LOG").map(|x| x.starts_with("trace")) == Ok(true))
+        .build()?;
+    let x = XClient::from_settings(&settings, client.clone())?;
 
-    while let Some(notification) = rx.recv().await {
-        if let Err(e) = send_x_notification(
-            client.clone(),
-            token_secrets.clone(),
-            &notification,
-            media_id,
-        )
-        .await
-        {
-            tracing::error!("Failed to send X notification: {}", e);
+    let cards = settings
+        .cards
+        .enabled
+        .then(|| CardRenderer::new(client.clone(), settings.number_format.clone()));
+    let sink = XSink {
+        x: &x,
+        templates: &templates,
+        cards: cards.as_ref(),
+        posts: &posts,
+        x_settings: &settings.x,
+    };
+
+    let mut scheduler = XScheduler::new(settings.x.schedule.clone());
+    let mut receiving = true;
+    // Queued posts are still sent once the channel is closed
+    while receiving || !scheduler.is_empty() {
+        let now = now_secs();
+        let wait = (!scheduler.is_empty())
+            .then(|| scheduler.ready_at(now, x.posting_blocked_until(now)) - now);
+        if let Some(wait) = wait.filter(|wait| *wait > 0) {
+            tracing::info!("{} X posts queued, next one in {}s", scheduler.len(), wait);
+        }
+        // Received notifications are queued first so bursts can be collapsed
+        tokio::select! {
+            biased;
+            received = rx.recv(), if receiving => match received {
+                Some(notification) => scheduler.push(notification),
+                None => receiving = false,
+            },
+            _ = tokio::time::sleep(Duration::from_secs(wait.unwrap_or_default())), if wait.is_some() => {
+                let Some(post) = scheduler.next_post() else {
+                    continue;
+                };
+                match sink.send(&post, scheduler.remaining_budget(now_secs())).await {
+                    Ok(count) => scheduler.record_posts(count, now_secs()),
+                    Err(AppError::XRateLimited(reset)) => {
+                        tracing::warn!("X rate limit reached until {}, post queued again", reset);
+                        scheduler.requeue(post);
+                    }
+                    Err(e) => tracing::error!("Failed to send X post: {}", e),
+                }
+            }
         }
     }
     // TODO: Update return with post_id when the backend/DB is ready
@@ -0,0 +1,322 @@
+use reqwest::header::HeaderMap;
+use reqwest::{multipart, Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    config::{Settings, XAuthMethod},
+    error::AppError,
+    x_oauth::{OAuth1Credentials, OAuth2App, OAuth2Session},
+};
+
+// API v2 endpoint, overridden with `x.api_url` (e.g. a local mock server)
+pub const DEFAULT_API_URL: &str = "https://api.x.com";
+// Rate limit windows besides the per endpoint ones, see `XClient::rate_limit`
+pub const CREATE_TWEET_ENDPOINT: &str = "POST /2/tweets";
+pub const USER_24HOUR_LIMIT: &str = "user-24hour";
+pub const APP_24HOUR_LIMIT: &str = "app-24hour";
+// Wait used on a 429 without rate limit headers
+const DEFAULT_RATE_LIMIT_WAIT_SECS: u64 = 15 * 60;
+
+pub enum XAuth {
+    OAuth1(OAuth1Credentials),
+    OAuth2(OAuth2Session),
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct NewTweet {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media: Option<TweetMedia>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<TweetReply>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote_tweet_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TweetMedia {
+    pub media_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TweetReply {
+    pub in_reply_to_tweet_id: String,
+}
+
+// Rate limit window reported in the x-rate-limit-* headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    // Unix seconds
+    pub reset: u64,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &HeaderMap, prefix: &str) -> Option<Self> {
+        let value = |name: &str| -> Option<u64> {
+            headers
+                .get(format!("{}-{}", prefix, name))?
+                .to_str()
+                .ok()?
+                .parse()
+                .ok()
+        };
+        Some(RateLimit {
+            limit: value("limit").unwrap_or_default(),
+            remaining: value("remaining")?,
+            reset: value("reset")?,
+        })
+    }
+
+    fn exhausted(&self, now: u64) -> bool {
+        self.remaining == 0 && self.reset > now
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+// Every v2 response is wrapped in {"data": ..}
+#[derive(Deserialize)]
+struct DataResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct CreatedTweet {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct UploadedMedia {
+    id: String,
+}
+
+// Minimal API v2 client posting as one account
+pub struct XClient {
+    client: Client,
+    base_url: String,
+    auth: XAuth,
+    // Last windows seen per endpoint (e.g. "POST /2/tweets") and for the 24 hour post limits
+    rate_limits: RwLock<HashMap<String, RateLimit>>,
+}
+
+impl XClient {
+    pub fn new(client: Client, base_url: &str, auth: XAuth) -> Self {
+        XClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth,
+            rate_limits: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // Credentials of `x.auth` read from the .env file
+    pub fn from_settings(settings: &Settings, client: Client) -> Result<Self, AppError> {
+        let auth = match settings.x.auth {
+            XAuthMethod::OAuth1 => XAuth::OAuth1(OAuth1Credentials {
+                consumer_key: env::var("X_API_KEY").expect("X_API_KEY not found in .env file"),
+                consumer_secret: env::var("X_API_KEY_SECRET")
+                    .expect("X_API_KEY_SECRET not found in .env file"),
+                access_token: env::var("X_ACCESS_TOKEN")
+                    .expect("X_ACCESS_TOKEN not found in .env file"),
+                access_token_secret: env::var("X_ACCESS_TOKEN_SECRET")
+                    .expect("X_ACCESS_TOKEN_SECRET not found in .env file"),
+            }),
+            XAuthMethod::OAuth2 => XAuth::OAuth2(OAuth2Session::load(
+                oauth2_app(settings, client.clone()),
+                &settings.x.oauth2_token_path(),
+            )?),
+        };
+        Ok(XClient::new(client, settings.x.api_url(), auth))
+    }
+
+    pub fn rate_limit(&self, key: &str) -> Option<RateLimit> {
+        self.rate_limits
+            .read()
+            .expect("rate limits lock poisoned")
+            .get(key)
+            .copied()
+    }
+
+    // Unix time posting is possible again, None while no post window is exhausted
+    pub fn posting_blocked_until(&self, now: u64) -> Option<u64> {
+        [CREATE_TWEET_ENDPOINT, USER_24HOUR_LIMIT, APP_24HOUR_LIMIT]
+            .iter()
+            .filter_map(|key| self.rate_limit(key))
+            .filter(|limit| limit.exhausted(now))
+            .map(|limit| limit.reset)
+            .max()
+    }
+
+    fn record_rate_limits(&self, endpoint: &str, headers: &HeaderMap) {
+        let mut rate_limits = self.rate_limits.write().expect("rate limits lock poisoned");
+        for (key, prefix) in [
+            (endpoint, "x-rate-limit"),
+            (USER_24HOUR_LIMIT, "x-user-limit-24hour"),
+            (APP_24HOUR_LIMIT, "x-app-limit-24hour"),
+        ] {
+            if let Some(limit) = RateLimit::from_headers(headers, prefix) {
+                rate_limits.insert(key.to_string(), limit);
+            }
+        }
+    }
+
+    // Reset of the windows exhausted by a 429, without headers the endpoint is blocked
+    // for DEFAULT_RATE_LIMIT_WAIT_SECS
+    fn rate_limited_until(&self, endpoint: &str) -> u64 {
+        let now = now_secs();
+        let reset = [endpoint, USER_24HOUR_LIMIT, APP_24HOUR_LIMIT]
+            .iter()
+            .filter_map(|key| self.rate_limit(key))
+            .filter(|limit| limit.exhausted(now))
+            .map(|limit| limit.reset)
+            .max();
+        reset.unwrap_or_else(|| {
+            let reset = now + DEFAULT_RATE_LIMIT_WAIT_SECS;
+            self.rate_limits
+                .write()
+                .expect("rate limits lock poisoned")
+                .insert(
+                    endpoint.to_string(),
+                    RateLimit {
+                        limit: 0,
+                        remaining: 0,
+                        reset,
+                    },
+                );
+            reset
+        })
+    }
+
+    // Send a request built by `build`, retried once with a refreshed OAuth 2.0 token on 401
+    // `endpoint` keys the rate limit window, e.g. "DELETE /2/tweets/:id"
+    async fn send<F>(
+        &self,
+        method: Method,
+        path: &str,
+        endpoint: &str,
+        build: F,
+    ) -> Result<Response, AppError>
+    where
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        let url = format!("{}{}", self.base_url, path);
+        let mut refreshed = false;
+        loop {
+            let authorization = match &self.auth {
+                XAuth::OAuth1(credentials) => credentials.authorization(method.as_str(), &url, &[]),
+                XAuth::OAuth2(session) => {
+                    format!("Bearer {}", session.access_token(refreshed).await?)
+                }
+            };
+            let request = self
+                .client
+                .request(method.clone(), &url)
+                .header(reqwest::header::AUTHORIZATION, authorization);
+            let response = build(request).send().await?;
+            self.record_rate_limits(endpoint, response.headers());
+            let resp_status = response.status();
+            if resp_status == StatusCode::TOO_MANY_REQUESTS {
+                return Err(AppError::XRateLimited(self.rate_limited_until(endpoint)));
+            }
+            if resp_status == StatusCode::UNAUTHORIZED
+                && !refreshed
+                && matches!(self.auth, XAuth::OAuth2(_))
+            {
+                refreshed = true;
+                continue;
+            }
+            if !resp_status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                return Err(AppError::XApi(format!(
+                    "{} {}: status: {}, text: {}",
+                    method, path, resp_status, error_text
+                )));
+            }
+            return Ok(response);
+        }
+    }
+
+    // Returns the id of the new post
+    #[tracing::instrument(name = "x_create_tweet", skip(self))]
+    pub async fn create_tweet(&self, tweet: &NewTweet) -> Result<u64, AppError> {
+        let response = self
+            .send(
+                Method::POST,
+                "/2/tweets",
+                CREATE_TWEET_ENDPOINT,
+                |request| request.json(tweet),
+            )
+            .await?;
+        let created: DataResponse<CreatedTweet> = response.json().await?;
+        created
+            .data
+            .id
+            .parse()
+            .map_err(|e| AppError::XApi(format!("Invalid tweet id {}: {}", created.data.id, e)))
+    }
+
+    #[tracing::instrument(name = "x_delete_tweet", skip(self))]
+    pub async fn delete_tweet(&self, tweet_id: u64) -> Result<(), AppError> {
+        self.send(
+            Method::DELETE,
+            &format!("/2/tweets/{}", tweet_id),
+            "DELETE /2/tweets/:id",
+            |request| request,
+        )
+        .await?;
+        Ok(())
+    }
+
+    // Upload an image with media upload v2, the id can be attached for 24 hours
+    #[tracing::instrument(name = "x_upload_media", skip(self, bytes))]
+    pub async fn upload_image(
+        &self,
+        bytes: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+    ) -> Result<String, AppError> {
+        // Multipart forms can't be cloned, the form is rebuilt for a retry
+        let media = || {
+            multipart::Part::bytes(bytes.clone())
+                .file_name(file_name.to_string())
+                .mime_str(mime)
+        };
+        media()?;
+        let response = self
+            .send(
+                Method::POST,
+                "/2/media/upload",
+                "POST /2/media/upload",
+                |request| {
+                    let form = multipart::Form::new()
+                        .text("media_category", "tweet_image")
+                        .part("media", media().expect("mime was already parsed"));
+                    request.multipart(form)
+                },
+            )
+            .await?;
+        let uploaded: DataResponse<UploadedMedia> = response.json().await?;
+        Ok(uploaded.data.id)
+    }
+}
+
+// OAuth 2.0 app of the .env file, X_CLIENT_SECRET is only set for confidential clients
+pub fn oauth2_app(settings: &Settings, client: Client) -> OAuth2App {
+    OAuth2App {
+        client,
+        token_url: format!("{}/2/oauth2/token", settings.x.api_url()),
+        client_id: env::var("X_CLIENT_ID").expect("X_CLIENT_ID not found in .env file"),
+        client_secret: env::var("X_CLIENT_SECRET").ok(),
+    }
+}
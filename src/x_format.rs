@@ -0,0 +1,146 @@
+// X post limits, counted the way X does: most Latin, Greek, Cyrillic and punctuation
+// characters weigh 1, everything else (CJK, emoji) 2 and every URL 23
+pub const TWEET_MAX_WEIGHT: usize = 280;
+pub const URL_WEIGHT: usize = 23;
+
+// Characters that can't end a URL, kept out of the 23 weighted characters
+const URL_TRAILING: &[char] = &['.', ',', ';', ':', '!', '?', ')', '"', '\''];
+
+fn char_weight(c: char) -> usize {
+    match c as u32 {
+        0..=4351 | 8192..=8205 | 8208..=8223 | 8242..=8247 => 1,
+        _ => 2,
+    }
+}
+
+// Byte offset of the URL in a word, e.g. after an opening parenthesis
+fn url_start(word: &str) -> Option<usize> {
+    word.find("https://").or_else(|| word.find("http://"))
+}
+
+// Weight of a text without URLs, emoji sequences (ZWJ, skin tones) count once
+fn text_weight(text: &str) -> usize {
+    let mut weight = 0;
+    let mut joined = false;
+    for c in text.chars() {
+        let modifier = matches!(c as u32, 0xFE0F | 0x1F3FB..=0x1F3FF);
+        if c == '\u{200D}' {
+            joined = true;
+        } else if joined || modifier {
+            joined = false;
+        } else {
+            weight += char_weight(c);
+        }
+    }
+    weight
+}
+
+// Length of a post as counted by X
+pub fn weighted_len(text: &str) -> usize {
+    text.split_inclusive(char::is_whitespace)
+        .map(|piece| {
+            let word = piece.trim_end();
+            let Some(start) = url_start(word) else {
+                return text_weight(piece);
+            };
+            let url_end = word.trim_end_matches(URL_TRAILING).len().max(start);
+            text_weight(&piece[..start]) + URL_WEIGHT + text_weight(&piece[url_end..])
+        })
+        .sum()
+}
+
+// Cut `word` into pieces of at most `budget`, URLs are never cut
+fn split_word(word: &str, budget: usize) -> Vec<String> {
+    if url_start(word).is_some() || weighted_len(word) <= budget {
+        return vec![word.to_string()];
+    }
+    let mut pieces = Vec::new();
+    let mut piece = String::new();
+    for c in word.chars() {
+        piece.push(c);
+        if weighted_len(&piece) > budget {
+            piece.pop();
+            pieces.push(std::mem::replace(&mut piece, c.to_string()));
+        }
+    }
+    pieces.push(piece);
+    pieces
+}
+
+// Fill posts of at most `budget` with the words of `text`, line breaks are kept
+fn fill_posts(text: &str, budget: usize) -> Vec<String> {
+    let mut posts = Vec::new();
+    let mut post = String::new();
+    for (i, line) in text.lines().enumerate() {
+        for (j, word) in line.split_whitespace().enumerate() {
+            let separator = match (i, j) {
+                (0, 0) => "",
+                (_, 0) => "\n",
+                _ => " ",
+            };
+            for piece in split_word(word, budget) {
+                let candidate = if post.is_empty() {
+                    piece.clone()
+                } else {
+                    format!("{}{}{}", post, separator, piece)
+                };
+                if weighted_len(&candidate) <= budget {
+                    post = candidate;
+                } else {
+                    posts.push(std::mem::replace(&mut post, piece));
+                }
+            }
+        }
+    }
+    if !post.is_empty() {
+        posts.push(post);
+    }
+    posts
+}
+
+fn numbering(index: usize, count: usize) -> String {
+    format!("\n\n{}/{}", index, count)
+}
+
+// Split a text into numbered posts fitting TWEET_MAX_WEIGHT
+// Past `max_posts` the last post is cut with …
+pub fn split_thread(text: &str, max_posts: usize) -> Vec<String> {
+    if max_posts == 0 || text.trim().is_empty() {
+        return Vec::new();
+    }
+    if weighted_len(text.trim()) <= TWEET_MAX_WEIGHT {
+        return vec![text.trim().to_string()];
+    }
+
+    // The room left by the numbering depends on the number of posts
+    let mut count = 2;
+    let mut posts = loop {
+        let budget = TWEET_MAX_WEIGHT - weighted_len(&numbering(count, count));
+        let posts = fill_posts(text, budget);
+        let needed = posts.len().min(max_posts);
+        if needed.to_string().len() <= count.to_string().len() {
+            break posts;
+        }
+        count = needed;
+    };
+
+    if posts.len() > max_posts {
+        posts.truncate(max_posts);
+        let budget = TWEET_MAX_WEIGHT - weighted_len(&numbering(max_posts, max_posts));
+        let last = posts.last_mut().expect("max_posts is not 0");
+        while !last.is_empty() && weighted_len(&format!("{}…", last)) > budget {
+            last.pop();
+        }
+        *last = format!("{}…", last.trim_end());
+    }
+
+    let count = posts.len();
+    if count == 1 {
+        return posts;
+    }
+    posts
+        .into_iter()
+        .enumerate()
+        .map(|(i, post)| format!("{}{}", post, numbering(i + 1, count)))
+        .collect()
+}
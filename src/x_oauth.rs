@@ -0,0 +1,319 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rand::distributions::{Alphanumeric, DistString};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+use crate::{error::AppError, utils::save_json};
+
+pub const AUTHORIZE_URL: &str = "https://x.com/i/oauth2/authorize";
+// Posting needs tweet.write and media.write, offline.access gives a refresh token
+pub const SCOPES: &[&str] = &[
+    "tweet.read",
+    "tweet.write",
+    "users.read",
+    "media.write",
+    "offline.access",
+];
+// Refresh access tokens this long before they expire
+const REFRESH_MARGIN_SECS: u64 = 60;
+
+// RFC 3986 unreserved characters are left as is
+const OAUTH_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, OAUTH_ENCODE_SET).to_string()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+// OAuth 1.0a user context: app keys and the access token of the posting account
+#[derive(Debug, Clone)]
+pub struct OAuth1Credentials {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    pub access_token: String,
+    pub access_token_secret: String,
+}
+
+impl OAuth1Credentials {
+    // HMAC-SHA1 signature of a request
+    // `params` are the query and form parameters, JSON and multipart bodies aren't signed
+    pub fn signature(
+        &self,
+        method: &str,
+        url: &str,
+        params: &[(&str, &str)],
+        nonce: &str,
+        timestamp: u64,
+    ) -> String {
+        let timestamp = timestamp.to_string();
+        let mut pairs: Vec<(String, String)> = params
+            .iter()
+            .map(|(key, value)| (encode(key), encode(value)))
+            .collect();
+        for (key, value) in self.oauth_params(nonce, &timestamp) {
+            pairs.push((encode(key), encode(value)));
+        }
+        pairs.sort();
+        let param_string = pairs
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&");
+        let base_string = format!(
+            "{}&{}&{}",
+            method.to_uppercase(),
+            encode(url),
+            encode(&param_string)
+        );
+        let key = format!(
+            "{}&{}",
+            encode(&self.consumer_secret),
+            encode(&self.access_token_secret)
+        );
+        let mut mac =
+            Hmac::<Sha1>::new_from_slice(key.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(base_string.as_bytes());
+        STANDARD.encode(mac.finalize().into_bytes())
+    }
+
+    // Authorization header with a fresh nonce and timestamp
+    pub fn authorization(&self, method: &str, url: &str, params: &[(&str, &str)]) -> String {
+        let nonce = Alphanumeric.sample_string(&mut rand::thread_rng(), 32);
+        self.authorization_with(method, url, params, &nonce, now_secs())
+    }
+
+    pub fn authorization_with(
+        &self,
+        method: &str,
+        url: &str,
+        params: &[(&str, &str)],
+        nonce: &str,
+        timestamp: u64,
+    ) -> String {
+        let signature = self.signature(method, url, params, nonce, timestamp);
+        let timestamp = timestamp.to_string();
+        let mut header_params = self.oauth_params(nonce, &timestamp);
+        header_params.push(("oauth_signature", &signature));
+        let header_params: Vec<String> = header_params
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", encode(key), encode(value)))
+            .collect();
+        format!("OAuth {}", header_params.join(", "))
+    }
+
+    fn oauth_params<'a>(&'a self, nonce: &'a str, timestamp: &'a str) -> Vec<(&'a str, &'a str)> {
+        vec![
+            ("oauth_consumer_key", &self.consumer_key),
+            ("oauth_nonce", nonce),
+            ("oauth_signature_method", "HMAC-SHA1"),
+            ("oauth_timestamp", timestamp),
+            ("oauth_token", &self.access_token),
+            ("oauth_version", "1.0"),
+        ]
+    }
+}
+
+// PKCE verifier and its S256 challenge
+#[derive(Debug, Clone)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn new() -> Self {
+        Pkce::from_verifier(&Alphanumeric.sample_string(&mut rand::thread_rng(), 64))
+    }
+
+    pub fn from_verifier(verifier: &str) -> Self {
+        Pkce {
+            verifier: verifier.to_string(),
+            challenge: URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())),
+        }
+    }
+}
+
+impl Default for Pkce {
+    fn default() -> Self {
+        Pkce::new()
+    }
+}
+
+// Page where the account owner allows the app to post
+pub fn authorize_url(client_id: &str, redirect_uri: &str, state: &str, pkce: &Pkce) -> String {
+    let scope = SCOPES.join(" ");
+    url::Url::parse_with_params(
+        AUTHORIZE_URL,
+        &[
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", redirect_uri),
+            ("scope", scope.as_str()),
+            ("state", state),
+            ("code_challenge", pkce.challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .expect("AUTHORIZE_URL is valid")
+    .to_string()
+}
+
+// User access token, saved so the bot survives restarts without a new login
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    // Unix seconds
+    pub expires_at: u64,
+}
+
+impl OAuth2Token {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let raw = std::fs::read_to_string(path).map_err(|e| {
+            AppError::Config(format!(
+                "Can't read X token {}, run the x_login binary first: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(serde_json::from_str(&raw)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        save_json(path, self)
+            .map_err(|e| AppError::Config(format!("Can't save X token {}: {}", path.display(), e)))
+    }
+
+    fn expires_soon(&self) -> bool {
+        self.expires_at <= now_secs() + REFRESH_MARGIN_SECS
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: u64,
+}
+
+// Token endpoint of an OAuth 2.0 app
+// Confidential clients authenticate with their secret, public ones only send their id
+#[derive(Debug, Clone)]
+pub struct OAuth2App {
+    pub client: Client,
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+}
+
+impl OAuth2App {
+    #[tracing::instrument(name = "x_exchange_code", skip_all)]
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        pkce: &Pkce,
+    ) -> Result<OAuth2Token, AppError> {
+        self.request_token(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", &pkce.verifier),
+        ])
+        .await
+    }
+
+    #[tracing::instrument(name = "x_refresh_token", skip_all)]
+    pub async fn refresh(&self, refresh_token: &str) -> Result<OAuth2Token, AppError> {
+        self.request_token(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .await
+    }
+
+    async fn request_token(&self, params: &[(&str, &str)]) -> Result<OAuth2Token, AppError> {
+        let mut form = params.to_vec();
+        form.push(("client_id", &self.client_id));
+        let mut request = self.client.post(&self.token_url).form(&form);
+        if let Some(client_secret) = &self.client_secret {
+            request = request.basic_auth(&self.client_id, Some(client_secret));
+        }
+        let response = request.send().await?;
+        let resp_status = response.status();
+        if !resp_status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AppError::XApi(format!(
+                "Can't get an OAuth 2.0 token: status: {}, text: {}",
+                resp_status, error_text
+            )));
+        }
+        let token: TokenResponse = response.json().await?;
+        Ok(OAuth2Token {
+            access_token: token.access_token,
+            refresh_token: token.refresh_token,
+            expires_at: now_secs() + token.expires_in,
+        })
+    }
+}
+
+// Access token kept fresh, every refresh is saved as X rotates refresh tokens
+pub struct OAuth2Session {
+    app: OAuth2App,
+    path: PathBuf,
+    token: Mutex<OAuth2Token>,
+}
+
+impl OAuth2Session {
+    pub fn new(app: OAuth2App, path: &Path, token: OAuth2Token) -> Self {
+        OAuth2Session {
+            app,
+            path: path.to_path_buf(),
+            token: Mutex::new(token),
+        }
+    }
+
+    // Load the token saved by the x_login binary
+    pub fn load(app: OAuth2App, path: &Path) -> Result<Self, AppError> {
+        Ok(OAuth2Session::new(app, path, OAuth2Token::load(path)?))
+    }
+
+    // Valid access token, refreshed when it expires soon or `force` is set (e.g. after a 401)
+    pub async fn access_token(&self, force: bool) -> Result<String, AppError> {
+        let mut token = self.token.lock().await;
+        if force || token.expires_soon() {
+            let Some(refresh_token) = token.refresh_token.clone() else {
+                return Err(AppError::XApi(
+                    "X access token expired without a refresh token, run x_login again".to_string(),
+                ));
+            };
+            let mut refreshed = self.app.refresh(&refresh_token).await?;
+            // Keep the old refresh token when none is returned
+            refreshed.refresh_token = refreshed.refresh_token.or(Some(refresh_token));
+            if let Err(e) = refreshed.save(&self.path) {
+                tracing::error!("{}", e);
+            }
+            *token = refreshed;
+            tracing::info!("Refreshed X access token");
+        }
+        Ok(token.access_token.clone())
+    }
+}
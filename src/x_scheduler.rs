@@ -0,0 +1,160 @@
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+use crate::{
+    digest::Digest, events::JobStatusNotification, models::ArbitratorProfile, JobNotification,
+    Notification,
+};
+
+pub const DAY_SECS: u64 = 24 * 60 * 60;
+
+// Pace of the X sink, set under `x.schedule` in the config
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct XScheduleSettings {
+    // Posts (announcements, thread replies, status updates) allowed in any 24 hours, null for no limit
+    pub daily_budget: Option<usize>,
+    // The oldest queued notifications are dropped past this
+    pub max_queue: usize,
+    // Queued new jobs are collapsed into one summary post once there are this many
+    pub summary_threshold: Option<usize>,
+}
+
+impl Default for XScheduleSettings {
+    fn default() -> Self {
+        XScheduleSettings {
+            daily_budget: Some(50),
+            max_queue: 500,
+            summary_threshold: None,
+        }
+    }
+}
+
+// What the X sink posts next
+#[derive(Debug, Clone)]
+pub enum XPost {
+    Job(JobNotification),
+    Status(JobStatusNotification),
+    // "N new jobs", the jobs don't get their own post
+    Summary(Vec<JobNotification>),
+    Digest(Digest),
+    Arbitrator(ArbitratorProfile),
+}
+
+// Queue of the X posts waiting for the daily budget or the X rate limits
+pub struct XScheduler {
+    settings: XScheduleSettings,
+    queue: VecDeque<XPost>,
+    // When the posts of the last 24 hours were sent
+    sent: VecDeque<u64>,
+}
+
+impl XScheduler {
+    pub fn new(settings: XScheduleSettings) -> Self {
+        XScheduler {
+            settings,
+            queue: VecDeque::new(),
+            sent: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    // Queue a notification, deliveries aren't posted on X
+    pub fn push(&mut self, notification: Notification) {
+        let post = match notification {
+            Notification::JobPublished(job) => XPost::Job(job),
+            Notification::JobUpdated(status) => XPost::Status(status),
+            Notification::Digest(digest) => XPost::Digest(digest),
+            Notification::ArbitratorRegistered(arbitrator) => XPost::Arbitrator(arbitrator),
+            Notification::JobDelivered(delivery) => {
+                tracing::debug!(
+                    "Skipping X notification for delivered job {}",
+                    delivery.job_id
+                );
+                return;
+            }
+        };
+        self.queue.push_back(post);
+        while self.queue.len() > self.settings.max_queue {
+            if let Some(dropped) = self.queue.pop_front() {
+                tracing::error!("X queue full, dropping {:?}", dropped);
+            }
+        }
+    }
+
+    // Put a post that couldn't be sent back in front of the queue
+    pub fn requeue(&mut self, post: XPost) {
+        match post {
+            XPost::Summary(jobs) => {
+                for job in jobs.into_iter().rev() {
+                    self.queue.push_front(XPost::Job(job));
+                }
+            }
+            post => self.queue.push_front(post),
+        }
+    }
+
+    pub fn record_posts(&mut self, count: usize, now: u64) {
+        self.sent.extend(std::iter::repeat_n(now, count));
+    }
+
+    // Posts left in the budget, None without a budget
+    pub fn remaining_budget(&mut self, now: u64) -> Option<usize> {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| *sent + DAY_SECS <= now)
+        {
+            self.sent.pop_front();
+        }
+        self.settings
+            .daily_budget
+            .map(|budget| budget.saturating_sub(self.sent.len()))
+    }
+
+    // When the next post can be sent, `blocked_until` is the end of an exhausted X window
+    pub fn ready_at(&mut self, now: u64, blocked_until: Option<u64>) -> u64 {
+        let budget_ready = match self.remaining_budget(now) {
+            Some(0) => self
+                .sent
+                .front()
+                .map(|oldest| oldest + DAY_SECS)
+                .unwrap_or(now),
+            _ => now,
+        };
+        budget_ready.max(blocked_until.unwrap_or(now)).max(now)
+    }
+
+    // Next post, queued new jobs are collapsed into a summary when enough are waiting
+    pub fn next_post(&mut self) -> Option<XPost> {
+        let waiting_jobs = self
+            .queue
+            .iter()
+            .filter(|post| matches!(post, XPost::Job(_)))
+            .count();
+        let summarize = self
+            .settings
+            .summary_threshold
+            .is_some_and(|threshold| waiting_jobs >= threshold.max(2));
+        if !summarize {
+            return self.queue.pop_front();
+        }
+
+        let mut jobs = Vec::new();
+        self.queue.retain(|post| match post {
+            XPost::Job(job) => {
+                jobs.push(job.clone());
+                false
+            }
+            _ => true,
+        });
+        Some(XPost::Summary(jobs))
+    }
+}
@@ -0,0 +1,141 @@
+use alloy::primitives::{address, b256};
+use eacc_rs::admin::{
+    gated_channel, render_alert, send_alert, AdminAlert, AdminEvent, AdminSettings, PauseState,
+};
+use eacc_rs::events::{JobEventType, JobStatusNotification};
+use eacc_rs::telegram_api::TelegramClient;
+use eacc_rs::{JobNotification, Notification, MARKETPLACE_DATA_ADDRESS};
+use std::sync::Arc;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TOKEN: &str = "123:test";
+
+fn published(job_id: &str) -> Notification {
+    Notification::JobPublished(JobNotification {
+        job_id: job_id.to_string(),
+        ..Default::default()
+    })
+}
+
+fn paused_alert() -> AdminAlert {
+    AdminAlert {
+        event: AdminEvent::Paused {
+            account: address!("1111111111111111111111111111111111111111"),
+        },
+        contract: MARKETPLACE_DATA_ADDRESS,
+        tx_hash: Some(b256!(
+            "00000000000000000000000000000000000000000000000000000000000000aa"
+        )),
+    }
+}
+
+#[test]
+fn test_alert_text() {
+    let text = render_alert(&paused_alert());
+    assert!(text.starts_with("🚨 <b>Marketplace paused</b>\nBy <a href=\"https://arbiscan.io/address/0x1111111111111111111111111111111111111111\">"));
+    assert!(text.contains("Announcements are held back"));
+    assert!(text.ends_with(
+        "<a href=\"https://arbiscan.io/tx/0x00000000000000000000000000000000000000000000000000000000000000aa\">Transaction</a>"
+    ));
+
+    let upgraded = render_alert(&AdminAlert {
+        event: AdminEvent::Upgraded {
+            implementation: address!("2222222222222222222222222222222222222222"),
+        },
+        contract: address!("3333333333333333333333333333333333333333"),
+        tx_hash: None,
+    });
+    assert!(upgraded.starts_with("🚨 <b>Marketplace upgraded</b>"));
+    assert!(!upgraded.contains("Transaction"));
+}
+
+fn updated(job_id: &str) -> Notification {
+    Notification::JobUpdated(JobStatusNotification {
+        event: JobEventType::Closed,
+        status: "Closed".to_string(),
+        job: JobNotification {
+            job_id: job_id.to_string(),
+            ..Default::default()
+        },
+    })
+}
+
+#[tokio::test]
+async fn test_announcements_held_while_paused() {
+    let pause = Arc::new(PauseState::default());
+    let (tx, mut rx) = gated_channel(Some(pause.clone()), 10);
+
+    tx.send(published("1")).await.unwrap();
+    assert_eq!(rx.recv().await.unwrap().job_id(), "1");
+
+    assert!(pause.set(true));
+    assert!(!pause.set(true));
+    tx.send(published("2")).await.unwrap();
+    // Edits of existing posts still go through, unless the announcement is held
+    tx.send(updated("1")).await.unwrap();
+    tx.send(updated("2")).await.unwrap();
+    let edit = rx.recv().await.unwrap();
+    assert!(matches!(edit, Notification::JobUpdated(_)));
+    assert_eq!(edit.job_id(), "1");
+    assert!(rx.try_recv().is_err());
+
+    // Released in order on the unpause, before anything newer
+    assert!(pause.set(false));
+    tx.send(published("3")).await.unwrap();
+    assert!(matches!(
+        rx.recv().await.unwrap(),
+        Notification::JobPublished(job) if job.job_id == "2"
+    ));
+    assert!(matches!(
+        rx.recv().await.unwrap(),
+        Notification::JobUpdated(status) if status.job.job_id == "2"
+    ));
+    assert_eq!(rx.recv().await.unwrap().job_id(), "3");
+}
+
+#[tokio::test]
+async fn test_alerts_are_retried() {
+    let server = MockServer::start().await;
+    // The first attempt fails, the second one goes through
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/sendMessage", TOKEN)))
+        .respond_with(ResponseTemplate::new(502))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/sendMessage", TOKEN)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ok": true,
+            "result": {"message_id": 1, "chat": {"id": -42}, "text": ""}
+        })))
+        .mount(&server)
+        .await;
+
+    let api = TelegramClient::new(reqwest::Client::new(), &server.uri(), TOKEN);
+    send_alert(&api, "-42", &paused_alert()).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let body: serde_json::Value = requests[1].body_json().unwrap();
+    assert_eq!(body["chat_id"], "-42");
+    assert!(body["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("🚨 <b>Marketplace paused</b>"));
+}
+
+#[test]
+fn test_admin_chat_id() {
+    // Resolved before the worker starts, a missing chat is reported there
+    std::env::remove_var("TG_ADMIN_CHAT_ID");
+    assert_eq!(AdminSettings::default().chat_id(), None);
+    std::env::set_var("TG_ADMIN_CHAT_ID", "-7");
+    assert_eq!(AdminSettings::default().chat_id().as_deref(), Some("-7"));
+    let settings = AdminSettings {
+        chat_id: Some("-42".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(settings.chat_id().as_deref(), Some("-42"));
+}
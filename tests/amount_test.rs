@@ -0,0 +1,54 @@
+use alloy::primitives::U256;
+use eacc_rs::amount::{AmountFormat, TokenAmount};
+
+fn amount(raw: &str, decimals: u8) -> TokenAmount {
+    TokenAmount::new(raw.parse::<U256>().unwrap(), decimals)
+}
+
+#[test]
+fn amounts_are_exact() {
+    // 0.3 ETH would print 0.30000000000000004 through f64 arithmetic
+    assert_eq!(amount("300000000000000000", 18).to_string(), "0.3");
+    assert_eq!(
+        amount("123456789123456789123456789", 18).to_string(),
+        "123,456,789.123456789123456789"
+    );
+    assert_eq!(amount("1500000000", 6).to_string(), "1,500");
+    assert_eq!(amount("0", 18).to_string(), "0");
+}
+
+#[test]
+fn locales_and_small_amounts() {
+    let de = AmountFormat {
+        locale: "de".to_string(),
+        ..AmountFormat::default()
+    };
+    assert_eq!(amount("1234567890", 6).format(&de), "1.234,56789");
+
+    // Five leading zeros after the decimal point
+    assert_eq!(amount("1234", 9).to_string(), "0.0₅1234");
+    assert_eq!(amount("1234", 7).to_string(), "0.0001234");
+}
+
+#[test]
+fn significant_digits_round_half_up() {
+    let format = AmountFormat {
+        significant_digits: Some(4),
+        ..AmountFormat::default()
+    };
+    assert_eq!(amount("1234567", 6).format(&format), "1.235");
+    assert_eq!(amount("999960000", 6).format(&format), "1,000");
+    // Integer digits are never dropped
+    assert_eq!(amount("123456000000", 6).format(&format), "123,456");
+}
+
+#[test]
+fn serializes_raw_and_exact_value() {
+    let value = serde_json::to_value(amount("1500250000", 6)).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({"raw": "1500250000", "decimals": 6, "value": "1500.25"})
+    );
+    let back: TokenAmount = serde_json::from_value(value).unwrap();
+    assert_eq!(back, amount("1500250000", 6));
+}
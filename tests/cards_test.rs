@@ -0,0 +1,219 @@
+use eacc_rs::amount::AmountFormat;
+use eacc_rs::cards::{CardRenderer, CARD_HEIGHT, CARD_WIDTH};
+use eacc_rs::config::TelegramDestination;
+use eacc_rs::events::{JobEventType, JobStatusNotification};
+use eacc_rs::models::JobDetails;
+use eacc_rs::posts::PostStore;
+use eacc_rs::tags::TagCatalogue;
+use eacc_rs::telegram_api::{send_telegram_notification, update_telegram_posts, TelegramClient};
+use eacc_rs::templates::Templates;
+use eacc_rs::tokens::TokenInfo;
+use eacc_rs::{JobNotification, Notification};
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::io::Cursor;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TOKEN: &str = "123:test";
+
+fn job(logo_url: Option<String>) -> JobNotification {
+    let tags = vec!["audit".to_string(), "solidity".to_string()];
+    JobNotification {
+        job_id: "3".to_string(),
+        title: "Audit a vault contract ".repeat(8),
+        symbol: "USDC".to_string(),
+        token: TokenInfo {
+            symbol: "USDC".to_string(),
+            logo_url,
+            ..Default::default()
+        },
+        tags: TagCatalogue::bundled().labels("3", &tags),
+        job: JobDetails {
+            tags,
+            ..Default::default()
+        },
+        usd_value: Some(250.0),
+        ..Default::default()
+    }
+}
+
+fn png(image: &RgbaImage) -> Vec<u8> {
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+#[tokio::test]
+async fn test_card_is_rendered_with_a_cached_logo() {
+    let server = MockServer::start().await;
+    let logo = RgbaImage::from_pixel(32, 32, Rgba([255, 0, 0, 255]));
+    Mock::given(method("GET"))
+        .and(path("/usdc.png"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(png(&logo)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let cards = CardRenderer::new(reqwest::Client::new(), AmountFormat::default());
+    let job = job(Some(format!("{}/usdc.png", server.uri())));
+    let card = cards.render(&job).await.unwrap();
+    let image = image::load_from_memory(&card).unwrap().to_rgb8();
+    assert_eq!(image.dimensions(), (CARD_WIDTH, CARD_HEIGHT));
+    // Center of the logo
+    assert_eq!(image.get_pixel(72 + 48, 390 + 48).0, [255, 0, 0]);
+
+    // The logo is downloaded once, and the card rendered the same way
+    assert_eq!(cards.render(&job).await.unwrap(), card);
+}
+
+#[tokio::test]
+async fn test_failed_logo_downloads_are_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/usdc.png"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    let logo = RgbaImage::from_pixel(32, 32, Rgba([255, 0, 0, 255]));
+    Mock::given(method("GET"))
+        .and(path("/usdc.png"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(png(&logo)))
+        .mount(&server)
+        .await;
+
+    let cards = CardRenderer::new(reqwest::Client::new(), AmountFormat::default());
+    let job = job(Some(format!("{}/usdc.png", server.uri())));
+    // The outage isn't remembered, the next card gets the logo
+    let without_logo = cards.render(&job).await.unwrap();
+    assert_eq!(without_logo, cards.render_png(&job, None).unwrap());
+    let card = cards.render(&job).await.unwrap();
+    let image = image::load_from_memory(&card).unwrap().to_rgb8();
+    assert_eq!(image.get_pixel(72 + 48, 390 + 48).0, [255, 0, 0]);
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_card_falls_back_to_the_symbol() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<svg/>"))
+        .mount(&server)
+        .await;
+
+    let cards = CardRenderer::new(reqwest::Client::new(), AmountFormat::default());
+    let with_broken_logo = cards
+        .render(&job(Some(format!("{}/usdc.svg", server.uri()))))
+        .await
+        .unwrap();
+    let without_logo = cards.render_png(&job(None), None).unwrap();
+    assert_eq!(with_broken_logo, without_logo);
+}
+
+#[test]
+fn test_card_shows_the_tag_names() {
+    let cards = CardRenderer::new(reqwest::Client::new(), AmountFormat::default());
+    let tagged = |raw: &str, catalogue: &TagCatalogue| {
+        let tags = vec![raw.to_string()];
+        JobNotification {
+            tags: catalogue.labels("3", &tags),
+            job: JobDetails {
+                tags,
+                ..Default::default()
+            },
+            ..job(None)
+        }
+    };
+    let catalogue = TagCatalogue::bundled();
+    let long_form = cards.render_png(&tagged("DA", &catalogue), None).unwrap();
+
+    // The long form is drawn, whatever the raw tag
+    let mut raw_changed = tagged("DA", &catalogue);
+    raw_changed.job.tags = vec!["other".to_string()];
+    assert_eq!(cards.render_png(&raw_changed, None).unwrap(), long_form);
+
+    catalogue.set("DA", "");
+    let short_form = cards.render_png(&tagged("DA", &catalogue), None).unwrap();
+    assert_ne!(short_form, long_form);
+}
+
+#[tokio::test]
+async fn test_telegram_card_is_uploaded_once() {
+    let server = MockServer::start().await;
+    let photo_message = serde_json::json!({
+        "ok": true,
+        "result": {
+            "message_id": 5,
+            "chat": {"id": -100},
+            "photo": [
+                {"file_id": "small", "width": 320, "height": 180},
+                {"file_id": "large", "width": 1200, "height": 675}
+            ]
+        }
+    });
+    for name in ["sendPhoto", "editMessageCaption"] {
+        Mock::given(method("POST"))
+            .and(path(format!("/bot{}/{}", TOKEN, name)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&photo_message))
+            .mount(&server)
+            .await;
+    }
+
+    let api = TelegramClient::new(reqwest::Client::new(), &server.uri(), TOKEN);
+    let templates = Templates::bundled();
+    let posts = PostStore::default();
+    let destinations: Vec<TelegramDestination> = ["-100", "-200"]
+        .iter()
+        .map(|chat_id| TelegramDestination {
+            chat_id: chat_id.to_string(),
+            ..Default::default()
+        })
+        .collect();
+    let cards = CardRenderer::new(reqwest::Client::new(), AmountFormat::default());
+    let card = cards.render_png(&job(None), None).unwrap();
+
+    send_telegram_notification(
+        &api,
+        &templates,
+        &Notification::JobPublished(job(None)),
+        &destinations,
+        &posts,
+        false,
+        Some(card),
+    )
+    .await
+    .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    // Multipart upload first, then the file id of the largest size
+    let upload = String::from_utf8_lossy(&requests[0].body);
+    assert!(upload.contains("filename=\"card.png\""));
+    assert!(upload.contains("Audit a vault contract"));
+    let reuse: serde_json::Value = requests[1].body_json().unwrap();
+    assert_eq!(reuse["photo"], "large");
+    assert_eq!(reuse["chat_id"], "-200");
+    assert!(reuse["caption"].as_str().unwrap().chars().count() <= 1024);
+
+    let job_posts = posts.get("3").telegram;
+    assert_eq!(job_posts.len(), 2);
+    assert!(job_posts.iter().all(|post| post.photo));
+
+    // Cards are updated through their caption
+    let status = JobStatusNotification {
+        event: JobEventType::Taken,
+        status: "Taken".to_string(),
+        job: job(None),
+    };
+    update_telegram_posts(&api, &templates, &status, &posts, false, false)
+        .await
+        .unwrap();
+    let edits: Vec<_> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.url.path().ends_with("/editMessageCaption"))
+        .collect();
+    assert_eq!(edits.len(), 2);
+}
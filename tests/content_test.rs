@@ -0,0 +1,65 @@
+use eacc_rs::content::JobContent;
+
+// Every opening tag must be closed in order for Telegram to accept the message
+fn assert_balanced_html(html: &str) {
+    let mut stack: Vec<String> = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let end = rest[start..].find('>').expect("unterminated tag") + start;
+        let tag = &rest[start + 1..end];
+        if let Some(name) = tag.strip_prefix('/') {
+            assert_eq!(stack.pop().as_deref(), Some(name), "unbalanced in {html}");
+        } else {
+            let name = tag.split_whitespace().next().unwrap().to_string();
+            stack.push(name);
+        }
+        rest = &rest[end + 1..];
+    }
+    assert!(stack.is_empty(), "unclosed tags {stack:?} in {html}");
+}
+
+#[test]
+fn parses_links_images_and_attachments() {
+    let content = JobContent::parse(
+        "# Logo design\n\nSee [brief](https://example.com/brief.pdf) and [site](https://eacc.ai).\n\n![mockup](https://example.com/mock.png)\n\nMore at https://github.com/eacc\0\0",
+    );
+
+    assert_eq!(content.links.len(), 2);
+    assert_eq!(content.links[0].url, "https://eacc.ai");
+    assert_eq!(content.links[1].url, "https://github.com/eacc");
+    assert_eq!(content.attachments[0].text, "brief");
+    assert_eq!(content.images[0].url, "https://example.com/mock.png");
+    assert!(content
+        .summary
+        .starts_with("Logo design See brief (https://example.com/brief.pdf)"));
+    assert!(!content.markdown.ends_with('\0'));
+}
+
+#[test]
+fn telegram_html_escapes_raw_html_and_keeps_tags_balanced() {
+    let content = JobContent::parse(
+        "**Bold <script>** & *it*\n\n<div>raw</div>\n\n- one\n- `a<b`\n\n```\nif a < b {}\n```\n\n[bad](javascript:alert(1))",
+    );
+    let html = content.to_telegram_html();
+
+    assert!(html.contains("<b>Bold &lt;script&gt;</b> &amp; <i>it</i>"));
+    assert!(html.contains("&lt;div&gt;raw&lt;/div&gt;"));
+    assert!(html.contains("• <code>a&lt;b</code>"));
+    assert!(html.contains("<pre>if a &lt; b {}\n</pre>"));
+    assert!(!html.contains("javascript"));
+    assert_balanced_html(&html);
+}
+
+#[test]
+fn x_text_and_discord_markdown() {
+    let content = JobContent::parse("Build a **bot** using [alloy](https://alloy.rs) for_me");
+
+    assert_eq!(
+        content.to_x_text(),
+        "Build a bot using alloy (https://alloy.rs) for_me"
+    );
+    assert_eq!(
+        content.to_discord_markdown(),
+        "Build a **bot** using [alloy](<https://alloy.rs>) for\\_me"
+    );
+}
@@ -0,0 +1,75 @@
+use alloy::primitives::{b256, B256};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use eacc_rs::deliverables::{fetch_deliverable, preview, result_hash_from_event};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const JOB_RESULT_HASH: B256 =
+    b256!("00000000000000000000000000000000000000000000000000000000000000bb");
+
+#[test]
+fn test_result_hash_from_event() {
+    let hash = b256!("00000000000000000000000000000000000000000000000000000000000000aa");
+    assert_eq!(
+        result_hash_from_event(hash.as_slice(), JOB_RESULT_HASH),
+        hash
+    );
+    // Event data other than a bare hash falls back to the job
+    assert_eq!(
+        result_hash_from_event(&[], JOB_RESULT_HASH),
+        JOB_RESULT_HASH
+    );
+    assert_eq!(
+        result_hash_from_event(&[0xaa; 64], JOB_RESULT_HASH),
+        JOB_RESULT_HASH
+    );
+}
+
+#[test]
+fn test_short_previews_are_kept() {
+    assert_eq!(
+        preview("  Done, see the logo  \n").as_deref(),
+        Some("Done, see the logo")
+    );
+    assert_eq!(preview(""), None);
+    assert_eq!(preview(" \n\t"), None);
+}
+
+#[test]
+fn test_long_previews_are_cut_on_chars() {
+    let content = "é".repeat(300);
+    let cut = preview(&content).unwrap();
+    assert!(cut.ends_with('…'));
+    assert_eq!(cut.chars().count(), 281);
+
+    // Trailing spaces of the cut are dropped
+    let words = "word ".repeat(100);
+    assert!(preview(&words).unwrap().ends_with("word…"));
+}
+
+#[tokio::test]
+async fn test_only_public_deliverables_are_previewed() {
+    let server = MockServer::start().await;
+    std::env::set_var("IPFS_GATEWAY", format!("{}/ipfs/", server.uri()));
+    let public = BASE64.encode("Logo attached, 3 variants");
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(public))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    // Encrypted results aren't valid UTF-8
+    let encrypted = BASE64.encode([0xff, 0xfe, 0x80, 0x81, 0x00, 0x9f]);
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(encrypted))
+        .mount(&server)
+        .await;
+
+    assert_eq!(
+        fetch_deliverable(JOB_RESULT_HASH).await.unwrap().as_deref(),
+        Some("Logo attached, 3 variants")
+    );
+    assert_eq!(fetch_deliverable(JOB_RESULT_HASH).await.unwrap(), None);
+    // Nothing delivered, nothing fetched
+    assert_eq!(fetch_deliverable(B256::ZERO).await.unwrap(), None);
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
@@ -0,0 +1,254 @@
+use alloy::primitives::{address, U256};
+use eacc_rs::amount::TokenAmount;
+use eacc_rs::digest::{
+    block_ranges, send_due_digests, Activity, ActivitySource, Digest, DigestPeriod, DigestSettings,
+    DigestState, JobActivity, NewArbitrator,
+};
+use eacc_rs::error::AppError;
+use eacc_rs::events::JobEventType;
+use eacc_rs::telegram_api::render_notification;
+use eacc_rs::templates::{digest_context, Templates, X_DIGEST};
+use eacc_rs::tokens::TokenInfo;
+use eacc_rs::Notification;
+use futures::future::BoxFuture;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+const DAY: u64 = 24 * 60 * 60;
+// Monday 2024-01-01 00:00 UTC
+const MONDAY: u64 = 1_704_067_200;
+
+fn usdc() -> TokenInfo {
+    TokenInfo {
+        address: address!("af88d065e77c8cC2239327C5EDb3A432268e5831"),
+        symbol: "USDC".to_string(),
+        decimals: 6,
+        ..Default::default()
+    }
+}
+
+fn job(id: &str, tags: &[&str], usdc_amount: u64, events: Vec<(JobEventType, u64)>) -> JobActivity {
+    JobActivity {
+        job_id: id.to_string(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        token: usdc(),
+        amount: TokenAmount::new(U256::from(usdc_amount * 1_000_000), 6),
+        usd_value: Some(usdc_amount as f64),
+        events,
+    }
+}
+
+fn activity(now: u64) -> Activity {
+    Activity {
+        jobs: vec![
+            job(
+                "1",
+                &["DA", "DV"],
+                100,
+                vec![(JobEventType::Created, now - 3600)],
+            ),
+            job(
+                "2",
+                &["DA"],
+                50,
+                vec![
+                    (JobEventType::Created, now - 7200),
+                    (JobEventType::Taken, now - 3600),
+                    (JobEventType::Completed, now - 60),
+                ],
+            ),
+            // Published before the window, only the arbitration counts
+            job(
+                "3",
+                &["SC"],
+                1000,
+                vec![
+                    (JobEventType::Created, now - 30 * DAY),
+                    (JobEventType::Arbitrated, now - 60),
+                ],
+            ),
+            // A refused arbitration resolves the dispute too
+            job(
+                "4",
+                &["SC"],
+                10,
+                vec![
+                    (JobEventType::Disputed, now - 600),
+                    (JobEventType::ArbitrationRefused, now - 60),
+                ],
+            ),
+        ],
+        arbitrators: vec![
+            NewArbitrator {
+                name: "Carol".to_string(),
+                timestamp: now - 600,
+                ..Default::default()
+            },
+            NewArbitrator {
+                name: "Dave".to_string(),
+                timestamp: now - 30 * DAY,
+                ..Default::default()
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_digest_counts_the_window() {
+    let now = MONDAY + 9 * 3600;
+    let digest = Digest::compute(DigestPeriod::Daily, now - DAY, now, &activity(now), 1);
+    assert_eq!(digest.jobs_published, 2);
+    assert_eq!(digest.rewards.len(), 1);
+    assert_eq!(digest.rewards[0].amount.to_decimal_string(), "150");
+    assert_eq!(digest.total_usd, Some(150.0));
+    assert_eq!(digest.top_tags.len(), 1);
+    assert_eq!(digest.top_tags[0].tag, "DA");
+    assert_eq!(digest.top_tags[0].count, 2);
+    assert_eq!(digest.jobs_completed, 1);
+    assert_eq!(digest.disputes_resolved, 2);
+    assert_eq!(digest.new_arbitrators, vec!["Carol"]);
+
+    let telegram =
+        render_notification(&Templates::bundled(), &Notification::Digest(digest.clone())).unwrap();
+    assert!(telegram.starts_with("<b>EACC daily digest</b>"));
+    assert!(telegram.contains("<b>Jobs published</b>: 2 (≈ $150"));
+    assert!(telegram.contains("• 150 $USDC"));
+    assert!(telegram.contains("<b>New arbitrators</b>: Carol"));
+
+    let x = Templates::bundled()
+        .render(X_DIGEST, &digest_context(&digest).unwrap())
+        .unwrap();
+    assert!(x.contains("2 jobs published"));
+    assert!(x.contains("1 completed, 2 disputes resolved"));
+    assert!(x.ends_with("https://effectiveacceleration.ai/dashboard"));
+}
+
+#[test]
+fn test_digest_slots() {
+    let settings = DigestSettings {
+        hour_utc: 9,
+        weekday: 2,
+        ..Default::default()
+    };
+    // Before 9:00 the last daily slot is the day before
+    assert_eq!(
+        settings.last_slot(DigestPeriod::Daily, MONDAY + 8 * 3600),
+        MONDAY - DAY + 9 * 3600
+    );
+    assert_eq!(
+        settings.last_slot(DigestPeriod::Daily, MONDAY + 9 * 3600),
+        MONDAY + 9 * 3600
+    );
+    // Weekly digests go out on Wednesdays
+    assert_eq!(
+        settings.last_slot(DigestPeriod::Weekly, MONDAY + 3 * DAY),
+        MONDAY + 2 * DAY + 9 * 3600
+    );
+    assert_eq!(
+        settings.last_slot(DigestPeriod::Weekly, MONDAY + 2 * DAY),
+        MONDAY - 5 * DAY + 9 * 3600
+    );
+}
+
+// Records the windows it was asked for
+struct FakeActivity {
+    since: Mutex<Vec<u64>>,
+}
+
+impl ActivitySource for FakeActivity {
+    fn activity(&self, since: u64) -> BoxFuture<'_, eyre::Result<Activity>> {
+        self.since.lock().unwrap().push(since);
+        Box::pin(async move { Ok(activity(since + DAY)) })
+    }
+}
+
+#[tokio::test]
+async fn test_due_digests_are_sent_once() {
+    let dir = std::env::temp_dir().join(format!("eacc_digest_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("digests.json");
+    let _ = std::fs::remove_file(&path);
+
+    let settings = DigestSettings {
+        periods: vec![DigestPeriod::Daily, DigestPeriod::Weekly],
+        ..Default::default()
+    };
+    let source = FakeActivity {
+        since: Mutex::new(Vec::new()),
+    };
+    let (tx, mut rx) = mpsc::channel(10);
+    let mut state = DigestState::load(&path).unwrap();
+
+    // The first start only records the slots
+    let now = MONDAY + 10 * 3600;
+    let sent = send_due_digests(
+        &source,
+        &settings,
+        &mut state,
+        std::slice::from_ref(&tx),
+        now,
+    )
+    .await;
+    assert_eq!(sent.unwrap(), 0);
+
+    // A day later the daily digest is due, the state survives a restart
+    let mut state = DigestState::load(&path).unwrap();
+    let now = now + DAY;
+    let sent = send_due_digests(
+        &source,
+        &settings,
+        &mut state,
+        std::slice::from_ref(&tx),
+        now,
+    )
+    .await;
+    assert_eq!(sent.unwrap(), 1);
+    assert_eq!(*source.since.lock().unwrap(), vec![MONDAY + 9 * 3600]);
+    let Some(Notification::Digest(digest)) = rx.recv().await else {
+        panic!("expected a digest");
+    };
+    assert_eq!(digest.period, DigestPeriod::Daily);
+    assert_eq!(digest.to, MONDAY + DAY + 9 * 3600);
+    assert_eq!(digest.jobs_published, 2);
+
+    let sent = send_due_digests(&source, &settings, &mut state, &[tx], now + 60).await;
+    assert_eq!(sent.unwrap(), 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+struct FailingActivity;
+
+impl ActivitySource for FailingActivity {
+    fn activity(&self, _since: u64) -> BoxFuture<'_, eyre::Result<Activity>> {
+        Box::pin(async { Err(eyre::eyre!("RPC unavailable")) })
+    }
+}
+
+#[tokio::test]
+async fn test_reading_the_window() {
+    // Logs are read in bounded block ranges from the start of the window
+    assert_eq!(
+        block_ranges(100, 349, 100),
+        vec![(100, 199), (200, 299), (300, 349)]
+    );
+    assert_eq!(block_ranges(100, 100, 100), vec![(100, 100)]);
+    // Nothing mined since the window started
+    assert_eq!(block_ranges(101, 100, 100), vec![]);
+
+    let settings = DigestSettings {
+        periods: vec![DigestPeriod::Daily],
+        ..Default::default()
+    };
+    let mut state = DigestState::default();
+    state.set_sent(DigestPeriod::Daily, MONDAY);
+    let sent = send_due_digests(
+        &FailingActivity,
+        &settings,
+        &mut state,
+        &[],
+        MONDAY + DAY * 2,
+    )
+    .await;
+    assert!(matches!(sent, Err(AppError::Chain(_))));
+    assert_eq!(state.last_sent(DigestPeriod::Daily), Some(MONDAY));
+}
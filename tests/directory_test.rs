@@ -0,0 +1,69 @@
+use alloy::primitives::{address, Bytes};
+use eacc_rs::directory::{avatar_url, Directory};
+use eacc_rs::models::UserProfile;
+use eacc_rs::MarketPlaceData;
+
+const GATEWAY: &str = "https://ipfs.example/ipfs/";
+const CID: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+
+#[test]
+fn test_avatar_links() {
+    assert_eq!(
+        avatar_url("https://example.com/me.png", GATEWAY).as_deref(),
+        Some("https://example.com/me.png")
+    );
+    let expected = format!("{}{}", GATEWAY, CID);
+    assert_eq!(
+        avatar_url(&format!("ipfs://{}", CID), GATEWAY),
+        Some(expected.clone())
+    );
+    assert_eq!(avatar_url(CID, GATEWAY), Some(expected));
+    assert_eq!(
+        avatar_url(&format!("ipfs://ipfs/{}/me.png", CID), GATEWAY),
+        Some(format!("{}{}/me.png", GATEWAY, CID))
+    );
+    assert_eq!(avatar_url("", GATEWAY), None);
+    assert_eq!(avatar_url("not an avatar", GATEWAY), None);
+}
+
+#[test]
+fn test_directory_tracks_profile_updates() {
+    let dir = std::env::temp_dir().join(format!("eacc_directory_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("directory.json");
+    let _ = std::fs::remove_file(&path);
+
+    let alice = address!("1111111111111111111111111111111111111111");
+    let directory = Directory::load(&path, GATEWAY).unwrap();
+    directory.set_users(vec![UserProfile::from(&MarketPlaceData::UserRegistered {
+        addr: alice,
+        pubkey: Bytes::from(vec![0x02; 33]),
+        name: "Alice".to_string(),
+        bio: "Designer".to_string(),
+        avatar: CID.to_string(),
+    })]);
+    directory.update_user(&MarketPlaceData::UserUpdated {
+        addr: alice,
+        name: "Alice B.".to_string(),
+        bio: "Designer".to_string(),
+        avatar: String::new(),
+    });
+
+    // The directory survives a restart, lookups ignore the address case
+    let directory = Directory::load(&path, GATEWAY).unwrap();
+    let user = directory
+        .user(&alice.to_string().to_uppercase().replace("0X", "0x"))
+        .unwrap();
+    assert_eq!(user.name, "Alice B.");
+    assert!(user.public_key.starts_with("0x0202"));
+    assert_eq!(user.avatar_url, None);
+    assert_eq!(
+        directory.name(&alice.to_string()).as_deref(),
+        Some("Alice B.")
+    );
+    assert_eq!(
+        directory.name("0x2222222222222222222222222222222222222222"),
+        None
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
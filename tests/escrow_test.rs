@@ -0,0 +1,134 @@
+use alloy::primitives::{address, Address, Bytes, B256, U256};
+use alloy::providers::ProviderBuilder;
+use alloy::sol_types::{SolCall, SolValue};
+use alloy::transports::mock::Asserter;
+use eacc_rs::escrow::{check_funding, escrow_record, FundingStatus};
+use eacc_rs::{MarketPlaceData, Unicrow};
+
+const WORKER: Address = address!("2222222222222222222222222222222222222222");
+const TOKEN: Address = address!("af88d065e77c8cC2239327C5EDb3A432268e5831");
+
+fn job(state: u8, escrow_id: u64, collateral_owed: u64) -> MarketPlaceData::JobPost {
+    MarketPlaceData::JobPost {
+        state,
+        whitelistWorkers: false,
+        roles: MarketPlaceData::JobRoles {
+            creator: Address::ZERO,
+            arbitrator: Address::ZERO,
+            worker: WORKER,
+        },
+        title: "Logo".to_string(),
+        tags: Vec::new(),
+        contentHash: B256::ZERO,
+        multipleApplicants: false,
+        amount: U256::from(100u64),
+        token: TOKEN,
+        timestamp: 0,
+        maxTime: 0,
+        deliveryMethod: "ipfs".to_string(),
+        collateralOwed: U256::from(collateral_owed),
+        escrowId: U256::from(escrow_id),
+        resultHash: B256::ZERO,
+        rating: 0,
+        disputed: false,
+    }
+}
+
+// Unclaimed escrow paying the reward to the worker
+fn escrow() -> Unicrow::Escrow {
+    Unicrow::Escrow {
+        buyer: address!("1111111111111111111111111111111111111111"),
+        challengeExtension: 0,
+        seller: WORKER,
+        challengePeriodStart: 0,
+        marketplace: Address::ZERO,
+        marketplaceFee: U256::ZERO,
+        challengePeriodEnd: 0,
+        currency: TOKEN,
+        claimed: 0,
+        consensus: [0, 0],
+        split: [0, 0, 0, 0],
+        amount: U256::from(100u64),
+    }
+}
+
+#[test]
+fn test_consistent_funding() {
+    let held = check_funding(&job(0, 0, 0), None);
+    assert_eq!(held.status, FundingStatus::Held);
+    assert_eq!(held.issue, None);
+    assert_eq!(
+        check_funding(&job(1, 12, 0), Some(&escrow())).status,
+        FundingStatus::Escrowed
+    );
+    // Without the record the escrow isn't vouched for
+    let unverified = check_funding(&job(1, 12, 0), None);
+    assert_eq!(unverified.status, FundingStatus::Unverified);
+    assert_eq!(unverified.issue, None);
+    assert_eq!(
+        check_funding(&job(2, 0, 100), None).status,
+        FundingStatus::CollateralOwed
+    );
+    assert_eq!(
+        check_funding(&job(2, 12, 0), None).status,
+        FundingStatus::Settled
+    );
+}
+
+#[test]
+fn test_inconsistent_funding() {
+    let issue =
+        |job, escrow: Option<Unicrow::Escrow>| check_funding(&job, escrow.as_ref()).issue.unwrap();
+    assert_eq!(issue(job(0, 7, 0), None), "open job with escrow #7");
+    assert_eq!(issue(job(0, 0, 50), None), "open job owing collateral");
+    assert_eq!(issue(job(1, 0, 0), None), "taken job without escrow");
+    assert_eq!(
+        issue(job(2, 0, 150), None),
+        "collateral owed 150 is above the reward 100"
+    );
+    assert_eq!(
+        check_funding(&job(1, 0, 0), None).status,
+        FundingStatus::Inconsistent
+    );
+
+    let missing = Unicrow::Escrow {
+        seller: Address::ZERO,
+        ..escrow()
+    };
+    assert_eq!(issue(job(1, 12, 0), Some(missing)), "escrow #12 not found");
+    let short = Unicrow::Escrow {
+        amount: U256::from(90u64),
+        ..escrow()
+    };
+    assert_eq!(
+        issue(job(1, 12, 0), Some(short)),
+        "escrow #12 holds 90, less than the reward 100"
+    );
+    let claimed = Unicrow::Escrow {
+        claimed: 1,
+        ..escrow()
+    };
+    assert_eq!(
+        issue(job(1, 12, 0), Some(claimed)),
+        "escrow #12 is already claimed"
+    );
+}
+
+#[tokio::test]
+async fn test_escrow_record_is_read() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().on_mocked_client(asserter.clone());
+    // marketplace() of MarketplaceData, unicrow() of the marketplace, then the escrow
+    let marketplace = address!("3333333333333333333333333333333333333333");
+    let unicrow = address!("4444444444444444444444444444444444444444");
+    asserter.push_success(&Bytes::from(marketplace.abi_encode()));
+    asserter.push_success(&Bytes::from(unicrow.abi_encode()));
+    asserter.push_success(&Bytes::from(Unicrow::getEscrowCall::abi_encode_returns(&(
+        escrow(),
+    ))));
+
+    let record = escrow_record(provider, U256::from(12u64)).await.unwrap();
+    assert_eq!(record.seller, WORKER);
+    assert_eq!(record.amount, U256::from(100u64));
+    assert!(asserter.read_q().is_empty());
+}
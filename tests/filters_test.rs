@@ -0,0 +1,100 @@
+use alloy::primitives::{address, U256};
+use eacc_rs::amount::TokenAmount;
+use eacc_rs::content::JobContent;
+use eacc_rs::deliverables::DeliveryNotification;
+use eacc_rs::filters::FilterRule;
+use eacc_rs::models::JobDetails;
+use eacc_rs::tokens::TokenRegistry;
+use eacc_rs::{JobNotification, Notification};
+
+fn design_job() -> Notification {
+    Notification::JobPublished(JobNotification {
+        job_id: "7".to_string(),
+        title: "Logo for a DeFi app".to_string(),
+        description: JobContent::parse("Vector **logo** in SVG"),
+        amount: TokenAmount::new(U256::from(250_000_000u64), 6),
+        symbol: "USDC".to_string(),
+        token: TokenRegistry::bundled()
+            .get(address!("af88d065e77c8cC2239327C5EDb3A432268e5831"))
+            .unwrap(),
+        job: JobDetails {
+            creator: "0x1111111111111111111111111111111111111111".to_string(),
+            tags: vec!["DA".to_string(), "design".to_string()],
+            delivery_method: "ipfs".to_string(),
+            ..Default::default()
+        },
+        usd_value: Some(250.0),
+        ..Default::default()
+    })
+}
+
+fn rule(json: &str) -> FilterRule {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_default_rule_matches_everything() {
+    assert!(FilterRule::default().matches(&design_job()));
+    assert!(rule("{}").matches(&design_job()));
+}
+
+#[test]
+fn test_job_conditions() {
+    let job = design_job();
+    assert!(rule(r#"{"tags": ["Design", "dev"]}"#).matches(&job));
+    assert!(!rule(r#"{"tags": ["dev"]}"#).matches(&job));
+    assert!(!rule(r#"{"exclude_tags": ["da"]}"#).matches(&job));
+
+    assert!(rule(r#"{"tokens": ["usdc"]}"#).matches(&job));
+    assert!(rule(r#"{"tokens": ["0xaf88d065e77c8cc2239327c5edb3a432268e5831"]}"#).matches(&job));
+    assert!(!rule(r#"{"tokens": ["ARB"]}"#).matches(&job));
+
+    assert!(rule(r#"{"min_usd": 200}"#).matches(&job));
+    assert!(!rule(r#"{"min_usd": 1000}"#).matches(&job));
+    assert!(rule(r#"{"min_amount": 250}"#).matches(&job));
+    assert!(!rule(r#"{"min_amount": 250.01}"#).matches(&job));
+
+    assert!(rule(r#"{"delivery_methods": ["IPFS"]}"#).matches(&job));
+    assert!(!rule(r#"{"delivery_methods": ["courier"]}"#).matches(&job));
+
+    assert!(rule(r#"{"creators": ["0x1111111111111111111111111111111111111111"]}"#).matches(&job));
+    assert!(
+        !rule(r#"{"exclude_creators": ["0x1111111111111111111111111111111111111111"]}"#)
+            .matches(&job)
+    );
+
+    assert!(rule(r#"{"keywords": ["svg", "figma"]}"#).matches(&job));
+    assert!(!rule(r#"{"keywords": ["figma"]}"#).matches(&job));
+    assert!(!rule(r#"{"exclude_keywords": ["DeFi"]}"#).matches(&job));
+
+    // Every condition has to hold
+    assert!(!rule(r#"{"tags": ["design"], "min_usd": 1000}"#).matches(&job));
+
+    // Without a USD quote a minimum USD value can't be met
+    let Notification::JobPublished(mut unpriced) = job else {
+        unreachable!()
+    };
+    unpriced.usd_value = None;
+    assert!(!rule(r#"{"min_usd": 1}"#).matches(&Notification::JobPublished(unpriced)));
+}
+
+#[test]
+fn test_delivery_conditions() {
+    let Notification::JobPublished(job) = design_job() else {
+        unreachable!()
+    };
+    let delivery = Notification::JobDelivered(DeliveryNotification {
+        job_id: "7".to_string(),
+        title: "Logo for a DeFi app".to_string(),
+        result_hash: String::new(),
+        preview: None,
+        job,
+    });
+    // Deliveries match on their job like its announcement
+    assert!(rule(r#"{"tags": ["design"], "keywords": ["logo"]}"#).matches(&delivery));
+    assert!(!rule(r#"{"tags": ["rust"]}"#).matches(&delivery));
+    assert!(!rule(r#"{"min_usd": 500}"#).matches(&delivery));
+    assert!(!rule(r#"{"tokens": ["ARB"]}"#).matches(&delivery));
+    assert!(!rule(r#"{"keywords": ["audit"]}"#).matches(&delivery));
+    assert!(!rule(r#"{"deliveries": false}"#).matches(&delivery));
+}
@@ -0,0 +1,122 @@
+use alloy::primitives::Bytes;
+use eacc_rs::indexer::{event_ranges, EventRange, JobStore};
+use eacc_rs::job_state::JobState;
+use eacc_rs::models::JobDetails;
+use eacc_rs::MarketPlaceData::JobEventData;
+use sqlx::Row;
+
+#[test]
+fn test_event_ranges_cover_the_missing_events() {
+    assert_eq!(
+        event_ranges(7, 3, 10, 4),
+        vec![
+            EventRange {
+                job_id: 7,
+                start: 3,
+                limit: 4
+            },
+            EventRange {
+                job_id: 7,
+                start: 7,
+                limit: 3
+            },
+        ]
+    );
+    // Nothing to read once the database caught up
+    assert!(event_ranges(7, 10, 10, 4).is_empty());
+    assert!(event_ranges(7, 12, 10, 4).is_empty());
+}
+
+fn event(type_: u8, timestamp: u32) -> JobEventData {
+    JobEventData {
+        type_,
+        address_: Bytes::from(vec![0xaa; 20]),
+        data_: Bytes::from(vec![0x01, 0x02]),
+        timestamp_: timestamp,
+    }
+}
+
+// Runs against a scratch database, e.g.
+// DATABASE_URL=postgres://postgres@localhost/eacc_test cargo test -- --ignored
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_job_store_roundtrip() {
+    let store = JobStore::connect(&std::env::var("DATABASE_URL").unwrap())
+        .await
+        .unwrap();
+    let job_id = 900_000_000 + std::process::id() as u64;
+    sqlx::query("DELETE FROM jobs WHERE job_id = $1")
+        .bind(job_id as i64)
+        .execute(store.pool())
+        .await
+        .unwrap();
+
+    let mut job = JobDetails {
+        state: JobState::Open,
+        tags: vec!["DA".to_string(), "DV".to_string()],
+        amount: "1000000000000000000000".to_string(),
+        collateral_owed: "0".to_string(),
+        escrow_id: "0".to_string(),
+        ..Default::default()
+    };
+    store.upsert_job(job_id, "Write docs", &job).await.unwrap();
+    job.state = JobState::Taken;
+    store.upsert_job(job_id, "Write docs", &job).await.unwrap();
+
+    let row = sqlx::query("SELECT state, tags, amount::text AS amount FROM jobs WHERE job_id = $1")
+        .bind(job_id as i64)
+        .fetch_one(store.pool())
+        .await
+        .unwrap();
+    assert_eq!(row.try_get::<i16, _>("state").unwrap(), 1);
+    assert_eq!(
+        row.try_get::<Vec<String>, _>("tags").unwrap(),
+        vec!["DA", "DV"]
+    );
+    assert_eq!(
+        row.try_get::<String, _>("amount").unwrap(),
+        "1000000000000000000000"
+    );
+
+    // Overlapping pages keep the stored events
+    store
+        .insert_events(job_id, 0, &[event(0, 10), event(1, 20)])
+        .await
+        .unwrap();
+    store
+        .insert_events(job_id, 1, &[event(1, 20), event(2, 30)])
+        .await
+        .unwrap();
+    let counts = store.event_counts(job_id, job_id + 1).await.unwrap();
+    assert_eq!(counts.get(&job_id), Some(&3));
+
+    sqlx::query("DELETE FROM jobs WHERE job_id = $1")
+        .bind(job_id as i64)
+        .execute(store.pool())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_last_block_is_kept() {
+    let store = JobStore::connect(&std::env::var("DATABASE_URL").unwrap())
+        .await
+        .unwrap();
+    let previous = store.last_block().await.unwrap();
+
+    store.set_last_block(300_000_000).await.unwrap();
+    assert_eq!(store.last_block().await.unwrap(), Some(300_000_000));
+    store.set_last_block(300_000_042).await.unwrap();
+    assert_eq!(store.last_block().await.unwrap(), Some(300_000_042));
+
+    match previous {
+        Some(block) => store.set_last_block(block).await.unwrap(),
+        None => {
+            sqlx::query("DELETE FROM indexer_state")
+                .execute(store.pool())
+                .await
+                .unwrap();
+        }
+    }
+}
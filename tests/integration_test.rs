@@ -21,6 +21,7 @@ fn init_test_tracing() {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
     use std::time::Duration;
 
     use alloy::{
@@ -29,8 +30,9 @@ mod tests {
     };
     // use alloy::primitives::utils::format_units;
     use eacc_rs::{
+        amount::TokenAmount, config::Settings, content::JobContent, posts::PostStore,
         telegram_api::telegram_worker, utils::get_from_ipfs, x_api::x_worker, JobNotification,
-        MarketPlaceData, IERC20,
+        MarketPlaceData, Notification, IERC20,
     };
     use eyre::{Error, Result};
     use tokio::sync::mpsc;
@@ -51,12 +53,17 @@ mod tests {
         let rpc_api = env::var("RPC_API").expect("RPC API not set in environment variables");
 
         // Create platform-specific notification queues
-        let (telegram_tx, telegram_rx) = mpsc::channel::<JobNotification>(100);
-        let (twitter_tx, twitter_rx) = mpsc::channel::<JobNotification>(100);
+        let (telegram_tx, telegram_rx) = mpsc::channel::<Notification>(100);
+        let (twitter_tx, twitter_rx) = mpsc::channel::<Notification>(100);
 
         // Platform-specific workers
-        let telegram_handle = tokio::spawn(telegram_worker(telegram_rx));
-        let x_handle = tokio::spawn(x_worker(twitter_rx));
+        let posts = Arc::new(PostStore::default());
+        let telegram_handle = tokio::spawn(telegram_worker(
+            telegram_rx,
+            Settings::default(),
+            posts.clone(),
+        ));
+        let x_handle = tokio::spawn(x_worker(twitter_rx, Settings::default(), posts));
 
         let ws = WsConnect::new(format!(
             "wss://arbitrum-mainnet.infura.io/ws/v3/{}",
@@ -107,14 +114,19 @@ mod tests {
         let test_job = JobNotification {
             job_id: id.to_string(),
             title: job1.title,
-            description: job_description,
-            amount: decimal_amount,
+            description: JobContent::parse(&job_description),
+            amount: TokenAmount::new(job1.amount, token_decimals),
             symbol: token_symbol,
+            ..Default::default()
         };
 
         // Send test job to queue
-        telegram_tx.send(test_job.clone()).await?;
-        (twitter_tx).send(test_job.clone()).await?;
+        telegram_tx
+            .send(Notification::JobPublished(test_job.clone()))
+            .await?;
+        (twitter_tx)
+            .send(Notification::JobPublished(test_job.clone()))
+            .await?;
         tracing::info!("Sent test job to queue");
 
         // Wait briefly to allow worker to process
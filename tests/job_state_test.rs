@@ -0,0 +1,103 @@
+use eacc_rs::error::AppError;
+use eacc_rs::events::JobEventType;
+use eacc_rs::job_state::{JobState, JobStatus, JobTracker};
+use eacc_rs::models::JobDetails;
+
+fn snapshot(state: JobState, disputed: bool, delivered: bool) -> JobDetails {
+    JobDetails {
+        state,
+        status: JobStatus::from_snapshot(state, disputed, delivered),
+        disputed,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_replay_follows_the_lifecycle() {
+    use JobEventType::*;
+    let status = JobStatus::replay([
+        Created,
+        OwnerMessage,
+        Taken,
+        Delivered,
+        Disputed,
+        Arbitrated,
+    ]);
+    assert_eq!(status.unwrap(), JobStatus::Arbitrated);
+    assert_eq!(JobStatus::Arbitrated.state(), JobState::Closed);
+
+    // Refunds and reopening go back to open
+    let status = JobStatus::replay([Created, Taken, Refunded, Closed, Reopened, Paid, Completed]);
+    assert_eq!(status.unwrap(), JobStatus::Completed);
+
+    // Only a dispute can be arbitrated
+    let err = JobStatus::replay([Created, Taken, Arbitrated]).unwrap_err();
+    assert!(matches!(
+        err,
+        AppError::InvalidTransition {
+            status: JobStatus::Taken,
+            event: Arbitrated
+        }
+    ));
+    assert!(JobStatus::replay([Taken]).is_err());
+}
+
+#[test]
+fn test_state_keeps_the_raw_number_in_templates() {
+    let job = snapshot(JobState::Taken, false, true);
+    let json = serde_json::to_value(&job).unwrap();
+    assert_eq!(json["state"], 1);
+    assert_eq!(json["status"], "Delivered");
+    assert_eq!(
+        serde_json::from_value::<JobDetails>(json).unwrap().state,
+        JobState::Taken
+    );
+    assert_eq!(JobState::from(7), JobState::Unknown(7));
+    assert_eq!(
+        JobStatus::from_snapshot(JobState::Closed, true, true),
+        JobStatus::Arbitrated
+    );
+}
+
+#[test]
+fn test_tracker_detects_drift() {
+    let mut tracker = JobTracker::new();
+    assert_eq!(
+        tracker.advance("1", JobEventType::Created).unwrap(),
+        Some(JobStatus::Open)
+    );
+    // Jobs published before the tracker started wait for a snapshot
+    assert_eq!(tracker.advance("2", JobEventType::Taken).unwrap(), None);
+
+    let status = tracker.observe(
+        "1",
+        JobEventType::Taken,
+        &snapshot(JobState::Taken, false, false),
+    );
+    assert_eq!(status.unwrap(), JobStatus::Taken);
+
+    // A missed dispute and arbitration, the snapshot wins
+    let err = tracker
+        .observe(
+            "1",
+            JobEventType::Completed,
+            &snapshot(JobState::Taken, true, true),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        AppError::StateDrift {
+            expected: JobStatus::Completed,
+            actual: JobState::Taken,
+            ..
+        }
+    ));
+    assert_eq!(tracker.status("1"), Some(JobStatus::Disputed));
+
+    let status = tracker.observe(
+        "2",
+        JobEventType::Completed,
+        &snapshot(JobState::Closed, false, true),
+    );
+    assert_eq!(status.unwrap(), JobStatus::Completed);
+}
@@ -0,0 +1,149 @@
+use eacc_rs::config::TelegramDestination;
+use eacc_rs::events::{JobEventType, JobStatusNotification};
+use eacc_rs::job_state::JobState;
+use eacc_rs::models::JobDetails;
+use eacc_rs::posts::{PostStore, TelegramPost};
+use eacc_rs::telegram_api::{send_telegram_notification, update_telegram_posts, TelegramClient};
+use eacc_rs::templates::Templates;
+use eacc_rs::{JobNotification, Notification};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TOKEN: &str = "123:test";
+
+fn job(state: u8) -> JobNotification {
+    JobNotification {
+        job_id: "9".to_string(),
+        title: "Translate docs".to_string(),
+        job: JobDetails {
+            state: JobState::from(state),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn status(event: JobEventType, state: u8) -> JobStatusNotification {
+    JobStatusNotification {
+        event,
+        status: event.status().unwrap().to_string(),
+        job: job(state),
+    }
+}
+
+async fn mock_method(server: &MockServer, name: &str, result: serde_json::Value) {
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/{}", TOKEN, name)))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({"ok": true, "result": result})),
+        )
+        .mount(server)
+        .await;
+}
+
+// Bodies of the requests made to a Bot API method
+async fn requests(server: &MockServer, name: &str) -> Vec<serde_json::Value> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path().ends_with(name))
+        .map(|request| request.body_json().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_lifecycle_events_have_a_status() {
+    assert_eq!(JobEventType::from(1).status(), Some("Taken"));
+    assert_eq!(JobEventType::from(7).status(), Some("Closed"));
+    assert_eq!(JobEventType::Paid.status(), Some("Taken"));
+    assert_eq!(JobEventType::Disputed.status(), Some("Disputed"));
+    assert_eq!(
+        JobEventType::ArbitrationRefused.status(),
+        Some("Arbitration refused")
+    );
+    assert_eq!(JobEventType::from(0).status(), None);
+    assert_eq!(JobEventType::from(17).status(), None);
+}
+
+#[tokio::test]
+async fn test_announcements_are_edited_then_deleted() {
+    let server = MockServer::start().await;
+    mock_method(
+        &server,
+        "sendMessage",
+        serde_json::json!({"message_id": 77, "chat": {"id": -100}, "text": ""}),
+    )
+    .await;
+    mock_method(
+        &server,
+        "editMessageText",
+        serde_json::json!({"message_id": 77, "chat": {"id": -100}, "text": ""}),
+    )
+    .await;
+    mock_method(&server, "deleteMessage", serde_json::json!(true)).await;
+
+    let api = TelegramClient::new(reqwest::Client::new(), &server.uri(), TOKEN);
+    let templates = Templates::bundled();
+    let posts = PostStore::default();
+    let destinations = vec![TelegramDestination {
+        chat_id: "-100".to_string(),
+        ..Default::default()
+    }];
+
+    send_telegram_notification(
+        &api,
+        &templates,
+        &Notification::JobPublished(job(0)),
+        &destinations,
+        &posts,
+        false,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        posts.get("9").telegram,
+        vec![TelegramPost {
+            chat_id: "-100".to_string(),
+            message_id: 77,
+            photo: false,
+        }]
+    );
+
+    // Taken: the announcement gets a status banner
+    update_telegram_posts(
+        &api,
+        &templates,
+        &status(JobEventType::Taken, 1),
+        &posts,
+        true,
+        false,
+    )
+    .await
+    .unwrap();
+    let edits = requests(&server, "/editMessageText").await;
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0]["message_id"], 77);
+    let text = edits[0]["text"].as_str().unwrap();
+    assert!(text.starts_with("<b>Status: Taken</b>, no longer accepting applications"));
+    assert!(text.contains("Translate docs"));
+
+    // Closed with deletion enabled, here by completion: the post is removed and forgotten
+    update_telegram_posts(
+        &api,
+        &templates,
+        &status(JobEventType::Completed, 2),
+        &posts,
+        true,
+        false,
+    )
+    .await
+    .unwrap();
+    let deletions = requests(&server, "/deleteMessage").await;
+    assert_eq!(deletions.len(), 1);
+    assert_eq!(deletions[0]["chat_id"], "-100");
+    assert!(posts.get("9").telegram.is_empty());
+}
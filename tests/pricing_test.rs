@@ -0,0 +1,118 @@
+use alloy::primitives::{address, U256};
+use eacc_rs::amount::{AmountFormat, TokenAmount};
+use eacc_rs::pricing::{tick_to_price, HttpPriceSource, PriceOracle, PriceSource};
+use eacc_rs::tokens::{TokenInfo, TokenRegistry};
+use eyre::Result;
+use futures::future::BoxFuture;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn arb() -> TokenInfo {
+    TokenRegistry::bundled()
+        .get(address!("912CE59144191C1204E64559FE8253a0e49E6548"))
+        .expect("ARB is bundled")
+}
+
+// Source returning a fixed price and counting the lookups
+struct FixedSource {
+    price: Option<f64>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl PriceSource for FixedSource {
+    fn name(&self) -> &'static str {
+        "fixed"
+    }
+
+    fn usd_price<'a>(&'a self, _: &'a TokenInfo) -> BoxFuture<'a, Result<Option<f64>>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move { Ok(self.price) })
+    }
+}
+
+#[tokio::test]
+async fn test_http_source_reads_stub_api() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/simple/token_price/arbitrum-one"))
+        .and(query_param(
+            "contract_addresses",
+            "0x912ce59144191c1204e64559fe8253a0e49e6548",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "0x912ce59144191c1204e64559fe8253a0e49e6548": {"usd": 0.82}
+        })))
+        .mount(&server)
+        .await;
+    // The API answers with an empty object for tokens it doesn't track
+    Mock::given(method("GET"))
+        .and(path("/simple/token_price/arbitrum-one"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+
+    let source = HttpPriceSource::new(reqwest::Client::new(), &server.uri());
+    assert_eq!(source.usd_price(&arb()).await.unwrap(), Some(0.82));
+
+    // Tokens missing from the response have no price
+    let unknown = TokenInfo {
+        address: address!("1111111111111111111111111111111111111111"),
+        ..Default::default()
+    };
+    assert_eq!(source.usd_price(&unknown).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_oracle_falls_back_and_caches() {
+    let missing_calls = Arc::new(AtomicUsize::new(0));
+    let fixed_calls = Arc::new(AtomicUsize::new(0));
+    let oracle = PriceOracle::new(
+        vec![
+            Box::new(FixedSource {
+                price: None,
+                calls: missing_calls.clone(),
+            }),
+            Box::new(FixedSource {
+                price: Some(0.8),
+                calls: fixed_calls.clone(),
+            }),
+        ],
+        Duration::from_secs(60),
+    );
+
+    let amount = TokenAmount::new(
+        U256::from(1500u64) * U256::from(10u64).pow(U256::from(18)),
+        18,
+    );
+    let value = oracle.usd_value(&arb(), &amount).await.unwrap();
+    assert!((value - 1200.0).abs() < 1e-9);
+
+    // The second lookup is served from the cache
+    oracle.usd_value(&arb(), &amount).await.unwrap();
+    assert_eq!(missing_calls.load(Ordering::SeqCst), 1);
+    assert_eq!(fixed_calls.load(Ordering::SeqCst), 1);
+
+    // Without any source knowing the token there is no value
+    let oracle = PriceOracle::new(Vec::new(), Duration::from_secs(60));
+    assert_eq!(oracle.usd_value(&arb(), &amount).await, None);
+}
+
+#[test]
+fn test_twap_tick_and_usd_format() {
+    // WETH (18 decimals) / USDC (6 decimals) pool around $3000
+    let tick = (3000.0f64 * 1e-12).ln() / 1.0001f64.ln();
+    assert!((tick_to_price(tick, 18, 6) - 3000.0).abs() < 1e-6);
+
+    let format = AmountFormat::default();
+    assert_eq!(format.format_usd(1234.5), "$1,234.50");
+    assert_eq!(format.format_usd(0.004), "$0.00");
+    let format = AmountFormat {
+        locale: "de".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(format.format_usd(1234567.891), "$1.234.567,89");
+}
@@ -0,0 +1,110 @@
+use alloy::primitives::{address, Address, Bytes, U256};
+use alloy::providers::ProviderBuilder;
+use alloy::sol_types::SolCall;
+use alloy::transports::mock::Asserter;
+use eacc_rs::models::UserRating;
+use eacc_rs::reputation::{reputation_badge, summarize, Review, ReviewIndex};
+use eacc_rs::MarketPlaceData;
+
+const CREATOR: Address = address!("AbCd000000000000000000000000000000000002");
+
+fn review(rating: u8) -> Review {
+    Review {
+        reviewer: "0x1111111111111111111111111111111111111111".to_string(),
+        rating,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_reputation_badge() {
+    assert_eq!(
+        reputation_badge(4.8, 12).as_deref(),
+        Some("★4.8 from 12 reviews")
+    );
+    assert_eq!(
+        reputation_badge(5.0, 1).as_deref(),
+        Some("★5.0 from 1 review")
+    );
+    assert_eq!(reputation_badge(0.0, 0), None);
+
+    let rating = summarize(&[review(5), review(4), review(4)]);
+    assert_eq!(rating.number_of_reviews, 3);
+    assert_eq!(rating.badge.as_deref(), Some("★4.3 from 3 reviews"));
+    assert_eq!(summarize(&[]).badge, None);
+}
+
+#[test]
+fn test_review_index_keeps_each_review_once() {
+    let dir = std::env::temp_dir().join(format!("eacc_reviews_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("reviews.json");
+    let _ = std::fs::remove_file(&path);
+
+    let worker = "0xAbCd000000000000000000000000000000000001";
+    let index = ReviewIndex::load(&path).unwrap();
+    index.add_reviews(worker, 0, vec![review(5), review(3)]);
+    // Overlapping page, only the third review is new
+    index.add_reviews(worker, 1, vec![review(3), review(4)]);
+    // A page past the stored reviews would leave a gap
+    index.add_reviews(worker, 5, vec![review(1)]);
+    // Nothing is written before the index is saved
+    assert!(!path.exists());
+    index.save();
+
+    let index = ReviewIndex::load(&path).unwrap();
+    let ratings: Vec<u8> = index
+        .reviews(&worker.to_lowercase())
+        .iter()
+        .map(|review| review.rating)
+        .collect();
+    assert_eq!(ratings, vec![5, 3, 4]);
+    assert_eq!(index.summary(worker).average_rating, 4.0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// `getReviews` answer for reviews with these ratings
+fn reviews_page(ratings: &[u8]) -> Bytes {
+    let page: Vec<MarketPlaceData::Review> = ratings
+        .iter()
+        .map(|rating| MarketPlaceData::Review {
+            reviewer: address!("1111111111111111111111111111111111111111"),
+            jobId: U256::from(7u64),
+            rating: *rating,
+            text: "Great".to_string(),
+            timestamp: 0,
+        })
+        .collect();
+    MarketPlaceData::getReviewsCall::abi_encode_returns(&(page,)).into()
+}
+
+#[tokio::test]
+async fn test_creator_rating_from_the_index() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().on_mocked_client(asserter.clone());
+    let index = ReviewIndex::default();
+
+    // The contract counts two reviews the index misses, they are read
+    asserter.push_success(&reviews_page(&[5, 4]));
+    let rating = index
+        .rating(provider.clone(), CREATOR, Some(&UserRating::new(4.5, 2)))
+        .await
+        .unwrap();
+    assert_eq!(rating.badge.as_deref(), Some("★4.5 from 2 reviews"));
+    assert_eq!(index.reviews(&CREATOR.to_string()).len(), 2);
+
+    // Up to date, nothing is read and the indexed reviews are used
+    let rating = index
+        .rating(provider.clone(), CREATOR, Some(&UserRating::new(3.0, 2)))
+        .await
+        .unwrap();
+    assert_eq!(rating.average_rating, 4.5);
+
+    // A failed read falls back to the contract rating
+    let onchain = UserRating::new(4.0, 3);
+    assert_eq!(
+        index.rating(provider, CREATOR, Some(&onchain)).await,
+        Some(onchain)
+    );
+    assert!(asserter.read_q().is_empty());
+}
@@ -0,0 +1,96 @@
+use alloy::primitives::{Bytes, U256};
+use alloy::providers::bindings::IMulticall3;
+use alloy::providers::ProviderBuilder;
+use alloy::sol_types::{SolCall, SolValue};
+use alloy::transports::mock::Asserter;
+use eacc_rs::filters::FilterRule;
+use eacc_rs::models::JobDetails;
+use eacc_rs::tags::{hashtag, TagCatalogue};
+use eacc_rs::{JobNotification, Notification};
+
+#[test]
+fn test_hashtags() {
+    assert_eq!(hashtag("Digital Audio").as_deref(), Some("#DigitalAudio"));
+    assert_eq!(
+        hashtag("Non-Digital Goods").as_deref(),
+        Some("#NonDigitalGoods")
+    );
+    assert_eq!(hashtag("rust").as_deref(), Some("#rust"));
+    assert_eq!(hashtag(" - "), None);
+}
+
+#[test]
+fn test_catalogue_labels() {
+    let catalogue = TagCatalogue::bundled();
+    let labels = catalogue.labels("7", &["DA".to_string(), "design".to_string()]);
+    assert_eq!(labels[0].name, "Digital Audio");
+    assert!(labels[0].mece);
+    assert_eq!(labels[1].name, "design");
+    assert!(!labels[1].mece);
+
+    // New categories are picked up, removed ones fall back to the short form
+    catalogue.set("DG", "Digital Graphics");
+    catalogue.set("DA", "");
+    let labels = catalogue.labels("8", &["DG".to_string(), "DA".to_string()]);
+    assert_eq!(labels[0].hashtag.as_deref(), Some("#DigitalGraphics"));
+    assert_eq!(labels[1].name, "DA");
+    assert_eq!(catalogue.get("DA"), None);
+}
+
+#[test]
+fn test_filters_match_long_forms() {
+    let tags = vec!["NDG".to_string(), "furniture".to_string()];
+    let notification = Notification::JobPublished(JobNotification {
+        tags: TagCatalogue::bundled().labels("7", &tags),
+        job: JobDetails {
+            tags,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let by_name: FilterRule = serde_json::from_str(r#"{"tags": ["non-digital goods"]}"#).unwrap();
+    assert!(by_name.matches(&notification));
+    let by_short: FilterRule = serde_json::from_str(r#"{"exclude_tags": ["ndg"]}"#).unwrap();
+    assert!(!by_short.matches(&notification));
+    let other: FilterRule = serde_json::from_str(r#"{"tags": ["digital audio"]}"#).unwrap();
+    assert!(!other.matches(&notification));
+}
+
+#[tokio::test]
+async fn test_unknown_tags_survive_a_failed_refresh() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().on_mocked_client(asserter.clone());
+    let catalogue = TagCatalogue::bundled();
+    catalogue.labels("9", &["DX".to_string()]);
+
+    // Nothing queued, the multicall fails
+    assert!(catalogue.refresh(provider.clone()).await.is_err());
+    assert_eq!(catalogue.get("DX"), None);
+
+    // The next refresh still looks the reported tag up, in short form order
+    let long_forms = [
+        "Digital Audio",
+        "Digital Others",
+        "Digital Software",
+        "Digital Text",
+        "Digital Video",
+        "Digital Experiments",
+        "Non-Digital Goods",
+        "Non-Digital Others",
+        "Non-Digital Services",
+    ];
+    let returns = IMulticall3::aggregateCall::abi_encode_returns(&(
+        U256::from(1u64),
+        long_forms
+            .iter()
+            .map(|long_form| Bytes::from(long_form.to_string().abi_encode()))
+            .collect::<Vec<_>>(),
+    ));
+    asserter.push_success(&Bytes::from(returns));
+    catalogue.refresh(provider).await.unwrap();
+    assert_eq!(catalogue.get("DX").as_deref(), Some("Digital Experiments"));
+    assert_eq!(
+        catalogue.get("NDS").as_deref(),
+        Some("Non-Digital Services")
+    );
+}
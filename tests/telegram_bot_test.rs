@@ -0,0 +1,315 @@
+use eacc_rs::filters::FilterRule;
+use eacc_rs::models::{JobDetails, UserProfile};
+use eacc_rs::telegram_api::{job_keyboard, CallbackAction, TelegramClient};
+use eacc_rs::telegram_bot::{
+    describe_subscription, Command, JobLookup, SubscriptionStore, TelegramBot,
+};
+use eacc_rs::templates::Templates;
+use eacc_rs::{JobNotification, Notification};
+use eyre::Result;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TOKEN: &str = "123:test";
+
+fn rust_job() -> JobNotification {
+    JobNotification {
+        job_id: "42".to_string(),
+        title: "Port a CLI to Rust".to_string(),
+        symbol: "USDC".to_string(),
+        job: JobDetails {
+            tags: vec!["rust".to_string()],
+            ..Default::default()
+        },
+        usd_value: Some(500.0),
+        ..Default::default()
+    }
+}
+
+// Only knows job 42
+struct FakeJobs;
+
+impl JobLookup for FakeJobs {
+    fn job<'a>(&'a self, job_id: &'a str) -> BoxFuture<'a, Result<Option<JobNotification>>> {
+        Box::pin(async move { Ok((job_id == "42").then(rust_job)) })
+    }
+}
+
+fn update(update_id: i64, chat_id: i64, text: &str) -> serde_json::Value {
+    serde_json::json!({
+        "update_id": update_id,
+        "message": {"message_id": update_id, "chat": {"id": chat_id}, "text": text}
+    })
+}
+
+// Texts sent with sendMessage, in order
+async fn sent_messages(server: &MockServer) -> Vec<(i64, String)> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path().ends_with("/sendMessage"))
+        .map(|request| {
+            let body: serde_json::Value = request.body_json().unwrap();
+            (
+                body["chat_id"].as_str().unwrap().parse().unwrap(),
+                body["text"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_commands() {
+    let Ok(Command::Subscribe(rule)) =
+        Command::parse("/subscribe@eacc_bot tag:rust min:100 token:ARB audit -test")
+    else {
+        panic!("subscribe not parsed");
+    };
+    assert_eq!(rule.tags, vec!["rust"]);
+    assert_eq!(rule.min_usd, Some(100.0));
+    assert_eq!(rule.tokens, vec!["ARB"]);
+    assert_eq!(rule.keywords, vec!["audit"]);
+    assert_eq!(rule.exclude_keywords, vec!["test"]);
+    assert!(!rule.deliveries);
+    assert_eq!(
+        describe_subscription(&rule),
+        "tag:rust token:ARB min:100 audit -test"
+    );
+    assert_eq!(describe_subscription(&FilterRule::default()), "all jobs");
+
+    assert!(Command::parse("/subscribe min:lots").is_err());
+    assert!(Command::parse("/subscribe colour:red").is_err());
+    assert_eq!(
+        Command::parse("/unsubscribe"),
+        Ok(Command::Unsubscribe(None))
+    );
+    assert_eq!(
+        Command::parse("/unsubscribe 2"),
+        Ok(Command::Unsubscribe(Some(2)))
+    );
+    assert!(Command::parse("/unsubscribe 0").is_err());
+    assert_eq!(Command::parse("/job 7"), Ok(Command::Job("7".to_string())));
+    assert!(Command::parse("/job").is_err());
+    assert!(Command::parse("hello").is_err());
+
+    assert!(Command::is_addressed_to("/list", Some("eacc_bot")));
+    assert!(Command::is_addressed_to("/list@EACC_bot", Some("eacc_bot")));
+    assert!(!Command::is_addressed_to(
+        "/list@other_bot",
+        Some("eacc_bot")
+    ));
+    assert!(Command::is_addressed_to("/list@other_bot", None));
+    assert!(!Command::is_addressed_to("hello /list", Some("eacc_bot")));
+    assert!(!Command::is_addressed_to("", None));
+}
+
+#[tokio::test]
+async fn test_bot_stays_quiet_in_groups() {
+    let server = MockServer::start().await;
+    let topic_message = |update_id: i64, text: &str| {
+        serde_json::json!({
+            "update_id": update_id,
+            "message": {
+                "message_id": update_id,
+                "chat": {"id": -100},
+                "message_thread_id": 7,
+                "is_topic_message": true,
+                "text": text
+            }
+        })
+    };
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/getUpdates", TOKEN)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ok": true,
+            "result": [
+                topic_message(30, "Anyone up for a logo job?"),
+                topic_message(31, "/list@other_bot"),
+                topic_message(32, "/list@eacc_bot"),
+                update(33, -200, "/help"),
+            ]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/sendMessage", TOKEN)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ok": true,
+            "result": {"message_id": 1, "chat": {"id": -100}, "text": ""}
+        })))
+        .mount(&server)
+        .await;
+
+    let bot = TelegramBot::new(
+        TelegramClient::new(reqwest::Client::new(), &server.uri(), TOKEN),
+        Templates::bundled(),
+        SubscriptionStore::default(),
+        Arc::new(FakeJobs),
+    );
+    bot.set_username("eacc_bot");
+    assert_eq!(bot.poll_once(0, 0).await.unwrap(), 34);
+
+    let bodies: Vec<serde_json::Value> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path().ends_with("/sendMessage"))
+        .map(|request| request.body_json().unwrap())
+        .collect();
+    // Only the commands for this bot are answered, in the topic they came from
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0]["chat_id"], "-100");
+    assert_eq!(bodies[0]["message_thread_id"], 7);
+    assert_eq!(bodies[1]["chat_id"], "-200");
+    assert!(bodies[1].get("message_thread_id").is_none());
+}
+
+#[tokio::test]
+async fn test_bot_against_mock_telegram() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/getUpdates", TOKEN)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ok": true,
+            "result": [
+                update(10, 1, "/subscribe tag:rust min:100"),
+                update(11, 2, "/subscribe tag:design"),
+                update(12, 1, "/list"),
+                update(13, 2, "/job 42"),
+                update(14, 2, "/job 7"),
+            ]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/sendMessage", TOKEN)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ok": true,
+            "result": {"message_id": 1, "chat": {"id": 1}, "text": ""}
+        })))
+        .mount(&server)
+        .await;
+
+    let store_path = std::env::temp_dir().join("eacc_rs_subscriptions_test.json");
+    let _ = std::fs::remove_file(&store_path);
+    let bot = TelegramBot::new(
+        TelegramClient::new(reqwest::Client::new(), &server.uri(), TOKEN),
+        Templates::bundled(),
+        SubscriptionStore::load(&store_path).unwrap(),
+        Arc::new(FakeJobs),
+    );
+
+    let offset = bot.poll_once(0, 0).await.unwrap();
+    assert_eq!(offset, 15);
+
+    let replies = sent_messages(&server).await;
+    assert_eq!(replies.len(), 5);
+    assert_eq!(replies[0].0, 1);
+    assert!(replies[0].1.starts_with("Subscribed to tag:rust min:100"));
+    assert!(replies[2].1.contains("1. tag:rust min:100"));
+    assert!(replies[3].1.contains("Port a CLI to Rust"));
+    assert_eq!(replies[4].1, "Job 7 not found");
+
+    // Only the rust subscriber gets the job
+    let sent = bot
+        .deliver(&Notification::JobPublished(rust_job()))
+        .await
+        .unwrap();
+    assert_eq!(sent, 1);
+    let messages = sent_messages(&server).await;
+    let (chat_id, text) = messages.last().unwrap();
+    assert_eq!(*chat_id, 1);
+    assert!(text.contains("Port a CLI to Rust"));
+
+    // Subscriptions survive a restart
+    let reloaded = SubscriptionStore::load(&store_path).unwrap();
+    assert_eq!(reloaded.list(1).len(), 1);
+    assert_eq!(reloaded.list(2)[0].tags, vec!["design"]);
+    assert_eq!(reloaded.unsubscribe(2, None).unwrap(), 1);
+    assert!(reloaded.list(2).is_empty());
+
+    std::fs::remove_file(store_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_job_keyboard_and_callbacks() {
+    let mut job = rust_job();
+    let keyboard = job_keyboard(&job, false);
+    assert_eq!(keyboard.inline_keyboard.len(), 1);
+    assert_eq!(
+        keyboard.inline_keyboard[0][0].url.as_deref(),
+        Some("https://effectiveacceleration.ai/dashboard/jobs/42")
+    );
+    assert!(keyboard.inline_keyboard[0][1]
+        .url
+        .as_deref()
+        .unwrap()
+        .starts_with("https://t.me/share/url?url=https%3A%2F%2Feffectiveacceleration.ai"));
+
+    // The profile button only shows up when the creator has one
+    assert_eq!(job_keyboard(&job, true).inline_keyboard[1].len(), 1);
+    job.creator_profile = Some(UserProfile {
+        name: "Alice".to_string(),
+        ..Default::default()
+    });
+    let callbacks = &job_keyboard(&job, true).inline_keyboard[1];
+    assert_eq!(callbacks.len(), 2);
+    assert_eq!(callbacks[0].callback_data.as_deref(), Some("profile:42"));
+    assert_eq!(
+        CallbackAction::parse("similar:42"),
+        Some(CallbackAction::SubscribeSimilar("42".to_string()))
+    );
+    assert_eq!(CallbackAction::parse("other:42"), None);
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/getUpdates", TOKEN)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ok": true,
+            "result": [
+                {"update_id": 20, "callback_query": {"id": "a", "from": {"id": 5}, "data": "similar:42"}},
+                {"update_id": 21, "callback_query": {"id": "b", "from": {"id": 5}, "data": "profile:7"}},
+            ]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("/bot{}/answerCallbackQuery", TOKEN)))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({"ok": true, "result": true})),
+        )
+        .mount(&server)
+        .await;
+
+    let bot = TelegramBot::new(
+        TelegramClient::new(reqwest::Client::new(), &server.uri(), TOKEN),
+        Templates::bundled(),
+        SubscriptionStore::default(),
+        Arc::new(FakeJobs),
+    );
+    assert_eq!(bot.poll_once(0, 0).await.unwrap(), 22);
+
+    // The user pressing the button is subscribed to the job tags
+    assert_eq!(bot.store().list(5)[0].tags, vec!["rust"]);
+    let answers: Vec<serde_json::Value> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path().ends_with("/answerCallbackQuery"))
+        .map(|request| request.body_json().unwrap())
+        .collect();
+    assert_eq!(answers.len(), 2);
+    assert_eq!(answers[0]["callback_query_id"], "a");
+    assert!(answers[0]["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("Subscribed to tag:rust"));
+    assert_eq!(answers[1]["text"], "Job not found");
+}
@@ -0,0 +1,101 @@
+use alloy::primitives::U256;
+use eacc_rs::{
+    amount::TokenAmount,
+    content::JobContent,
+    telegram_format::{render_job_message, truncate_html, visible_len, MESSAGE_MAX_LEN},
+    templates::Templates,
+    JobNotification,
+};
+
+fn notification(title: &str, description: &str) -> JobNotification {
+    JobNotification {
+        job_id: "517".to_string(),
+        title: title.to_string(),
+        description: JobContent::parse(description),
+        amount: TokenAmount::new(U256::from(1_500_000), 6),
+        symbol: "USDC".to_string(),
+        ..Default::default()
+    }
+}
+
+// Opening and closing tags must match for Telegram to accept the message
+fn assert_balanced_html(html: &str) {
+    let mut stack: Vec<String> = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let end = rest[start..].find('>').expect("unterminated tag") + start;
+        let tag = &rest[start + 1..end];
+        match tag.strip_prefix('/') {
+            Some(name) => assert_eq!(stack.pop().as_deref(), Some(name), "in {html}"),
+            None => stack.push(tag.split_whitespace().next().unwrap().to_string()),
+        }
+        rest = &rest[end + 1..];
+    }
+    assert!(stack.is_empty(), "unclosed tags {stack:?} in {html}");
+}
+
+#[test]
+fn adversarial_titles_are_escaped() {
+    let templates = Templates::bundled();
+    for title in [
+        "<b>Unclosed bold",
+        "Fix </a> & <i>break</b>",
+        "AT&T <script>alert(1)</script>",
+        "\"quoted\" 'title' >_<",
+    ] {
+        let message = render_job_message(
+            &templates,
+            &notification(title, "Plain description"),
+            MESSAGE_MAX_LEN,
+        )
+        .unwrap();
+        assert!(!message.contains(title), "title not escaped: {message}");
+        assert_balanced_html(&message);
+    }
+
+    let message =
+        render_job_message(&templates, &notification("AT&T <dev>", ""), MESSAGE_MAX_LEN).unwrap();
+    assert!(message.contains("AT&amp;T &lt;dev&gt;"));
+}
+
+#[test]
+fn long_descriptions_fit_the_message_limit() {
+    let description = "**Lorem ipsum** dolor sit amet 🚀 & <more>. ".repeat(400);
+    let message = render_job_message(
+        &Templates::bundled(),
+        &notification("Long job", &description),
+        MESSAGE_MAX_LEN,
+    )
+    .unwrap();
+
+    assert!(visible_len(&message) <= MESSAGE_MAX_LEN);
+    assert!(message.contains("Read more…</a>"));
+    assert!(message.contains("https://effectiveacceleration.ai/dashboard/jobs/517"));
+    assert!(message.contains("<b>Job Reward</b>: 1.5 $USDC"));
+    assert_balanced_html(&message);
+}
+
+#[test]
+fn truncation_respects_utf16_entities_and_tags() {
+    // Each emoji takes two UTF-16 code units
+    let (cut, truncated) = truncate_html("🚀🚀🚀🚀", 5);
+    assert!(truncated);
+    assert_eq!(cut, "🚀🚀…");
+
+    // Entities count as a single character and are never split
+    let (cut, _) = truncate_html("a&amp;b&lt;c&gt;d", 4);
+    assert_eq!(cut, "a&amp;b…");
+
+    // Tags opened before the cut are closed, empty ones dropped
+    let (cut, _) = truncate_html(
+        "<b>bold <i>text</i></b> <a href=\"https://x.y\">link</a>",
+        8,
+    );
+    assert_eq!(cut, "<b>bold <i>te…</i></b>");
+    let (cut, _) = truncate_html("<b>bold</b><i>more text</i>", 4);
+    assert_eq!(cut, "<b>bol…</b>");
+
+    let (same, truncated) = truncate_html("<b>short</b>", 10);
+    assert!(!truncated);
+    assert_eq!(same, "<b>short</b>");
+}
@@ -0,0 +1,59 @@
+use eacc_rs::config::TelegramSettings;
+use eacc_rs::deliverables::DeliveryNotification;
+use eacc_rs::models::JobDetails;
+use eacc_rs::telegram_api::route_notification;
+use eacc_rs::{JobNotification, Notification};
+
+fn job(tags: &[&str], usd_value: f64) -> Notification {
+    Notification::JobPublished(JobNotification {
+        job_id: "1".to_string(),
+        title: "Job".to_string(),
+        job: JobDetails {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        },
+        usd_value: Some(usd_value),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_routes_jobs_to_chats_and_topics() {
+    let settings: TelegramSettings = serde_json::from_str(
+        r#"{
+            "destinations": [
+                {
+                    "chat_id": "-1001",
+                    "message_thread_id": 1,
+                    "topics": {"DA": 10, "DV": 20}
+                },
+                {"chat_id": "-1002", "filter": {"min_usd": 1000, "deliveries": false}}
+            ]
+        }"#,
+    )
+    .unwrap();
+    let destinations = &settings.destinations;
+
+    // The first mapped tag picks the topic
+    assert_eq!(
+        route_notification(destinations, &job(&["rust", "dv", "DA"], 50.0)),
+        vec![("-1001", Some(20))]
+    );
+    // Unmapped tags fall back to the default topic, big jobs also go to the second chat
+    assert_eq!(
+        route_notification(destinations, &job(&["misc"], 5000.0)),
+        vec![("-1001", Some(1)), ("-1002", None)]
+    );
+
+    let delivery = Notification::JobDelivered(DeliveryNotification {
+        job_id: "1".to_string(),
+        title: "Job".to_string(),
+        result_hash: String::new(),
+        preview: None,
+        job: Default::default(),
+    });
+    assert_eq!(
+        route_notification(destinations, &delivery),
+        vec![("-1001", Some(1))]
+    );
+}
@@ -0,0 +1,251 @@
+use alloy::primitives::{address, Bytes, U256};
+use eacc_rs::{
+    amount::TokenAmount,
+    config::{Settings, XSettings},
+    content::JobContent,
+    escrow::{Funding, FundingStatus},
+    events::{JobEventType, JobStatusNotification},
+    models::{ArbitratorProfile, JobDetails, UserProfile, UserRating},
+    tags::TagCatalogue,
+    telegram_api::render_notification,
+    templates::{
+        job_context, status_context, Templates, TELEGRAM_JOB, X_ARBITRATOR, X_JOB, X_STATUS,
+    },
+    JobNotification, MarketPlaceData, Notification,
+};
+
+fn notification() -> JobNotification {
+    JobNotification {
+        job_id: "42".to_string(),
+        title: "Audit <Vault> & tests".to_string(),
+        description: JobContent::parse("Review the **vault** contract"),
+        amount: TokenAmount::new("12500250000000000000000".parse::<U256>().unwrap(), 18),
+        symbol: "ARB".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn bundled_templates_render_every_sink() {
+    let templates = Templates::bundled();
+    let context = job_context(&notification()).unwrap();
+
+    let telegram = templates.render(TELEGRAM_JOB, &context).unwrap();
+    assert!(telegram.contains("Audit &lt;Vault&gt; &amp; tests"));
+    assert!(telegram.contains("Review the <b>vault</b> contract"));
+    assert!(telegram.contains("12,500.25 $ARB"));
+
+    let x = templates.render(X_JOB, &context).unwrap();
+    assert_eq!(
+        x,
+        "Title: Audit <Vault> & tests\nReward: 12,500.25 $ARB\nDetails: https://effectiveacceleration.ai/dashboard/jobs/42"
+    );
+}
+
+#[test]
+fn templates_are_loaded_from_config() {
+    let path = std::env::temp_dir().join("eacc_rs_x_template_test.txt");
+    std::fs::write(
+        &path,
+        "{{ title | truncate(length=5) }} | {{ amount | currency(symbol=symbol) }} | {{ description_text }}",
+    )
+    .unwrap();
+    let settings = Settings {
+        x: XSettings {
+            job_template: Some(path.clone()),
+            ..Default::default()
+        },
+        ..Settings::default()
+    };
+
+    let templates = Templates::from_settings(&settings).unwrap();
+    let x = templates
+        .render(X_JOB, &job_context(&notification()).unwrap())
+        .unwrap();
+    assert_eq!(x, "Audit… | 12,500.25 $ARB | Review the vault contract");
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn overrides_replace_their_own_template() {
+    let path = std::env::temp_dir().join("eacc_rs_x_arbitrator_template_test.txt");
+    std::fs::write(&path, "Custom arbitrator").unwrap();
+    let settings = Settings {
+        x: XSettings {
+            arbitrator_template: Some(path.clone()),
+            ..Default::default()
+        },
+        ..Settings::default()
+    };
+
+    let templates = Templates::from_settings(&settings).unwrap();
+    assert_eq!(
+        templates
+            .render(X_ARBITRATOR, &tera::Context::new())
+            .unwrap(),
+        "Custom arbitrator"
+    );
+    // The others stay bundled
+    let context = job_context(&notification()).unwrap();
+    for name in [X_JOB, TELEGRAM_JOB] {
+        assert_eq!(
+            templates.render(name, &context).unwrap(),
+            Templates::bundled().render(name, &context).unwrap()
+        );
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn job_details_and_participants_are_available() {
+    let notification = JobNotification {
+        job: JobDetails {
+            creator: "0xabc".to_string(),
+            delivery_method: "ipfs".to_string(),
+            max_time: 90000,
+            tags: vec!["DO".to_string(), "rust".to_string()],
+            ..Default::default()
+        },
+        arbitrator_profile: Some(ArbitratorProfile {
+            name: "Judge <Dredd>".to_string(),
+            fee_percent: 2.5,
+            ..Default::default()
+        }),
+        ..notification()
+    };
+    let telegram = Templates::bundled()
+        .render(TELEGRAM_JOB, &job_context(&notification).unwrap())
+        .unwrap();
+
+    assert!(telegram.contains("<b>Delivery</b>: ipfs within 1 day and 1 hour"));
+    assert!(telegram.contains("<b>Posted by</b>: 0xabc"));
+    assert!(telegram.contains("<b>Arbitrator</b>: Judge &lt;Dredd&gt; (2.5% fee)"));
+    assert!(telegram.contains("<b>Tags</b>: DO, rust"));
+}
+
+#[test]
+fn directory_names_are_shown() {
+    let arbitrator = ArbitratorProfile::from(&MarketPlaceData::ArbitratorRegistered {
+        addr: address!("2222222222222222222222222222222222222222"),
+        pubkey: Bytes::new(),
+        name: "Judge <Dredd>".to_string(),
+        bio: "Fair".to_string(),
+        avatar: String::new(),
+        fee: 250,
+    });
+    let telegram = render_notification(
+        &Templates::bundled(),
+        &Notification::ArbitratorRegistered(arbitrator),
+    )
+    .unwrap();
+    assert!(telegram.starts_with("<b>New arbitrator available</b>"));
+    assert!(telegram.contains("<b>Judge &lt;Dredd&gt;</b> (2.5% fee)"));
+
+    let taken = JobStatusNotification {
+        event: JobEventType::Taken,
+        status: "Taken".to_string(),
+        job: JobNotification {
+            title: "Logo".to_string(),
+            worker_profile: Some(UserProfile {
+                name: "Carol".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    };
+    let x = Templates::bundled()
+        .render(X_STATUS, &status_context(&taken).unwrap())
+        .unwrap();
+    assert!(x.starts_with("Update on \"Logo\": Taken by Carol"));
+}
+
+#[test]
+fn creator_badge_is_shown() {
+    let mut job = JobNotification {
+        creator_profile: Some(UserProfile {
+            name: "Alice".to_string(),
+            ..Default::default()
+        }),
+        creator_rating: Some(UserRating::new(4.8, 12)),
+        ..notification()
+    };
+    let telegram = Templates::bundled()
+        .render(TELEGRAM_JOB, &job_context(&job).unwrap())
+        .unwrap();
+    assert!(telegram.contains("<b>Posted by</b>: Alice (★4.8 from 12 reviews)"));
+
+    // Nothing to show before the first review
+    job.creator_rating = Some(UserRating::new(0.0, 0));
+    let telegram = Templates::bundled()
+        .render(TELEGRAM_JOB, &job_context(&job).unwrap())
+        .unwrap();
+    assert!(telegram.contains("<b>Posted by</b>: Alice"));
+    assert!(!telegram.contains('★'));
+}
+
+#[test]
+fn tag_names_are_shown() {
+    let tags = vec!["NDG".to_string(), "furniture".to_string()];
+    let job = JobNotification {
+        tags: TagCatalogue::bundled().labels("42", &tags),
+        job: JobDetails {
+            tags,
+            ..Default::default()
+        },
+        ..notification()
+    };
+    let templates = Templates::bundled();
+    let context = job_context(&job).unwrap();
+    let telegram = templates.render(TELEGRAM_JOB, &context).unwrap();
+    assert!(telegram.contains("<b>Tags</b>: Non-Digital Goods, furniture"));
+    // Only MECE tags become hashtags
+    let x = templates.render(X_JOB, &context).unwrap();
+    assert!(x.contains("\n#NonDigitalGoods"));
+    assert!(!x.contains("#furniture"));
+}
+
+#[test]
+fn funding_is_shown() {
+    let mut job = JobNotification {
+        funding: Some(Funding {
+            status: FundingStatus::Held,
+            issue: None,
+        }),
+        ..notification()
+    };
+    let templates = Templates::bundled();
+    // Nothing was read on-chain for open jobs or unread escrows
+    let telegram = templates
+        .render(TELEGRAM_JOB, &job_context(&job).unwrap())
+        .unwrap();
+    assert!(!telegram.contains('✅'));
+    job.funding = Some(Funding {
+        status: FundingStatus::Unverified,
+        issue: None,
+    });
+    let telegram = templates
+        .render(TELEGRAM_JOB, &job_context(&job).unwrap())
+        .unwrap();
+    assert!(!telegram.contains('✅'));
+
+    job.funding = Some(Funding {
+        status: FundingStatus::Escrowed,
+        issue: None,
+    });
+    let telegram = templates
+        .render(TELEGRAM_JOB, &job_context(&job).unwrap())
+        .unwrap();
+    assert!(telegram.contains("✅ in escrow"));
+
+    job.funding = Some(Funding {
+        status: FundingStatus::Inconsistent,
+        issue: Some("taken job without escrow".to_string()),
+    });
+    let telegram = templates
+        .render(TELEGRAM_JOB, &job_context(&job).unwrap())
+        .unwrap();
+    assert!(telegram.contains("\n⚠️ <b>Funding issue</b>: taken job without escrow"));
+    assert!(!telegram.contains('✅'));
+}
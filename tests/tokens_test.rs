@@ -0,0 +1,110 @@
+use alloy::primitives::{address, Address, Bytes, U256};
+use alloy::providers::ProviderBuilder;
+use alloy::sol_types::SolValue;
+use alloy::transports::mock::Asserter;
+use eacc_rs::tokens::{decode_string_or_bytes32, TokenInfo, TokenRegistry, NATIVE_ETH_SENTINEL};
+
+#[test]
+fn bundled_registry_knows_arbitrum_tokens() {
+    let registry = TokenRegistry::bundled();
+
+    let usdc = registry
+        .get(address!("af88d065e77c8cC2239327C5EDb3A432268e5831"))
+        .expect("USDC is bundled");
+    assert_eq!(usdc.symbol, "USDC");
+    assert_eq!(usdc.decimals, 6);
+    assert!(usdc.logo_url.is_some());
+
+    assert!(registry.get(Address::repeat_byte(0x42)).is_none());
+}
+
+#[test]
+fn native_eth_sentinels_resolve_without_rpc() {
+    let registry = TokenRegistry::default();
+    for address in [Address::ZERO, NATIVE_ETH_SENTINEL] {
+        let eth = registry.get(address).unwrap();
+        assert_eq!(eth.symbol, "ETH");
+        assert_eq!(eth.decimals, 18);
+    }
+}
+
+#[test]
+fn inserted_tokens_are_cached() {
+    let registry = TokenRegistry::default();
+    let token = TokenInfo {
+        address: Address::repeat_byte(0x01),
+        symbol: "EACC".to_string(),
+        ..Default::default()
+    };
+    registry.insert(token.clone());
+    assert_eq!(registry.get(token.address), Some(token));
+}
+
+#[test]
+fn decodes_string_and_bytes32_symbols() {
+    assert_eq!(
+        decode_string_or_bytes32(&"WETH".to_string().abi_encode()),
+        Some("WETH".to_string())
+    );
+
+    // Raw `symbol()` return data: offset, length, padded bytes
+    let mut returned = vec![0u8; 96];
+    returned[31] = 0x20;
+    returned[63] = 4;
+    returned[64..68].copy_from_slice(b"USDT");
+    assert_eq!(
+        decode_string_or_bytes32(&returned),
+        Some("USDT".to_string())
+    );
+
+    let mut bytes32 = [0u8; 32];
+    bytes32[..3].copy_from_slice(b"MKR");
+    assert_eq!(decode_string_or_bytes32(&bytes32), Some("MKR".to_string()));
+
+    assert_eq!(decode_string_or_bytes32(&[0u8; 32]), None);
+    assert_eq!(decode_string_or_bytes32(&[1, 2, 3]), None);
+}
+
+#[tokio::test]
+async fn silent_tokens_get_a_cached_placeholder() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().on_mocked_client(asserter.clone());
+    let registry = TokenRegistry::default();
+    let address = address!("1234567890aBcDeF1234567890abcdef1234cDeF");
+
+    // symbol(), name() and decimals() all revert
+    for _ in 0..3 {
+        asserter.push_failure_msg("execution reverted");
+    }
+    let token = registry.resolve(provider.clone(), address).await;
+    assert_eq!(token.symbol, "0x1234…CdEf");
+    assert_eq!(token.name, token.symbol);
+    assert_eq!(token.decimals, 18);
+
+    // The placeholder is cached, the token isn't asked again
+    asserter.push_success(&"EACC".to_string().abi_encode());
+    assert_eq!(registry.resolve(provider, address).await, token);
+    assert_eq!(asserter.read_q().len(), 1);
+}
+
+#[tokio::test]
+async fn transport_errors_are_not_cached() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().on_mocked_client(asserter.clone());
+    let registry = TokenRegistry::default();
+    let address = address!("1234567890aBcDeF1234567890abcdef1234cDeF");
+
+    // The RPC doesn't answer, nothing is known about the token yet
+    let token = registry.resolve(provider.clone(), address).await;
+    assert_eq!(token.decimals, 18);
+    assert_eq!(registry.get(address), None);
+
+    // The next lookup reads it again
+    asserter.push_success(&Bytes::from("USDC".to_string().abi_encode()));
+    asserter.push_success(&Bytes::from("USD Coin".to_string().abi_encode()));
+    asserter.push_success(&Bytes::from(U256::from(6u8).abi_encode()));
+    let token = registry.resolve(provider, address).await;
+    assert_eq!(token.symbol, "USDC");
+    assert_eq!(token.decimals, 6);
+    assert_eq!(registry.get(address), Some(token));
+}
@@ -0,0 +1,187 @@
+use eacc_rs::x_client::{NewTweet, TweetMedia, TweetReply, XAuth, XClient};
+use eacc_rs::x_oauth::{
+    authorize_url, OAuth1Credentials, OAuth2App, OAuth2Session, OAuth2Token, Pkce,
+};
+use wiremock::matchers::{body_string_contains, header, header_regex, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn credentials() -> OAuth1Credentials {
+    // Example of the X OAuth 1.0a documentation
+    OAuth1Credentials {
+        consumer_key: "xvz1evFS4wEEPTGEFPHBog".to_string(),
+        consumer_secret: "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw".to_string(),
+        access_token: "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb".to_string(),
+        access_token_secret: "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE".to_string(),
+    }
+}
+
+#[test]
+fn test_oauth_signatures() {
+    let signature = credentials().signature(
+        "POST",
+        "https://api.twitter.com/1.1/statuses/update.json",
+        &[
+            ("include_entities", "true"),
+            (
+                "status",
+                "Hello Ladies + Gentlemen, a signed OAuth request!",
+            ),
+        ],
+        "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg",
+        1318622958,
+    );
+    assert_eq!(signature, "hCtSmYh+iHYCEqBWrE7C7hYmtUk=");
+
+    let header = credentials().authorization_with(
+        "POST",
+        "https://api.x.com/2/tweets",
+        &[],
+        "abc",
+        1318622958,
+    );
+    assert!(header.starts_with("OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\""));
+    assert!(header.contains("oauth_signature=\""));
+
+    // RFC 7636 appendix B
+    let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+    assert_eq!(
+        pkce.challenge,
+        "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+    );
+    let url = authorize_url("client", "http://127.0.0.1:3000/callback", "xyz", &pkce);
+    assert!(url.starts_with("https://x.com/i/oauth2/authorize?response_type=code&client_id=client"));
+    assert!(url.contains("scope=tweet.read+tweet.write+users.read+media.write+offline.access"));
+    assert!(url.contains("code_challenge_method=S256"));
+}
+
+#[tokio::test]
+async fn test_oauth1_upload_and_post() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/2/media/upload"))
+        .and(header_regex("authorization", "^OAuth .*oauth_signature="))
+        .and(body_string_contains("tweet_image"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({"data": {"id": "1880028106020515840"}})),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/2/tweets"))
+        .respond_with(
+            ResponseTemplate::new(201).set_body_json(
+                serde_json::json!({"data": {"id": "1445880548472328192", "text": ""}}),
+            ),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/2/tweets/1445880548472328192"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({"data": {"deleted": true}})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let x = XClient::new(
+        reqwest::Client::new(),
+        &server.uri(),
+        XAuth::OAuth1(credentials()),
+    );
+    let media_id = x
+        .upload_image(b"PNG".to_vec(), "card.png", "image/png")
+        .await
+        .unwrap();
+    let tweet_id = x
+        .create_tweet(&NewTweet {
+            text: "New job".to_string(),
+            media: Some(TweetMedia {
+                media_ids: vec![media_id],
+            }),
+            reply: Some(TweetReply {
+                in_reply_to_tweet_id: "1".to_string(),
+            }),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(tweet_id, 1445880548472328192);
+    x.delete_tweet(tweet_id).await.unwrap();
+
+    let tweet: serde_json::Value = server.received_requests().await.unwrap()[1]
+        .body_json()
+        .unwrap();
+    assert_eq!(
+        tweet,
+        serde_json::json!({
+            "text": "New job",
+            "media": {"media_ids": ["1880028106020515840"]},
+            "reply": {"in_reply_to_tweet_id": "1"}
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_oauth2_token_is_refreshed_and_saved() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/2/oauth2/token"))
+        .and(body_string_contains("grant_type=refresh_token"))
+        .and(body_string_contains("refresh_token=old-refresh"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "token_type": "bearer",
+            "access_token": "fresh",
+            "refresh_token": "new-refresh",
+            "expires_in": 7200
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/2/tweets"))
+        .and(header("authorization", "Bearer fresh"))
+        .respond_with(
+            ResponseTemplate::new(201).set_body_json(serde_json::json!({"data": {"id": "7"}})),
+        )
+        .mount(&server)
+        .await;
+
+    let token_path = std::env::temp_dir().join("eacc_rs_x_token_test.json");
+    let app = OAuth2App {
+        client: reqwest::Client::new(),
+        token_url: format!("{}/2/oauth2/token", server.uri()),
+        client_id: "client".to_string(),
+        client_secret: Some("secret".to_string()),
+    };
+    // Expired token
+    let session = OAuth2Session::new(
+        app,
+        &token_path,
+        OAuth2Token {
+            access_token: "stale".to_string(),
+            refresh_token: Some("old-refresh".to_string()),
+            expires_at: 0,
+        },
+    );
+    let x = XClient::new(
+        reqwest::Client::new(),
+        &server.uri(),
+        XAuth::OAuth2(session),
+    );
+    let tweet = NewTweet {
+        text: "New job".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(x.create_tweet(&tweet).await.unwrap(), 7);
+    // Still valid, no second refresh
+    assert_eq!(x.create_tweet(&tweet).await.unwrap(), 7);
+
+    // The rotated refresh token survives a restart
+    let saved = OAuth2Token::load(&token_path).unwrap();
+    assert_eq!(saved.access_token, "fresh");
+    assert_eq!(saved.refresh_token.as_deref(), Some("new-refresh"));
+    std::fs::remove_file(token_path).unwrap();
+}
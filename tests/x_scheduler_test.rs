@@ -0,0 +1,163 @@
+use alloy::primitives::U256;
+use eacc_rs::amount::TokenAmount;
+use eacc_rs::deliverables::DeliveryNotification;
+use eacc_rs::error::AppError;
+use eacc_rs::events::{JobEventType, JobStatusNotification};
+use eacc_rs::templates::Templates;
+use eacc_rs::x_api::render_summary;
+use eacc_rs::x_client::{
+    now_secs, NewTweet, RateLimit, XAuth, XClient, CREATE_TWEET_ENDPOINT, USER_24HOUR_LIMIT,
+};
+use eacc_rs::x_format::{weighted_len, TWEET_MAX_WEIGHT};
+use eacc_rs::x_oauth::OAuth1Credentials;
+use eacc_rs::x_scheduler::{XPost, XScheduleSettings, XScheduler, DAY_SECS};
+use eacc_rs::{JobNotification, Notification};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn job(id: usize) -> JobNotification {
+    JobNotification {
+        job_id: id.to_string(),
+        title: format!("Job number {}", id),
+        amount: TokenAmount::new(U256::from(25_000_000u64), 6),
+        symbol: "USDC".to_string(),
+        ..Default::default()
+    }
+}
+
+fn status(id: usize) -> Notification {
+    Notification::JobUpdated(JobStatusNotification {
+        event: JobEventType::Taken,
+        status: "Taken".to_string(),
+        job: job(id),
+    })
+}
+
+#[test]
+fn test_daily_budget_and_queue_limit() {
+    let mut scheduler = XScheduler::new(XScheduleSettings {
+        daily_budget: Some(3),
+        max_queue: 2,
+        summary_threshold: None,
+    });
+    scheduler.push(Notification::JobDelivered(DeliveryNotification {
+        job_id: "1".to_string(),
+        title: "Delivered".to_string(),
+        result_hash: String::new(),
+        preview: None,
+        job: Default::default(),
+    }));
+    assert!(scheduler.is_empty());
+
+    // The oldest notification is dropped when the queue is full
+    scheduler.push(Notification::JobPublished(job(1)));
+    scheduler.push(Notification::JobPublished(job(2)));
+    scheduler.push(status(2));
+    assert_eq!(scheduler.len(), 2);
+    assert!(matches!(scheduler.next_post(), Some(XPost::Job(job)) if job.job_id == "2"));
+
+    let now = 1_000_000;
+    assert_eq!(scheduler.ready_at(now, None), now);
+    scheduler.record_posts(1, now - 100);
+    scheduler.record_posts(2, now);
+    assert_eq!(scheduler.remaining_budget(now), Some(0));
+    // The budget frees up a day after the oldest post
+    assert_eq!(scheduler.ready_at(now, None), now - 100 + DAY_SECS);
+    assert_eq!(scheduler.remaining_budget(now - 100 + DAY_SECS), Some(1));
+    // X windows are waited for too
+    assert_eq!(
+        scheduler.ready_at(now + DAY_SECS, Some(now + DAY_SECS + 60)),
+        now + DAY_SECS + 60
+    );
+}
+
+#[test]
+fn test_bursts_are_collapsed_into_a_summary() {
+    let mut scheduler = XScheduler::new(XScheduleSettings {
+        summary_threshold: Some(3),
+        ..Default::default()
+    });
+    scheduler.push(Notification::JobPublished(job(1)));
+    scheduler.push(status(1));
+    scheduler.push(Notification::JobPublished(job(2)));
+    assert!(matches!(scheduler.next_post(), Some(XPost::Job(_))));
+    scheduler.push(Notification::JobPublished(job(1)));
+    scheduler.push(Notification::JobPublished(job(3)));
+
+    let Some(XPost::Summary(jobs)) = scheduler.next_post() else {
+        panic!("expected a summary");
+    };
+    assert_eq!(jobs.len(), 3);
+    assert!(matches!(scheduler.next_post(), Some(XPost::Status(_))));
+
+    // A summary that couldn't be sent is queued again as is
+    scheduler.requeue(XPost::Summary(jobs.clone()));
+    assert!(matches!(scheduler.next_post(), Some(XPost::Summary(again)) if again.len() == 3));
+
+    let text = render_summary(&Templates::bundled(), &jobs).unwrap();
+    assert!(text.starts_with("3 new jobs on EACC\n\n• Job number 2: 25 $USDC\n"));
+    assert!(text.ends_with("https://effectiveacceleration.ai/dashboard"));
+
+    // Jobs that don't fit are counted instead
+    let burst: Vec<JobNotification> = (1..=40).map(job).collect();
+    let text = render_summary(&Templates::bundled(), &burst).unwrap();
+    assert!(weighted_len(&text) <= TWEET_MAX_WEIGHT);
+    assert!(text.contains("more"));
+}
+
+#[tokio::test]
+async fn test_rate_limits_are_tracked() {
+    let server = MockServer::start().await;
+    let reset = now_secs() + 600;
+    Mock::given(method("POST"))
+        .and(path("/2/tweets"))
+        .respond_with(
+            ResponseTemplate::new(201)
+                .insert_header("x-rate-limit-limit", "100")
+                .insert_header("x-rate-limit-remaining", "99")
+                .insert_header("x-rate-limit-reset", reset.to_string().as_str())
+                .insert_header("x-user-limit-24hour-limit", "17")
+                .insert_header("x-user-limit-24hour-remaining", "0")
+                .insert_header("x-user-limit-24hour-reset", reset.to_string().as_str())
+                .set_body_json(serde_json::json!({"data": {"id": "7"}})),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/2/tweets"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+    let x = XClient::new(
+        reqwest::Client::new(),
+        &server.uri(),
+        XAuth::OAuth1(OAuth1Credentials {
+            consumer_key: "key".to_string(),
+            consumer_secret: "secret".to_string(),
+            access_token: "token".to_string(),
+            access_token_secret: "token_secret".to_string(),
+        }),
+    );
+    let tweet = NewTweet {
+        text: "Hello".to_string(),
+        ..Default::default()
+    };
+
+    assert_eq!(x.create_tweet(&tweet).await.unwrap(), 7);
+    assert_eq!(
+        x.rate_limit(CREATE_TWEET_ENDPOINT),
+        Some(RateLimit {
+            limit: 100,
+            remaining: 99,
+            reset
+        })
+    );
+    assert_eq!(x.rate_limit(USER_24HOUR_LIMIT).unwrap().remaining, 0);
+    assert_eq!(x.posting_blocked_until(now_secs()), Some(reset));
+
+    match x.create_tweet(&tweet).await {
+        Err(AppError::XRateLimited(until)) => assert_eq!(until, reset),
+        other => panic!("expected a rate limit error, got {:?}", other),
+    }
+}
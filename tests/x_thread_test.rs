@@ -0,0 +1,109 @@
+use eacc_rs::content::JobContent;
+use eacc_rs::x_api::send_x_thread;
+use eacc_rs::x_client::{XAuth, XClient};
+use eacc_rs::x_format::{split_thread, weighted_len, TWEET_MAX_WEIGHT};
+use eacc_rs::x_oauth::OAuth1Credentials;
+use eacc_rs::JobNotification;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+#[test]
+fn test_weighted_length() {
+    assert_eq!(weighted_len("hello"), 5);
+    assert_eq!(weighted_len("Ünïcödé ĉĥàř"), 12);
+    assert_eq!(weighted_len("日本語"), 6);
+    assert_eq!(weighted_len("🚀"), 2);
+    // Family emoji joined with ZWJ and a skin tone count once
+    assert_eq!(weighted_len("👨‍👩‍👧"), 2);
+    assert_eq!(weighted_len("👍🏽"), 2);
+    assert_eq!(
+        weighted_len("see https://example.com/a/very/long/path?query=1"),
+        27
+    );
+    // Trailing punctuation isn't part of the URL
+    assert_eq!(weighted_len("(https://example.com)."), 1 + 23 + 2);
+}
+
+#[test]
+fn test_split_thread() {
+    assert!(split_thread("  ", 5).is_empty());
+    // Short descriptions aren't numbered
+    assert_eq!(split_thread("Fix a bug", 5), vec!["Fix a bug"]);
+
+    let text = format!(
+        "First line\n{} https://example.com/brief.pdf {}",
+        "word ".repeat(120),
+        "語".repeat(200)
+    );
+    let posts = split_thread(&text, 10);
+    assert!(posts.len() > 2);
+    for (i, post) in posts.iter().enumerate() {
+        assert!(weighted_len(post) <= TWEET_MAX_WEIGHT, "{}", post);
+        assert!(post.ends_with(&format!("\n\n{}/{}", i + 1, posts.len())));
+    }
+    assert!(posts[0].starts_with("First line\nword word"));
+    assert!(posts
+        .iter()
+        .any(|post| post.contains(" https://example.com/brief.pdf")));
+
+    // Cut past max_posts
+    let posts = split_thread(&text, 2);
+    assert_eq!(posts.len(), 2);
+    assert!(posts[1].ends_with("…\n\n2/2"));
+}
+
+// Answers every post with the next id
+struct NextId;
+
+impl Respond for NextId {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: serde_json::Value = request.body_json().unwrap();
+        let previous: u64 = body["reply"]["in_reply_to_tweet_id"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        ResponseTemplate::new(201)
+            .set_body_json(serde_json::json!({"data": {"id": (previous + 1).to_string()}}))
+    }
+}
+
+#[tokio::test]
+async fn test_thread_replies_are_chained() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/2/tweets"))
+        .respond_with(NextId)
+        .mount(&server)
+        .await;
+    let x = XClient::new(
+        reqwest::Client::new(),
+        &server.uri(),
+        XAuth::OAuth1(OAuth1Credentials {
+            consumer_key: "key".to_string(),
+            consumer_secret: "secret".to_string(),
+            access_token: "token".to_string(),
+            access_token_secret: "token_secret".to_string(),
+        }),
+    );
+    let job = JobNotification {
+        job_id: "4".to_string(),
+        description: JobContent::parse(&"Translate the **docs** to French. ".repeat(30)),
+        ..Default::default()
+    };
+
+    let reply_ids = send_x_thread(&x, &job, 100, 3).await;
+    assert_eq!(reply_ids, vec![101, 102, 103]);
+    let texts: Vec<String> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let body: serde_json::Value = request.body_json().unwrap();
+            body["text"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert!(texts[0].starts_with("Translate the docs to French."));
+    assert!(texts[2].ends_with("3/3"));
+}